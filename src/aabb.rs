@@ -0,0 +1,82 @@
+use crate::geom::Vec3;
+use crate::ray::Ray;
+
+/// Ограничивающий параллелепипед, выровненный по осям координат.
+#[derive(Copy, Clone, Debug)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Создает параллелепипед по двум противоположным углам.
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb { min, max }
+    }
+
+    /// Создает пустой параллелепипед, который при объединении не меняет другой параллелепипед.
+    pub fn empty() -> Self {
+        Aabb {
+            min: Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    /// Центр параллелепипеда.
+    pub fn centroid(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    /// Площадь поверхности параллелепипеда. Для пустого параллелепипеда равна нулю.
+    pub fn surface_area(&self) -> f32 {
+        let d = self.max - self.min;
+        if d[0] < 0.0 || d[1] < 0.0 || d[2] < 0.0 {
+            return 0.0;
+        }
+
+        2.0 * (d[0] * d[1] + d[1] * d[2] + d[2] * d[0])
+    }
+
+    /// Проверяет пересечение луча с параллелепипедом на отрезке `[t_min; t_max]`.
+    ///
+    /// Используется метод плит (slab method) по каждой из трех осей.
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        let origin = ray.origin();
+        let direction = ray.direction();
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+
+        for a in 0..3 {
+            let inv_d = 1.0 / direction[a];
+            let mut t0 = (self.min[a] - origin[a]) * inv_d;
+            let mut t1 = (self.max[a] - origin[a]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max <= t_min {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Возвращает параллелепипед, охватывающий оба переданных параллелепипеда.
+pub fn surrounding_box(a: Aabb, b: Aabb) -> Aabb {
+    let min = Vec3::new(
+        a.min[0].min(b.min[0]),
+        a.min[1].min(b.min[1]),
+        a.min[2].min(b.min[2]),
+    );
+    let max = Vec3::new(
+        a.max[0].max(b.max[0]),
+        a.max[1].max(b.max[1]),
+        a.max[2].max(b.max[2]),
+    );
+
+    Aabb::new(min, max)
+}
@@ -1,19 +1,29 @@
-use crate::geom::{dot, Vec3};
+use crate::aabb::{surrounding_box, Aabb};
+use crate::geom::{dot, Onb, Vec3};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::materials::Material;
-use std::rc::Rc;
+use crate::utils::random_unit_vector;
+use crate::{Point, World};
+use std::f32::consts::PI;
+use std::sync::Arc;
 
 /// Параметры попадания луча в объект.
 pub struct HitRecord {
     pub t: f32,
     pub point: Vec3,
     pub normal: Vec3,
-    pub(crate) material: Rc<dyn Material>,
+    /// Координаты точки попадания на развертке поверхности.
+    pub u: f32,
+    pub v: f32,
+    pub(crate) material: Arc<dyn Material>,
     pub front_face: bool,
+    /// Цвет вершин сетки, интерполированный в точке попадания, если вершинам заданы цвета.
+    pub vertex_color: Option<Vec3>,
 }
 
 impl HitRecord {
-    fn with_front_face(t: f32, point: Vec3, material: Rc<dyn Material>, outward_normal: Vec3, ray: &Ray) -> Self {
+    pub(crate) fn with_front_face(t: f32, point: Vec3, material: Arc<dyn Material>, outward_normal: Vec3, ray: &Ray, u: f32, v: f32) -> Self {
         let front_face = dot(ray.direction(), outward_normal) < 0.0;
         let normal = if front_face {
             outward_normal
@@ -25,26 +35,76 @@ impl HitRecord {
             t,
             point,
             normal,
+            u,
+            v,
             material,
             front_face,
+            vertex_color: None,
         }
     }
 }
 
 /// Типаж для реализации попадания луча в объект.
-pub trait Hittable {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
+pub trait Hittable: Send + Sync {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut dyn Sampler) -> Option<HitRecord>;
+
+    /// Ограничивающий параллелепипед объекта на интервале времени `[time0; time1]`.
+    ///
+    /// Возвращает `None` для объектов, которые нельзя ограничить (например, бесконечная плоскость).
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb>;
+
+    /// Плотность по телесному углу, с которой `random` выбирает направление `direction` из точки `origin`.
+    ///
+    /// По умолчанию объект не умеет выбирать направления на себя и плотность равна нулю.
+    fn pdf_value(&self, _origin: Vec3, _direction: Vec3) -> f32 {
+        0.0
+    }
+
+    /// Случайное направление из точки `origin` на объект; длина вектора не важна.
+    fn random(&self, _origin: Vec3, _sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+}
+
+/// Общий объект, например источник света, который входит и в сцену, и в список источников.
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        self.as_ref().hit(ray, t_min, t_max, sampler)
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        self.as_ref().bounding_box(time0, time1)
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        self.as_ref().pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.as_ref().random(origin, sampler)
+    }
+}
+
+/// Вычисляет координаты `(u, v)` точки `p` на единичной сфере с центром в начале координат.
+///
+/// `u` - угол вокруг оси `Y` от `X = -1`, `v` - угол от `Y = -1` до `Y = +1`, оба приведены к `[0; 1]`.
+pub fn sphere_uv(p: Vec3) -> (f32, f32) {
+    let theta = (-p.y()).acos();
+    let phi = (-p.z()).atan2(p.x()) + PI;
+
+    (phi / (2.0 * PI), theta / PI)
 }
 
 /// Описывает положение, радиус и материал сферы.
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f32,
-    pub material: Rc<dyn Material>,
+    pub material: Arc<dyn Material>,
 }
 
-impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+impl Sphere {
+    /// Ближайший параметр `t` из `(t_min; t_max)`, при котором луч пересекает сферу.
+    fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<f32> {
         let oc = ray.origin() - self.center;
         let a = ray.direction().length_squared();
         let half_b = dot(oc, ray.direction());
@@ -55,22 +115,467 @@ impl Hittable for Sphere {
             // first root
             let t = (-half_b - discriminant.sqrt()) / a;
             if t_min < t && t < t_max {
-                let point = ray.at(t);
-                let outward_normal = (point - self.center) / self.radius;
-
-                return Some(HitRecord::with_front_face(t, point, self.material.clone(), outward_normal, ray));
+                return Some(t);
             }
 
             // second root
             let t = (-half_b + discriminant.sqrt()) / a;
             if t_min < t && t < t_max {
-                let point = ray.at(t);
-                let outward_normal = (point - self.center) / self.radius;
-
-                return Some(HitRecord::with_front_face(t, point, self.material.clone(), outward_normal, ray));
+                return Some(t);
             }
         }
 
         None
     }
+
+    /// Лежит ли точка `origin` внутри сферы или на ее поверхности.
+    fn contains(&self, origin: Vec3) -> bool {
+        (origin - self.center).length_squared() <= self.radius * self.radius
+    }
+
+    /// Косинус половины угла, под которым сфера видна из точки `origin`.
+    fn cos_theta_max(&self, origin: Vec3) -> f32 {
+        let distance_squared = (self.center - origin).length_squared();
+        (1.0 - self.radius * self.radius / distance_squared).max(0.0).sqrt()
+    }
+}
+
+impl Hittable for Sphere {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, _sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        let t = self.intersect(ray, t_min, t_max)?;
+        let point = ray.at(t);
+        let outward_normal = (point - self.center) / self.radius;
+        let (u, v) = sphere_uv(outward_normal);
+
+        Some(HitRecord::with_front_face(t, point, self.material.clone(), outward_normal, ray, u, v))
+    }
+
+    fn bounding_box(&self, _: f32, _: f32) -> Option<Aabb> {
+        let r = self.radius.abs();
+        let radius = Vec3::new(r, r, r);
+
+        Some(Aabb::new(self.center - radius, self.center + radius))
+    }
+
+    /// Направления выбираются равномерно внутри конуса, под которым видна сфера,
+    /// а из точки внутри сферы - равномерно по всем направлениям.
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        if self.contains(origin) {
+            return 1.0 / (4.0 * PI);
+        }
+        if self.intersect(&Ray::new(origin, direction, 0.0), 0.001, f32::MAX).is_none() {
+            return 0.0;
+        }
+
+        let solid_angle = 2.0 * PI * (1.0 - self.cos_theta_max(origin));
+        1.0 / solid_angle
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        if self.contains(origin) {
+            return random_unit_vector(sampler);
+        }
+
+        let (r1, r2) = sampler.next_2d();
+        let z = 1.0 + r2 * (self.cos_theta_max(origin) - 1.0);
+        let phi = 2.0 * PI * r1;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+
+        Onb::from_w(self.center - origin).local(Vec3::new(r * phi.cos(), r * phi.sin(), z))
+    }
+}
+
+/// Сфера, центр которой равномерно движется из `center0` в момент `time0` в `center1` в момент `time1`.
+pub struct MovingSphere {
+    pub center0: Vec3,
+    pub center1: Vec3,
+    pub time0: f32,
+    pub time1: f32,
+    pub radius: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl MovingSphere {
+    /// Положение центра сферы в момент времени `time`.
+    pub fn center(&self, time: f32) -> Vec3 {
+        if self.time1 == self.time0 {
+            return self.center0;
+        }
+
+        self.center0 + ((time - self.time0) / (self.time1 - self.time0)) * (self.center1 - self.center0)
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, _sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        let center = self.center(ray.time());
+        let oc = ray.origin() - center;
+        let a = ray.direction().length_squared();
+        let half_b = dot(oc, ray.direction());
+        let c = oc.length_squared() - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let sqrtd = discriminant.sqrt();
+        let mut root = (-half_b - sqrtd) / a;
+        if root <= t_min || t_max <= root {
+            root = (-half_b + sqrtd) / a;
+            if root <= t_min || t_max <= root {
+                return None;
+            }
+        }
+
+        let point = ray.at(root);
+        let outward_normal = (point - center) / self.radius;
+        let (u, v) = sphere_uv(outward_normal);
+
+        Some(HitRecord::with_front_face(root, point, self.material.clone(), outward_normal, ray, u, v))
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        let r = self.radius.abs();
+        let radius = Vec3::new(r, r, r);
+        let center0 = self.center(time0);
+        let center1 = self.center(time1);
+
+        Some(surrounding_box(
+            Aabb::new(center0 - radius, center0 + radius),
+            Aabb::new(center1 - radius, center1 + radius),
+        ))
+    }
+}
+
+/// Толщина ограничивающего параллелепипеда плоских фигур вдоль нормали.
+const THIN_BOX: f32 = 0.0001;
+
+/// Параметр `t` пересечения луча с прямоугольником, перпендикулярным оси `axis` и лежащим в плоскости `axis = k`.
+///
+/// Оси `a` и `b` лежат в плоскости прямоугольника, стороны которого заданы отрезками `[a0; a1]` и `[b0; b1]`.
+fn aa_rect_intersect(
+    ray: &Ray,
+    t_min: f32,
+    t_max: f32,
+    (a, b, axis): (usize, usize, usize),
+    (a0, a1, b0, b1, k): (f32, f32, f32, f32, f32),
+) -> Option<f32> {
+    let t = (k - ray.origin()[axis]) / ray.direction()[axis];
+    if t.is_nan() || t < t_min || t > t_max {
+        return None;
+    }
+
+    let point = ray.at(t);
+    if point[a] < a0 || point[a] > a1 || point[b] < b0 || point[b] > b1 {
+        return None;
+    }
+
+    Some(t)
+}
+
+/// Параметры попадания луча в прямоугольник, см. [`aa_rect_intersect`].
+fn aa_rect_hit(
+    ray: &Ray,
+    t_min: f32,
+    t_max: f32,
+    (a, b, axis): (usize, usize, usize),
+    (a0, a1, b0, b1, k): (f32, f32, f32, f32, f32),
+    material: &Arc<dyn Material>,
+) -> Option<HitRecord> {
+    let t = aa_rect_intersect(ray, t_min, t_max, (a, b, axis), (a0, a1, b0, b1, k))?;
+    let point = ray.at(t);
+
+    let u = (point[a] - a0) / (a1 - a0);
+    let v = (point[b] - b0) / (b1 - b0);
+    let mut outward_normal = Vec3::default();
+    outward_normal[axis] = 1.0;
+
+    Some(HitRecord::with_front_face(t, point, material.clone(), outward_normal, ray, u, v))
+}
+
+/// Плотность выбора направления `direction` из точки `origin` на прямоугольник по телесному углу.
+///
+/// Точки прямоугольника выбираются равномерно по площади, поэтому плотность - квадрат расстояния,
+/// деленный на площадь и косинус угла между направлением и нормалью.
+fn aa_rect_pdf_value(
+    origin: Vec3,
+    direction: Vec3,
+    (a, b, axis): (usize, usize, usize),
+    (a0, a1, b0, b1, k): (f32, f32, f32, f32, f32),
+) -> f32 {
+    let ray = Ray::new(origin, direction, 0.0);
+    let t = match aa_rect_intersect(&ray, 0.001, f32::MAX, (a, b, axis), (a0, a1, b0, b1, k)) {
+        Some(t) => t,
+        None => return 0.0,
+    };
+
+    let area = (a1 - a0) * (b1 - b0);
+    let distance_squared = t * t * direction.length_squared();
+    let cosine = (direction[axis] / direction.length()).abs();
+
+    distance_squared / (cosine * area)
+}
+
+/// Направление из точки `origin` на точку прямоугольника, выбранную равномерно по площади.
+fn aa_rect_random(
+    origin: Vec3,
+    (a, b, axis): (usize, usize, usize),
+    (a0, a1, b0, b1, k): (f32, f32, f32, f32, f32),
+    sampler: &mut dyn Sampler,
+) -> Vec3 {
+    let (s, t) = sampler.next_2d();
+    let mut point = Vec3::default();
+    point[a] = a0 + s * (a1 - a0);
+    point[b] = b0 + t * (b1 - b0);
+    point[axis] = k;
+
+    point - origin
+}
+
+/// Прямоугольник `[x0; x1] x [y0; y1]` в плоскости `z = k`.
+pub struct XYRect {
+    pub x0: f32,
+    pub x1: f32,
+    pub y0: f32,
+    pub y1: f32,
+    pub k: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl Hittable for XYRect {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, _sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        aa_rect_hit(ray, t_min, t_max, (0, 1, 2), (self.x0, self.x1, self.y0, self.y1, self.k), &self.material)
+    }
+
+    fn bounding_box(&self, _: f32, _: f32) -> Option<Aabb> {
+        Some(Aabb::new(
+            Vec3::new(self.x0, self.y0, self.k - THIN_BOX),
+            Vec3::new(self.x1, self.y1, self.k + THIN_BOX),
+        ))
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        aa_rect_pdf_value(origin, direction, (0, 1, 2), (self.x0, self.x1, self.y0, self.y1, self.k))
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        aa_rect_random(origin, (0, 1, 2), (self.x0, self.x1, self.y0, self.y1, self.k), sampler)
+    }
+}
+
+/// Прямоугольник `[x0; x1] x [z0; z1]` в плоскости `y = k`.
+pub struct XZRect {
+    pub x0: f32,
+    pub x1: f32,
+    pub z0: f32,
+    pub z1: f32,
+    pub k: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl Hittable for XZRect {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, _sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        aa_rect_hit(ray, t_min, t_max, (0, 2, 1), (self.x0, self.x1, self.z0, self.z1, self.k), &self.material)
+    }
+
+    fn bounding_box(&self, _: f32, _: f32) -> Option<Aabb> {
+        Some(Aabb::new(
+            Vec3::new(self.x0, self.k - THIN_BOX, self.z0),
+            Vec3::new(self.x1, self.k + THIN_BOX, self.z1),
+        ))
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        aa_rect_pdf_value(origin, direction, (0, 2, 1), (self.x0, self.x1, self.z0, self.z1, self.k))
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        aa_rect_random(origin, (0, 2, 1), (self.x0, self.x1, self.z0, self.z1, self.k), sampler)
+    }
+}
+
+/// Прямоугольник `[y0; y1] x [z0; z1]` в плоскости `x = k`.
+pub struct YZRect {
+    pub y0: f32,
+    pub y1: f32,
+    pub z0: f32,
+    pub z1: f32,
+    pub k: f32,
+    pub material: Arc<dyn Material>,
+}
+
+impl Hittable for YZRect {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, _sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        aa_rect_hit(ray, t_min, t_max, (1, 2, 0), (self.y0, self.y1, self.z0, self.z1, self.k), &self.material)
+    }
+
+    fn bounding_box(&self, _: f32, _: f32) -> Option<Aabb> {
+        Some(Aabb::new(
+            Vec3::new(self.k - THIN_BOX, self.y0, self.z0),
+            Vec3::new(self.k + THIN_BOX, self.y1, self.z1),
+        ))
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        aa_rect_pdf_value(origin, direction, (1, 2, 0), (self.y0, self.y1, self.z0, self.z1, self.k))
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        aa_rect_random(origin, (1, 2, 0), (self.y0, self.y1, self.z0, self.z1, self.k), sampler)
+    }
+}
+
+/// Прямоугольный параллелепипед, выровненный по осям, из шести прямоугольников.
+pub struct BoxShape {
+    min: Vec3,
+    max: Vec3,
+    sides: World,
+}
+
+impl BoxShape {
+    /// Создает параллелепипед по двум противоположным углам `p0` и `p1`.
+    pub fn new(p0: Vec3, p1: Vec3, material: Arc<dyn Material>) -> Self {
+        let sides: Vec<Box<dyn Hittable>> = vec![
+            Box::new(XYRect{x0: p0.x(), x1: p1.x(), y0: p0.y(), y1: p1.y(), k: p1.z(), material: material.clone()}),
+            Box::new(XYRect{x0: p0.x(), x1: p1.x(), y0: p0.y(), y1: p1.y(), k: p0.z(), material: material.clone()}),
+            Box::new(XZRect{x0: p0.x(), x1: p1.x(), z0: p0.z(), z1: p1.z(), k: p1.y(), material: material.clone()}),
+            Box::new(XZRect{x0: p0.x(), x1: p1.x(), z0: p0.z(), z1: p1.z(), k: p0.y(), material: material.clone()}),
+            Box::new(YZRect{y0: p0.y(), y1: p1.y(), z0: p0.z(), z1: p1.z(), k: p1.x(), material: material.clone()}),
+            Box::new(YZRect{y0: p0.y(), y1: p1.y(), z0: p0.z(), z1: p1.z(), k: p0.x(), material}),
+        ];
+
+        BoxShape { min: p0, max: p1, sides: World(sides) }
+    }
+}
+
+impl Hittable for BoxShape {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        self.sides.hit(ray, t_min, t_max, sampler)
+    }
+
+    fn bounding_box(&self, _: f32, _: f32) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sphere_uv, BoxShape, Hittable, MovingSphere, XYRect, XZRect, YZRect};
+    use crate::geom::Vec3;
+    use crate::materials::Lambert;
+    use crate::ray::Ray;
+    use crate::sampler::IndependentSampler;
+    use std::sync::Arc;
+
+    #[test]
+    fn check_moving_sphere() {
+        let sphere = MovingSphere {
+            center0: Vec3::new(0.0, 0.0, -5.0),
+            center1: Vec3::new(4.0, 0.0, -5.0),
+            time0: 1.0,
+            time1: 3.0,
+            radius: 1.0,
+            material: Arc::new(Lambert::with_color(Vec3::new(0.5, 0.5, 0.5))),
+        };
+        assert_eq!(sphere.center0, sphere.center(1.0));
+        assert_eq!(sphere.center1, sphere.center(3.0));
+        assert_eq!(Vec3::new(2.0, 0.0, -5.0), sphere.center(2.0));
+
+        // Луч вдоль оси `Z` попадает в сферу только в начале интервала, пока она не ушла в сторону.
+        let sampler = &mut IndependentSampler::new(0);
+        let mut hit = |time: f32, x: f32| {
+            let ray = Ray::new(Vec3::new(x, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), time);
+            sphere.hit(&ray, 0.001, f32::MAX, sampler).map(|rec| rec.t)
+        };
+        assert_eq!(Some(4.0), hit(1.0, 0.0));
+        assert_eq!(None, hit(3.0, 0.0));
+        assert_eq!(Some(4.0), hit(3.0, 4.0));
+
+        let bbox = sphere.bounding_box(1.0, 3.0).unwrap();
+        assert_eq!(Vec3::new(-1.0, -1.0, -6.0), bbox.min);
+        assert_eq!(Vec3::new(5.0, 1.0, -4.0), bbox.max);
+    }
+
+    #[test]
+    fn check_sphere_uv() {
+        let uv = |x: f32, y: f32, z: f32| sphere_uv(Vec3::new(x, y, z));
+        let close = |(u, v): (f32, f32), (eu, ev): (f32, f32)| (u - eu).abs() < 1e-4 && (v - ev).abs() < 1e-4;
+
+        // Полюса: `v` равно 0 внизу и 1 вверху.
+        assert_eq!(0.0, uv(0.0, -1.0, 0.0).1);
+        assert_eq!(1.0, uv(0.0, 1.0, 0.0).1);
+
+        assert!(close(uv(1.0, 0.0, 0.0), (0.5, 0.5)));
+        assert!(close(uv(0.0, 0.0, 1.0), (0.25, 0.5)));
+        assert!(close(uv(0.0, 0.0, -1.0), (0.75, 0.5)));
+
+        // Шов проходит через `X = -1`: по разные стороны от него `u` близко к 0 и к 1.
+        let (before, _) = uv(-1.0, 0.0, 0.001);
+        let (after, _) = uv(-1.0, 0.0, -0.001);
+        assert!((0.0..0.001).contains(&before));
+        assert!(after > 0.999 && after <= 1.0);
+    }
+
+    #[test]
+    fn check_rect_edges() {
+        let material = Arc::new(Lambert::with_color(Vec3::new(0.5, 0.5, 0.5)));
+        let rects: Vec<(Box<dyn Hittable>, usize, usize, usize)> = vec![
+            (Box::new(XYRect { x0: 1.0, x1: 3.0, y0: 0.0, y1: 2.0, k: -2.0, material: material.clone() }), 0, 1, 2),
+            (Box::new(XZRect { x0: 1.0, x1: 3.0, z0: 0.0, z1: 2.0, k: -2.0, material: material.clone() }), 0, 2, 1),
+            (Box::new(YZRect { y0: 1.0, y1: 3.0, z0: 0.0, z1: 2.0, k: -2.0, material }), 1, 2, 0),
+        ];
+        let sampler = &mut IndependentSampler::new(0);
+
+        for (rect, a, b, axis) in &rects {
+            // Луч против нормали из точки `(a, b)`, поднятой на 3 над плоскостью прямоугольника.
+            let mut hit = |pa: f32, pb: f32| {
+                let mut origin = Vec3::default();
+                origin[*a] = pa;
+                origin[*b] = pb;
+                origin[*axis] = 1.0;
+                let mut direction = Vec3::default();
+                direction[*axis] = -1.0;
+                rect.hit(&Ray::new(origin, direction, 0.0), 0.001, f32::MAX, sampler)
+            };
+
+            for &(pa, pb, u, v) in &[(1.0, 0.0, 0.0, 0.0), (3.0, 2.0, 1.0, 1.0), (2.0, 0.0, 0.5, 0.0), (1.0, 1.5, 0.0, 0.75)] {
+                let rec = hit(pa, pb).unwrap();
+                assert_eq!((3.0, u, v), (rec.t, rec.u, rec.v));
+                assert!(rec.front_face);
+                assert_eq!(1.0, rec.normal[*axis]);
+            }
+            assert!(hit(0.99, 1.0).is_none());
+            assert!(hit(2.0, 2.01).is_none());
+
+            // Луч, параллельный плоскости, прямоугольник не задевает.
+            let mut direction = Vec3::default();
+            direction[*a] = 1.0;
+            assert!(rect.hit(&Ray::new(Vec3::default(), direction, 0.0), 0.001, f32::MAX, sampler).is_none());
+        }
+    }
+
+    #[test]
+    fn check_box_faces() {
+        let shape = BoxShape::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 3.0), Arc::new(Lambert::with_color(Vec3::new(0.5, 0.5, 0.5))));
+        let center = Vec3::new(0.5, 1.0, 1.5);
+        let sampler = &mut IndependentSampler::new(0);
+
+        for axis in 0..3 {
+            for &side in &[-1.0, 1.0] {
+                let mut outward = Vec3::default();
+                outward[axis] = side;
+                let rec = shape.hit(&Ray::new(center + 10.0 * outward, -outward, 0.0), 0.001, f32::MAX, sampler).unwrap();
+                assert_eq!(outward, rec.normal);
+                let face = if side > 0.0 { shape.max[axis] } else { shape.min[axis] };
+                assert!((rec.point[axis] - face).abs() < 1e-5);
+
+                // Изнутри луч попадает в ту же грань, и нормаль по-прежнему направлена навстречу лучу.
+                let rec = shape.hit(&Ray::new(center, outward, 0.0), 0.001, f32::MAX, sampler).unwrap();
+                assert!((rec.point[axis] - face).abs() < 1e-5);
+                assert_eq!(-outward, rec.normal);
+            }
+        }
+        assert!(shape.hit(&Ray::new(Vec3::new(5.0, 5.0, 5.0), Vec3::new(1.0, 0.0, 0.0), 0.0), 0.001, f32::MAX, sampler).is_none());
+    }
 }
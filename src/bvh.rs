@@ -0,0 +1,434 @@
+use crate::aabb::{surrounding_box, Aabb};
+use crate::bodies::{HitRecord, Hittable};
+use crate::geom::Vec3;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::World;
+use std::fmt::{Display, Formatter};
+use std::fmt;
+
+/// Число корзин, на которые делится интервал центроидов при поиске разбиения.
+const SAH_BINS: usize = 12;
+/// Стоимость обхода узла относительно стоимости проверки пересечения с объектом.
+const TRAVERSAL_COST: f32 = 0.125;
+/// Стоимость проверки пересечения луча с одним объектом.
+const INTERSECTION_COST: f32 = 1.0;
+/// Максимальная глубина стека при обходе иерархии.
+const MAX_DEPTH: usize = 64;
+
+/// Статистика построения иерархии.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BvhStats {
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub depth: usize,
+    pub object_count: usize,
+    /// Ожидаемая стоимость трассировки луча по эвристике площади поверхности.
+    ///
+    /// Для линейного перебора [`World`] стоимость равна числу объектов.
+    ///
+    /// [`World`]: ../struct.World.html
+    pub sah_cost: f32,
+}
+
+impl Display for BvhStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} objects, {} nodes ({} leaves), depth {}, SAH cost {:.2} (linear {})",
+            self.object_count, self.node_count, self.leaf_count, self.depth, self.sah_cost, self.object_count
+        )
+    }
+}
+
+/// Узел плоской иерархии.
+///
+/// Левый потомок внутреннего узла всегда следует сразу за ним,
+/// правый потомок находится по индексу `offset`.
+/// У листа `offset` указывает на первый объект, `count` - число объектов.
+#[derive(Copy, Clone)]
+struct LinearNode {
+    bbox: Aabb,
+    offset: usize,
+    count: usize,
+    axis: usize,
+}
+
+/// Объект на этапе построения: индекс в исходном массиве, параллелепипед и его центр.
+#[derive(Copy, Clone)]
+struct BuildItem {
+    index: usize,
+    bbox: Aabb,
+    centroid: Vec3,
+}
+
+#[derive(Copy, Clone)]
+struct Bin {
+    bbox: Aabb,
+    count: usize,
+}
+
+/// Иерархия ограничивающих объемов, построенная по эвристике площади поверхности (SAH).
+///
+/// Узлы хранятся в одном массиве в порядке обхода в глубину, а объекты переупорядочены так,
+/// что каждый лист ссылается на непрерывный отрезок массива объектов.
+pub struct SahBvh {
+    nodes: Vec<LinearNode>,
+    objects: Vec<Box<dyn Hittable>>,
+    stats: BvhStats,
+}
+
+impl SahBvh {
+    /// Строит иерархию с листьями не более чем из четырех объектов.
+    pub fn new(world: World, time0: f32, time1: f32) -> Self {
+        Self::with_leaf_size(world, time0, time1, 4)
+    }
+
+    /// Строит иерархию с листьями не более чем из `max_leaf_size` объектов.
+    ///
+    /// Паникует, если какой-либо объект не имеет ограничивающего параллелепипеда.
+    pub fn with_leaf_size(world: World, time0: f32, time1: f32, max_leaf_size: usize) -> Self {
+        let max_leaf_size = max_leaf_size.max(1);
+        let mut items: Vec<BuildItem> = world
+            .0
+            .iter()
+            .enumerate()
+            .map(|(index, object)| {
+                let bbox = object
+                    .bounding_box(time0, time1)
+                    .expect("No bounding box in SahBvh constructor");
+                BuildItem { index, bbox, centroid: bbox.centroid() }
+            })
+            .collect();
+
+        let mut bvh = SahBvh {
+            nodes: Vec::with_capacity(2 * items.len()),
+            objects: Vec::with_capacity(items.len()),
+            stats: BvhStats { object_count: items.len(), ..BvhStats::default() },
+        };
+
+        if !items.is_empty() {
+            bvh.build(&mut items, 0, max_leaf_size, 1);
+        }
+
+        // Переупорядочиваем объекты в порядке листьев.
+        let mut slots: Vec<Option<Box<dyn Hittable>>> = world.0.into_iter().map(Some).collect();
+        bvh.objects = items
+            .iter()
+            .map(|item| slots[item.index].take().unwrap())
+            .collect();
+
+        bvh.stats.node_count = bvh.nodes.len();
+        bvh.stats.sah_cost = bvh.cost();
+
+        bvh
+    }
+
+    /// Статистика построения.
+    pub fn stats(&self) -> BvhStats {
+        self.stats
+    }
+
+    /// Рекурсивно строит поддерево над `items`, которые начинаются с `first` в итоговом массиве объектов.
+    /// Возвращает индекс корня поддерева.
+    fn build(&mut self, items: &mut [BuildItem], first: usize, max_leaf_size: usize, depth: usize) -> usize {
+        self.stats.depth = self.stats.depth.max(depth);
+
+        let bbox = items.iter().fold(Aabb::empty(), |acc, item| surrounding_box(acc, item.bbox));
+        let node = self.nodes.len();
+        self.nodes.push(LinearNode { bbox, offset: first, count: items.len(), axis: 0 });
+
+        let count = items.len();
+        if count == 1 || depth >= MAX_DEPTH {
+            self.stats.leaf_count += 1;
+            return node;
+        }
+
+        let centroids = items
+            .iter()
+            .fold(Aabb::empty(), |acc, item| surrounding_box(acc, Aabb::new(item.centroid, item.centroid)));
+        let split = Self::find_split(items, &centroids, bbox.surface_area());
+
+        let (axis, bin, cost) = match split {
+            Some(split) => split,
+            None => {
+                // Все центры совпадают - разделить объекты невозможно.
+                self.stats.leaf_count += 1;
+                return node;
+            }
+        };
+
+        let leaf_cost = INTERSECTION_COST * count as f32;
+        if count <= max_leaf_size && leaf_cost <= cost {
+            self.stats.leaf_count += 1;
+            return node;
+        }
+
+        let mid = partition(items, |item| bin_index(item.centroid, &centroids, axis) <= bin);
+        let (left, right) = items.split_at_mut(mid);
+
+        self.build(left, first, max_leaf_size, depth + 1);
+        let right_node = self.build(right, first + mid, max_leaf_size, depth + 1);
+
+        self.nodes[node] = LinearNode { bbox, offset: right_node, count: 0, axis };
+        node
+    }
+
+    /// Ищет наилучшее разбиение по всем осям.
+    ///
+    /// Возвращает ось, номер последней корзины левой части и стоимость разбиения.
+    fn find_split(items: &[BuildItem], centroids: &Aabb, area: f32) -> Option<(usize, usize, f32)> {
+        let mut best: Option<(usize, usize, f32)> = None;
+
+        for axis in 0..3 {
+            if centroids.max[axis] <= centroids.min[axis] {
+                continue;
+            }
+
+            let mut bins = [Bin { bbox: Aabb::empty(), count: 0 }; SAH_BINS];
+            for item in items {
+                let b = bin_index(item.centroid, centroids, axis);
+                bins[b].count += 1;
+                bins[b].bbox = surrounding_box(bins[b].bbox, item.bbox);
+            }
+
+            // Площади и количества объектов справа от каждой границы.
+            let mut right_area = [0.0; SAH_BINS];
+            let mut right_count = [0; SAH_BINS];
+            let mut acc = Bin { bbox: Aabb::empty(), count: 0 };
+            for b in (1..SAH_BINS).rev() {
+                acc.bbox = surrounding_box(acc.bbox, bins[b].bbox);
+                acc.count += bins[b].count;
+                right_area[b] = acc.bbox.surface_area();
+                right_count[b] = acc.count;
+            }
+
+            let mut left = Bin { bbox: Aabb::empty(), count: 0 };
+            for b in 0..SAH_BINS - 1 {
+                left.bbox = surrounding_box(left.bbox, bins[b].bbox);
+                left.count += bins[b].count;
+                if left.count == 0 || right_count[b + 1] == 0 {
+                    continue;
+                }
+
+                let cost = TRAVERSAL_COST
+                    + INTERSECTION_COST
+                        * (left.count as f32 * left.bbox.surface_area()
+                            + right_count[b + 1] as f32 * right_area[b + 1])
+                        / area.max(f32::MIN_POSITIVE);
+
+                if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                    best = Some((axis, b, cost));
+                }
+            }
+        }
+
+        best
+    }
+
+    /// Ожидаемая стоимость трассировки луча через построенную иерархию.
+    fn cost(&self) -> f32 {
+        let root_area = match self.nodes.first() {
+            Some(root) => root.bbox.surface_area(),
+            None => return 0.0,
+        };
+        if root_area <= 0.0 {
+            return INTERSECTION_COST * self.objects.len() as f32;
+        }
+
+        self.nodes
+            .iter()
+            .map(|node| {
+                let p = node.bbox.surface_area() / root_area;
+                if node.count > 0 {
+                    p * INTERSECTION_COST * node.count as f32
+                } else {
+                    p * TRAVERSAL_COST
+                }
+            })
+            .sum()
+    }
+}
+
+/// Номер корзины, в которую попадает центр объекта вдоль оси `axis`.
+fn bin_index(centroid: Vec3, centroids: &Aabb, axis: usize) -> usize {
+    let extent = centroids.max[axis] - centroids.min[axis];
+    let b = (SAH_BINS as f32 * (centroid[axis] - centroids.min[axis]) / extent) as usize;
+
+    b.min(SAH_BINS - 1)
+}
+
+/// Переставляет элементы так, что удовлетворяющие предикату идут первыми.
+/// Возвращает число таких элементов.
+fn partition<T, F>(items: &mut [T], pred: F) -> usize
+where
+    F: Fn(&T) -> bool,
+{
+    let mut mid = 0;
+    for i in 0..items.len() {
+        if pred(&items[i]) {
+            items.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+impl Hittable for SahBvh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let dir_is_neg = [ray.direction()[0] < 0.0, ray.direction()[1] < 0.0, ray.direction()[2] < 0.0];
+        let mut closest = t_max;
+        let mut record = None;
+        let mut stack = [0usize; MAX_DEPTH];
+        let mut top = 0;
+        let mut current = 0;
+
+        loop {
+            let node = &self.nodes[current];
+            if node.bbox.hit(ray, t_min, closest) {
+                if node.count > 0 {
+                    for object in &self.objects[node.offset..node.offset + node.count] {
+                        if let Some(rec) = object.hit(ray, t_min, closest, sampler) {
+                            closest = rec.t;
+                            record = Some(rec);
+                        }
+                    }
+                } else {
+                    // Сначала обходим ближайшего к лучу потомка.
+                    if dir_is_neg[node.axis] {
+                        stack[top] = current + 1;
+                        current = node.offset;
+                    } else {
+                        stack[top] = node.offset;
+                        current += 1;
+                    }
+                    top += 1;
+                    continue;
+                }
+            }
+
+            if top == 0 {
+                break;
+            }
+            top -= 1;
+            current = stack[top];
+        }
+
+        record
+    }
+
+    fn bounding_box(&self, _: f32, _: f32) -> Option<Aabb> {
+        self.nodes.first().map(|root| root.bbox)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SahBvh;
+    use crate::bodies::{Hittable, Sphere};
+    use crate::geom::{unit_vector, Vec3};
+    use crate::materials::Lambert;
+    use crate::ray::Ray;
+    use crate::rng::Pcg32;
+    use crate::sampler::IndependentSampler;
+    use crate::World;
+    use std::sync::Arc;
+
+    fn random_vec(rng: &mut Pcg32, low: f32, high: f32) -> Vec3 {
+        Vec3::new(rng.range(low, high), rng.range(low, high), rng.range(low, high))
+    }
+
+    fn random_world(seed: u64, count: usize) -> World {
+        let mut rng = Pcg32::new(seed, 0);
+        let mut objects: Vec<Box<dyn Hittable>> = vec![];
+        for _ in 0..count {
+            objects.push(Box::new(Sphere {
+                center: random_vec(&mut rng, -10.0, 10.0),
+                radius: rng.range(0.1, 1.5),
+                material: Arc::new(Lambert::with_color([0.5, 0.5, 0.5].into())),
+            }));
+        }
+        World(objects)
+    }
+
+    #[test]
+    fn check_bvh_single_object() {
+        let linear = random_world(3, 1);
+        let bvh = SahBvh::new(random_world(3, 1), 0.0, 1.0);
+        let bbox = linear.0[0].bounding_box(0.0, 1.0).unwrap();
+        let target = 0.5 * (bbox.min + bbox.max);
+        let ray = Ray::new([20.0, 20.0, 20.0].into(), target - Vec3::new(20.0, 20.0, 20.0), 0.0);
+
+        let expected = linear.hit(&ray, 0.001, f32::MAX, &mut IndependentSampler::new(0)).map(|rec| rec.t);
+        assert!(expected.is_some());
+        assert_eq!(expected, bvh.hit(&ray, 0.001, f32::MAX, &mut IndependentSampler::new(0)).map(|rec| rec.t));
+    }
+
+    #[test]
+    fn check_bvh_box_contains_all_objects() {
+        let linear = random_world(11, 50);
+        let bvh = SahBvh::new(random_world(11, 50), 0.0, 1.0);
+        let outer = bvh.bounding_box(0.0, 1.0).unwrap();
+
+        for object in &linear.0 {
+            let inner = object.bounding_box(0.0, 1.0).unwrap();
+            for a in 0..3 {
+                assert!(outer.min[a] <= inner.min[a]);
+                assert!(outer.max[a] >= inner.max[a]);
+            }
+        }
+    }
+
+    #[test]
+    fn check_sah_bvh_matches_linear_traversal() {
+        let linear = random_world(5, 500);
+        let mut rng = Pcg32::new(17, 0);
+
+        for leaf_size in &[1, 4, 16] {
+            let bvh = SahBvh::with_leaf_size(random_world(5, 500), 0.0, 1.0, *leaf_size);
+            for _ in 0..2000 {
+                let ray = Ray {
+                    orig: random_vec(&mut rng, -15.0, 15.0),
+                    dir: unit_vector(random_vec(&mut rng, -1.0, 1.0)),
+                    time: 0.0,
+                };
+
+                let expected = linear.hit(&ray, 0.001, f32::MAX, &mut IndependentSampler::new(0)).map(|rec| rec.t);
+                let actual = bvh.hit(&ray, 0.001, f32::MAX, &mut IndependentSampler::new(0)).map(|rec| rec.t);
+                assert_eq!(expected, actual);
+            }
+        }
+    }
+
+    #[test]
+    fn check_sah_bvh_stats() {
+        let bvh = SahBvh::with_leaf_size(random_world(9, 200), 0.0, 1.0, 2);
+        let stats = bvh.stats();
+
+        assert_eq!(200, stats.object_count);
+        assert_eq!(stats.node_count, 2 * stats.leaf_count - 1);
+        assert!(stats.depth > 1);
+        assert!(stats.sah_cost > 0.0 && stats.sah_cost < stats.object_count as f32);
+    }
+
+    #[test]
+    fn check_sah_bvh_identical_objects() {
+        let mut objects: Vec<Box<dyn Hittable>> = vec![];
+        for _ in 0..10 {
+            objects.push(Box::new(Sphere {
+                center: [0.0, 0.0, -5.0].into(),
+                radius: 1.0,
+                material: Arc::new(Lambert::with_color([0.5, 0.5, 0.5].into())),
+            }));
+        }
+        let bvh = SahBvh::with_leaf_size(World(objects), 0.0, 1.0, 1);
+        let ray = Ray::new([0.0, 0.0, 0.0].into(), [0.0, 0.0, -1.0].into(), 0.0);
+
+        assert_eq!(1, bvh.stats().leaf_count);
+        assert_eq!(Some(4.0), bvh.hit(&ray, 0.001, f32::MAX, &mut IndependentSampler::new(0)).map(|rec| rec.t));
+    }
+}
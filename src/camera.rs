@@ -1,5 +1,6 @@
 use crate::geom::{cross, unit_vector, Vec3};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::utils::{deg_to_rad, random_in_unit_disk};
 use crate::Point;
 
@@ -13,9 +14,15 @@ pub struct Camera {
     v: Vec3,
     //w: Vec3,
     lens_radius: f32,
+    /// Время открытия затвора.
+    time0: f32,
+    /// Время закрытия затвора.
+    time1: f32,
 }
 
 impl Camera {
+    /// Создает камеру, затвор которой открыт на интервале времени `[time0; time1]`.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         lookfrom: Vec3,
         lookat: Vec3,
@@ -24,6 +31,8 @@ impl Camera {
         aspect_ratio: f32,
         aperture: f32,
         focus_dist: f32,
+        time0: f32,
+        time1: f32,
     ) -> Self {
         let theta = deg_to_rad(vfov);
         let h = (theta / 2.0).tan();
@@ -49,18 +58,54 @@ impl Camera {
             v,
             //w,
             lens_radius,
+            time0,
+            time1,
         }
     }
 
-    pub fn get_ray(&self, s: f32, t: f32) -> Ray {
-        let rd = self.lens_radius * random_in_unit_disk();
+    pub fn get_ray(&self, s: f32, t: f32, sampler: &mut dyn Sampler) -> Ray {
+        let rd = self.lens_radius * random_in_unit_disk(sampler);
         let offset = self.u * rd.x() + self.v * rd.y();
+        let time = if self.time1 > self.time0 {
+            self.time0 + (self.time1 - self.time0) * sampler.next_1d()
+        } else {
+            self.time0
+        };
 
         Ray {
             orig: self.origin + offset,
             dir: self.lower_left_corner + s * self.horizontal + t * self.vertical
                 - self.origin
                 - offset,
+            time,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Camera;
+    use crate::geom::Vec3;
+    use crate::sampler::{IndependentSampler, Sampler};
+
+    #[test]
+    fn check_ray_time() {
+        let camera = |time0: f32, time1: f32| {
+            let up = Vec3::new(0.0, 1.0, 0.0);
+            Camera::new(Vec3::default(), Vec3::new(0.0, 0.0, -1.0), up, 90.0, 1.0, 0.0, 1.0, time0, time1)
+        };
+        let sampler = &mut IndependentSampler::new(5);
+
+        let shutter = camera(0.5, 1.5);
+        let times: Vec<f32> = (0..1000)
+            .map(|index| {
+                sampler.start_sample(0, 0, index);
+                shutter.get_ray(0.5, 0.5, sampler).time()
+            })
+            .collect();
+        assert!(times.iter().all(|time| (0.5..=1.5).contains(time)));
+        assert!(times.iter().any(|&time| time < 0.6) && times.iter().any(|&time| time > 1.4));
+
+        assert_eq!(2.0, camera(2.0, 2.0).get_ray(0.5, 0.5, sampler).time());
+    }
+}
@@ -0,0 +1,495 @@
+use crate::exr::{ExrCompression, ExrPixelType};
+use crate::sampler::SamplerKind;
+use crate::scene::Scene;
+use crate::scenes::BUILTIN_SCENE_COUNT;
+use crate::tonemap::{ToneMapOperator, ToneMapping};
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Справка, которую печатает `--help`.
+pub fn usage() -> String {
+    format!(
+        "\
+Usage: raytracer-in-weekend-tnw [OPTIONS] [SCENE_FILE]
+
+Renders a built-in scene or a TOML scene file. Options override the settings stored in the scene.
+
+Options:
+      --scene <NUMBER>        Built-in scene from 1 to {} [default: 1]
+      --scene-file <PATH>     Scene file; may also be given as the only positional argument
+  -w, --width <PIXELS>        Image width
+      --height <PIXELS>       Image height; together with --width it also sets the aspect ratio
+  -a, --aspect <W:H|RATIO>    Aspect ratio, e.g. 16:9 or 1.5
+  -s, --samples <N>           Samples per pixel; the maximum per pixel with --adaptive
+      --adaptive <ERROR>      Stop sampling a pixel once the relative error of its mean is below ERROR, e.g. 0.01
+      --min-samples <N>       Samples every pixel gets before --adaptive may stop it [default: 16]
+  -d, --depth <N>             Maximum number of ray bounces
+      --roulette-depth <N>    Bounces after which paths may be terminated by Russian roulette
+      --sampler <NAME>        Sample generator: independent, stratified, halton, sobol or r2 [default: sobol]
+  -o, --output <PATH>         Output file [default: standard output]
+  -f, --format <FORMAT>       Output format: ppm, ppm-ascii, pfm, png, hdr or exr
+                              [default: from the output file extension, ppm for standard output]
+      --exr-type <TYPE>       OpenEXR channel type: half or float [default: half]
+      --exr-compression <C>   OpenEXR compression: none or zip [default: zip]
+      --exposure <EV>         Exposure in stops for 8-bit formats [default: 0]
+      --tonemap <OPERATOR>    Tone mapping for 8-bit formats: none, reinhard, reinhard-extended, hable or aces
+                              [default: none]
+      --white <LUMINANCE>     Luminance mapped to white by reinhard-extended [default: 4]
+      --dither                Dither 8-bit output to hide banding
+      --sample-map <PATH>     Also write the number of samples per pixel as a greyscale image,
+                              scaled so that the pixel with most samples is white
+  -j, --threads <N>           Number of render threads [default: number of CPUs]
+      --seed <N>              Random seed; the same seed gives the same image [default: random]
+  -h, --help                  Print this help
+",
+        BUILTIN_SCENE_COUNT
+    )
+}
+
+/// Ошибка разбора командной строки.
+#[derive(Debug, PartialEq)]
+pub struct CliError(pub String);
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for CliError {}
+
+/// Откуда берется сцена.
+#[derive(Debug, PartialEq)]
+pub enum SceneSource {
+    /// Встроенная сцена с номером.
+    Builtin(i32),
+    /// Файл сцены.
+    File(String),
+}
+
+/// Формат выходного изображения.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// Двоичный PPM (`P6`).
+    Ppm,
+    /// Текстовый PPM (`P3`).
+    PpmAscii,
+    /// PFM с яркостью без ограничений.
+    Pfm,
+    Png,
+    /// Radiance HDR в формате RGBE.
+    Hdr,
+    /// OpenEXR с линейными значениями.
+    Exr,
+}
+
+impl OutputFormat {
+    /// Формат по расширению имени файла.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "ppm" => Some(OutputFormat::Ppm),
+            "pfm" => Some(OutputFormat::Pfm),
+            "png" => Some(OutputFormat::Png),
+            "hdr" => Some(OutputFormat::Hdr),
+            "exr" => Some(OutputFormat::Exr),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ppm" => Ok(OutputFormat::Ppm),
+            "ppm-ascii" => Ok(OutputFormat::PpmAscii),
+            "pfm" => Ok(OutputFormat::Pfm),
+            "png" => Ok(OutputFormat::Png),
+            "hdr" => Ok(OutputFormat::Hdr),
+            "exr" => Ok(OutputFormat::Exr),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Параметры запуска. Незаданные значения берутся из сцены или выбираются по умолчанию.
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub scene: SceneSource,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub aspect_ratio: Option<f32>,
+    pub samples_per_pixel: Option<i32>,
+    pub depth: Option<i32>,
+    pub roulette_depth: Option<i32>,
+    pub sampler: SamplerKind,
+    /// Допустимая относительная ошибка адаптивной выборки; `None` - число сэмплов постоянно.
+    pub adaptive_threshold: Option<f32>,
+    pub min_samples: Option<u32>,
+    /// Путь к выходному файлу; `None` - стандартный вывод.
+    pub output: Option<String>,
+    /// Формат из расширения выходного файла или флага `--format`; по умолчанию двоичный PPM.
+    pub format: OutputFormat,
+    pub exr_pixel_type: ExrPixelType,
+    pub exr_compression: ExrCompression,
+    /// Преобразование линейной яркости для 8-битных форматов.
+    pub tone_mapping: ToneMapping,
+    /// Путь и формат отладочного изображения числа сэмплов.
+    pub sample_map: Option<(String, OutputFormat)>,
+    pub threads: Option<usize>,
+    pub seed: Option<u64>,
+}
+
+/// Что нужно сделать программе.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Render(Options),
+    Help,
+}
+
+/// Разбирает аргументы командной строки без имени программы.
+///
+/// Значения флагов передаются следующим аргументом (`--width 800`) или через знак равенства (`--width=800`).
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, CliError> {
+    let mut options = Options {
+        scene: SceneSource::Builtin(1),
+        width: None,
+        height: None,
+        aspect_ratio: None,
+        samples_per_pixel: None,
+        depth: None,
+        roulette_depth: None,
+        sampler: SamplerKind::Sobol,
+        adaptive_threshold: None,
+        min_samples: None,
+        output: None,
+        format: OutputFormat::Ppm,
+        exr_pixel_type: ExrPixelType::Half,
+        exr_compression: ExrCompression::Zip,
+        tone_mapping: ToneMapping::default(),
+        sample_map: None,
+        threads: None,
+        seed: None,
+    };
+    let mut scene_number = None;
+    let mut scene_file = None;
+    let mut format = None;
+    let mut white = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') || arg == "-" {
+            if scene_file.is_some() {
+                return Err(CliError(format!("unexpected argument '{}'", arg)));
+            }
+            scene_file = Some(arg);
+            continue;
+        }
+
+        let (flag, inline_value) = match arg.find('=') {
+            Some(pos) if arg.starts_with("--") => (arg[..pos].to_string(), Some(arg[pos + 1..].to_string())),
+            _ => (arg.clone(), None),
+        };
+        if flag == "-h" || flag == "--help" {
+            return Ok(Command::Help);
+        }
+        if flag == "--dither" {
+            if inline_value.is_some() {
+                return Err(CliError(String::from("'--dither' does not take a value")));
+            }
+            options.tone_mapping.dither = true;
+            continue;
+        }
+
+        let value = match inline_value.or_else(|| args.next()) {
+            Some(value) => value,
+            None => return Err(CliError(format!("missing value for '{}'", flag))),
+        };
+
+        match flag.as_str() {
+            "--scene" => {
+                let number = parse_value(&flag, &value, "a scene number")?;
+                if !(1..=BUILTIN_SCENE_COUNT).contains(&number) {
+                    return Err(CliError(format!(
+                        "invalid value '{}' for '--scene': expected a number from 1 to {}",
+                        value, BUILTIN_SCENE_COUNT
+                    )));
+                }
+                scene_number = Some(number);
+            }
+            "--scene-file" => {
+                if scene_file.is_some() {
+                    return Err(CliError(String::from("the scene file is given twice")));
+                }
+                scene_file = Some(value);
+            }
+            "-w" | "--width" => options.width = Some(parse_positive(&flag, &value)?),
+            "--height" => options.height = Some(parse_positive(&flag, &value)?),
+            "-a" | "--aspect" => options.aspect_ratio = Some(parse_aspect(&value)?),
+            "-s" | "--samples" => options.samples_per_pixel = Some(parse_positive(&flag, &value)?),
+            "-d" | "--depth" => options.depth = Some(parse_positive(&flag, &value)?),
+            "--roulette-depth" => options.roulette_depth = Some(parse_positive(&flag, &value)?),
+            "--sampler" => {
+                options.sampler = value.parse().map_err(|_| {
+                    CliError(format!(
+                        "unknown sampler '{}', expected independent, stratified, halton, sobol or r2",
+                        value
+                    ))
+                })?;
+            }
+            "--adaptive" => match value.parse::<f32>() {
+                Ok(threshold) if threshold.is_finite() && threshold > 0.0 => options.adaptive_threshold = Some(threshold),
+                _ => return Err(CliError(format!("invalid value '{}' for '{}': expected a positive number", value, flag))),
+            },
+            "--min-samples" => options.min_samples = Some(parse_positive(&flag, &value)?),
+            "-o" | "--output" => options.output = Some(value),
+            "-f" | "--format" => {
+                let parsed = value.parse().map_err(|_| {
+                    CliError(format!("unknown output format '{}', expected ppm, ppm-ascii, pfm, png, hdr or exr", value))
+                })?;
+                format = Some(parsed);
+            }
+            "--exr-type" => {
+                options.exr_pixel_type = match value.to_ascii_lowercase().as_str() {
+                    "half" => ExrPixelType::Half,
+                    "float" => ExrPixelType::Float,
+                    _ => return Err(CliError(format!("invalid value '{}' for '{}': expected half or float", value, flag))),
+                }
+            }
+            "--exr-compression" => {
+                options.exr_compression = match value.to_ascii_lowercase().as_str() {
+                    "none" => ExrCompression::None,
+                    "zip" => ExrCompression::Zip,
+                    _ => return Err(CliError(format!("invalid value '{}' for '{}': expected none or zip", value, flag))),
+                }
+            }
+            "--exposure" => {
+                let exposure: f32 = parse_value(&flag, &value, "a number")?;
+                if !exposure.is_finite() {
+                    return Err(CliError(format!("invalid value '{}' for '{}': expected a number", value, flag)));
+                }
+                options.tone_mapping.exposure = exposure;
+            }
+            "--tonemap" => {
+                options.tone_mapping.operator = match value.to_ascii_lowercase().as_str() {
+                    "none" => ToneMapOperator::Clamp,
+                    "reinhard" => ToneMapOperator::Reinhard,
+                    "reinhard-extended" => ToneMapOperator::ExtendedReinhard { white: 4.0 },
+                    "hable" => ToneMapOperator::Hable,
+                    "aces" => ToneMapOperator::Aces,
+                    _ => {
+                        return Err(CliError(format!(
+                            "unknown tone mapping '{}', expected none, reinhard, reinhard-extended, hable or aces",
+                            value
+                        )))
+                    }
+                }
+            }
+            "--white" => match value.parse::<f32>() {
+                Ok(luminance) if luminance.is_finite() && luminance > 0.0 => white = Some(luminance),
+                _ => return Err(CliError(format!("invalid value '{}' for '{}': expected a positive number", value, flag))),
+            },
+            "--sample-map" => {
+                let format = OutputFormat::from_path(&value)
+                    .ok_or_else(|| CliError(format!("can not guess the format of '{}' from its extension", value)))?;
+                options.sample_map = Some((value, format));
+            }
+            "-j" | "--threads" => options.threads = Some(parse_positive::<i32>(&flag, &value)? as usize),
+            "--seed" => options.seed = Some(parse_value(&flag, &value, "a non-negative integer")?),
+            _ => return Err(CliError(format!("unknown option '{}'", flag))),
+        }
+    }
+
+    options.scene = match (scene_number, scene_file) {
+        (Some(_), Some(_)) => return Err(CliError(String::from("'--scene' and a scene file are mutually exclusive"))),
+        (_, Some(path)) => SceneSource::File(path),
+        (number, None) => SceneSource::Builtin(number.unwrap_or(1)),
+    };
+    options.format = match (format, &options.output) {
+        (Some(format), _) => format,
+        (None, None) => OutputFormat::Ppm,
+        (None, Some(path)) => OutputFormat::from_path(path).ok_or_else(|| {
+            CliError(format!("can not guess the format of '{}', use '--format'", path))
+        })?,
+    };
+    if let Some(luminance) = white {
+        match &mut options.tone_mapping.operator {
+            ToneMapOperator::ExtendedReinhard { white } => *white = luminance,
+            _ => return Err(CliError(String::from("'--white' requires '--tonemap reinhard-extended'"))),
+        }
+    }
+    if options.min_samples.is_some() && options.adaptive_threshold.is_none() {
+        return Err(CliError(String::from("'--min-samples' requires '--adaptive'")));
+    }
+    if options.width.is_some() && options.height.is_some() && options.aspect_ratio.is_some() {
+        return Err(CliError(String::from("'--aspect' can not be combined with both '--width' and '--height'")));
+    }
+
+    Ok(Command::Render(options))
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: &str, expected: &str) -> Result<T, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError(format!("invalid value '{}' for '{}': expected {}", value, flag, expected)))
+}
+
+fn parse_positive<T: FromStr + PartialOrd + Default>(flag: &str, value: &str) -> Result<T, CliError> {
+    match parse_value::<T>(flag, value, "a positive integer") {
+        Ok(number) if number > T::default() => Ok(number),
+        _ => Err(CliError(format!("invalid value '{}' for '{}': expected a positive integer", value, flag))),
+    }
+}
+
+/// Соотношение сторон в виде `W:H` или десятичной дроби.
+fn parse_aspect(value: &str) -> Result<f32, CliError> {
+    let ratio = match value.split_once(':') {
+        Some((w, h)) => match (w.trim().parse::<f32>(), h.trim().parse::<f32>()) {
+            (Ok(w), Ok(h)) => w / h,
+            _ => f32::NAN,
+        },
+        None => value.parse().unwrap_or(f32::NAN),
+    };
+
+    if ratio.is_finite() && ratio > 0.0 {
+        Ok(ratio)
+    } else {
+        Err(CliError(format!("invalid value '{}' for '--aspect': expected W:H or a positive number", value)))
+    }
+}
+
+impl Options {
+    /// Заменяет настройки изображения сцены заданными в командной строке.
+    pub fn apply(&self, scene: &mut Scene) -> Result<(), CliError> {
+        if let Some(aspect_ratio) = self.aspect_ratio {
+            scene.aspect_ratio = aspect_ratio;
+        }
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => {
+                scene.image_width = width;
+                scene.aspect_ratio = width as f32 / height as f32;
+            }
+            (Some(width), None) => scene.image_width = width,
+            (None, Some(height)) => scene.image_width = (height as f32 * scene.aspect_ratio).round() as i32,
+            (None, None) => {}
+        }
+        if let Some(samples_per_pixel) = self.samples_per_pixel {
+            scene.samples_per_pixel = samples_per_pixel;
+        }
+        if let Some(depth) = self.depth {
+            scene.depth = depth;
+        }
+        if let Some(roulette_depth) = self.roulette_depth {
+            scene.roulette_depth = roulette_depth;
+        }
+
+        if scene.image_width < 1 || scene.image_height() < 1 {
+            return Err(CliError(format!(
+                "image size {}x{} is too small",
+                scene.image_width,
+                scene.image_height()
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_args, CliError, Command, OutputFormat, SceneSource};
+    use crate::exr::{ExrCompression, ExrPixelType};
+    use crate::sampler::SamplerKind;
+    use crate::scene::Scene;
+    use crate::scenes::BUILTIN_SCENE_COUNT;
+    use crate::tonemap::{ToneMapOperator, ToneMapping};
+    use crate::World;
+
+    fn parse(args: &[&str]) -> Result<Command, CliError> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn options(args: &[&str]) -> super::Options {
+        match parse(args) {
+            Ok(Command::Render(options)) => options,
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn check_flags() {
+        let options = options(&["--scene", "6", "-w", "320", "--height=240", "-s", "8", "-o", "out.ppm", "--seed", "7"]);
+
+        assert_eq!(SceneSource::Builtin(6), options.scene);
+        assert_eq!((Some(320), Some(240), Some(8)), (options.width, options.height, options.samples_per_pixel));
+        assert_eq!((Some(String::from("out.ppm")), Some(7)), (options.output, options.seed));
+        assert_eq!(OutputFormat::Ppm, options.format);
+        assert_eq!(OutputFormat::Png, self::options(&["-o", "frame.PNG"]).format);
+        assert_eq!(OutputFormat::PpmAscii, self::options(&["-o", "frame.png", "-f", "ppm-ascii"]).format);
+        let exr = self::options(&["-o", "frame.exr", "--exr-type", "float", "--exr-compression=none"]);
+        assert_eq!(OutputFormat::Exr, exr.format);
+        assert_eq!((ExrPixelType::Float, ExrCompression::None), (exr.exr_pixel_type, exr.exr_compression));
+
+        let tone_mapping = ToneMapping {
+            exposure: -1.5,
+            operator: ToneMapOperator::ExtendedReinhard { white: 8.0 },
+            dither: true,
+        };
+        let options = self::options(&["--white=8", "--exposure", "-1.5", "--dither", "--tonemap", "reinhard-extended"]);
+        assert_eq!(tone_mapping, options.tone_mapping);
+
+        assert_eq!(SceneSource::File(String::from("a.toml")), self::options(&["a.toml", "-j", "2"]).scene);
+        assert_eq!(Some(16.0 / 9.0), self::options(&["--aspect", "16:9"]).aspect_ratio);
+        assert_eq!(Some(2), self::options(&["--roulette-depth", "2"]).roulette_depth);
+        assert_eq!(SamplerKind::Sobol, options.sampler);
+        assert_eq!(SamplerKind::R2, self::options(&["--sampler", "R2"]).sampler);
+        assert_eq!(Ok(Command::Help), parse(&["-w", "10", "--help"]));
+
+        let adaptive = self::options(&["--adaptive", "0.05", "--min-samples=4", "--sample-map", "counts.pfm"]);
+        assert_eq!((Some(0.05), Some(4)), (adaptive.adaptive_threshold, adaptive.min_samples));
+        assert_eq!(Some((String::from("counts.pfm"), OutputFormat::Pfm)), adaptive.sample_map);
+        assert_eq!((None, None, None), (options.adaptive_threshold, options.min_samples, options.sample_map));
+    }
+
+    #[test]
+    fn check_invalid_flags() {
+        let error = |args: &[&str]| parse(args).err().unwrap().0;
+
+        assert_eq!("invalid value '0' for '--width': expected a positive integer", error(&["--width", "0"]));
+        assert_eq!("invalid value 'x' for '-s': expected a positive integer", error(&["-s", "x"]));
+        let scene = (BUILTIN_SCENE_COUNT + 1).to_string();
+        assert_eq!(
+            format!("invalid value '{}' for '--scene': expected a number from 1 to {}", scene, BUILTIN_SCENE_COUNT),
+            error(&["--scene", &scene])
+        );
+        assert_eq!("invalid value '4:0' for '--aspect': expected W:H or a positive number", error(&["-a", "4:0"]));
+        assert_eq!("unknown output format 'gif', expected ppm, ppm-ascii, pfm, png, hdr or exr", error(&["-f", "gif"]));
+        assert_eq!("can not guess the format of 'out.gif', use '--format'", error(&["-o", "out.gif"]));
+        assert_eq!("invalid value 'int' for '--exr-type': expected half or float", error(&["--exr-type", "int"]));
+        assert_eq!("'--white' requires '--tonemap reinhard-extended'", error(&["--white", "2", "--tonemap", "aces"]));
+        assert_eq!("invalid value '0' for '--adaptive': expected a positive number", error(&["--adaptive", "0"]));
+        assert_eq!("'--min-samples' requires '--adaptive'", error(&["--min-samples", "4"]));
+        assert_eq!("can not guess the format of 'map' from its extension", error(&["--sample-map", "map"]));
+        assert_eq!("missing value for '--depth'", error(&["--depth"]));
+        assert_eq!("'--dither' does not take a value", error(&["--dither=1"]));
+        assert_eq!("unknown option '--sample'", error(&["--sample", "2"]));
+        assert_eq!("'--scene' and a scene file are mutually exclusive", error(&["--scene", "2", "x.toml"]));
+    }
+
+    #[test]
+    fn check_apply_resolution() {
+        let mut scene = Scene::new(World(vec![]));
+        options(&["--width", "1920", "--height", "1080"]).apply(&mut scene).unwrap();
+        assert_eq!((1920, 1080), (scene.image_width, scene.image_height()));
+
+        options(&["--height", "100", "--aspect", "2"]).apply(&mut scene).unwrap();
+        assert_eq!((200, 100), (scene.image_width, scene.image_height()));
+
+        let err = options(&["--width", "1", "--aspect", "4"]).apply(&mut scene).err().unwrap();
+        assert_eq!("image size 1x0 is too small", err.0);
+    }
+}
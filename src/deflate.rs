@@ -0,0 +1,627 @@
+//! Сжатие данных алгоритмом Deflate (RFC 1951) и обертка zlib (RFC 1950).
+//!
+//! Повторы ищутся методом LZ77 с хеш-цепочками, каждый блок кодируется динамическими кодами Хаффмана
+//! с ограничением длины кода (алгоритм package-merge) или сохраняется без сжатия, если так короче.
+
+/// Размер окна, в котором ищутся повторы.
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// Сколько предыдущих позиций с тем же хешем проверяется при поиске повтора.
+const MAX_CHAIN: usize = 128;
+/// Повтор такой длины считается достаточно хорошим, чтобы прекратить поиск.
+const GOOD_MATCH: usize = 128;
+const HASH_BITS: u32 = 15;
+/// Число символов LZ77 в одном блоке.
+const BLOCK_TOKENS: usize = 32768;
+/// Максимальный размер блока без сжатия.
+const MAX_STORED: usize = 65535;
+
+/// Основания и число дополнительных бит кодов длин 257..285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+/// Основания и число дополнительных бит кодов расстояний 0..29.
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// Порядок передачи длин кодов алфавита длин кодов.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+const END_OF_BLOCK: usize = 256;
+
+/// Символ потока LZ77.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Token {
+    Literal(u8),
+    Match { length: u16, distance: u16 },
+}
+
+/// Записывает биты в поток начиная с младшего, как требует Deflate.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        BitWriter { bytes: vec![], buffer: 0, count: 0 }
+    }
+
+    fn write(&mut self, value: u32, bits: u32) {
+        self.buffer |= (value as u64) << self.count;
+        self.count += bits;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Дописывает нулевые биты до границы байта.
+    fn align(&mut self) {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer = 0;
+            self.count = 0;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+/// Разбивает данные на литералы и повторы жадным поиском с отложенным выбором (lazy matching):
+/// повтор откладывается, если со следующей позиции начинается более длинный.
+fn lz77(data: &[u8]) -> Vec<Token> {
+    let hash = |i: usize| {
+        let v = (data[i] as u32) << 16 | (data[i + 1] as u32) << 8 | data[i + 2] as u32;
+        (v.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+    };
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; data.len()];
+    let insert = |i: usize, head: &mut Vec<usize>, prev: &mut Vec<usize>| {
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(i);
+            prev[i] = head[h];
+            head[h] = i;
+        }
+    };
+
+    let find = |i: usize, head: &Vec<usize>, prev: &Vec<usize>| -> (usize, usize) {
+        if i + MIN_MATCH > data.len() {
+            return (0, 0);
+        }
+        let max_length = MAX_MATCH.min(data.len() - i);
+        let (mut best_length, mut best_distance) = (0, 0);
+        let mut candidate = head[hash(i)];
+        let mut chain = 0;
+
+        while candidate != usize::MAX && i - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+            if data[candidate + best_length.min(max_length - 1)] == data[i + best_length.min(max_length - 1)] {
+                let length = (0..max_length).take_while(|&k| data[candidate + k] == data[i + k]).count();
+                if length > best_length {
+                    best_length = length;
+                    best_distance = i - candidate;
+                    if length >= GOOD_MATCH.min(max_length) {
+                        break;
+                    }
+                }
+            }
+            candidate = prev[candidate];
+            chain += 1;
+        }
+
+        if best_length >= MIN_MATCH {
+            (best_length, best_distance)
+        } else {
+            (0, 0)
+        }
+    };
+
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < data.len() {
+        let (length, distance) = find(i, &head, &prev);
+        insert(i, &mut head, &mut prev);
+
+        if length == 0 {
+            tokens.push(Token::Literal(data[i]));
+            i += 1;
+            continue;
+        }
+
+        let (next_length, _) = find(i + 1, &head, &prev);
+        if next_length > length {
+            tokens.push(Token::Literal(data[i]));
+            i += 1;
+            continue;
+        }
+
+        tokens.push(Token::Match { length: length as u16, distance: distance as u16 });
+        for k in i + 1..i + length {
+            insert(k, &mut head, &mut prev);
+        }
+        i += length;
+    }
+
+    tokens
+}
+
+/// Номер кода и дополнительные биты для значения `value` по таблице оснований.
+fn symbol(base: &[u16], extra: &[u8], value: u16) -> (usize, u32, u32) {
+    let code = base.iter().rposition(|&b| b <= value).unwrap_or(0);
+    (code, (value - base[code]) as u32, extra[code] as u32)
+}
+
+/// Длины кодов Хаффмана не длиннее `max_bits` для частот `freqs` (алгоритм package-merge).
+///
+/// Символы с нулевой частотой получают длину 0. Если используется только один символ, второй код длины 1
+/// достается неиспользуемому символу: zlib отвергает неполные наборы кодов.
+fn code_lengths(freqs: &[u32], max_bits: u32) -> Vec<u8> {
+    let mut lengths = vec![0u8; freqs.len()];
+    let mut leaves: Vec<(u64, Vec<usize>)> = freqs
+        .iter()
+        .enumerate()
+        .filter(|(_, &f)| f > 0)
+        .map(|(symbol, &f)| (f as u64, vec![symbol]))
+        .collect();
+    leaves.sort_by_key(|(weight, _)| *weight);
+
+    match leaves.len() {
+        0 => return lengths,
+        1 => {
+            let symbol = leaves[0].1[0];
+            lengths[symbol] = 1;
+            lengths[if symbol == 0 { 1 } else { 0 }] = 1;
+            return lengths;
+        }
+        _ => {}
+    }
+
+    // На каждом шаге соседние элементы объединяются в пакеты, которые сливаются с исходными листьями.
+    let mut list = leaves.clone();
+    for _ in 1..max_bits {
+        let packages = list.chunks_exact(2).map(|pair| {
+            let mut symbols = pair[0].1.clone();
+            symbols.extend_from_slice(&pair[1].1);
+            (pair[0].0 + pair[1].0, symbols)
+        });
+
+        let mut merged = Vec::with_capacity(leaves.len() * 2);
+        let mut leaves_iter = leaves.iter().cloned().peekable();
+        for package in packages {
+            while leaves_iter.peek().is_some_and(|leaf| leaf.0 <= package.0) {
+                merged.push(leaves_iter.next().unwrap());
+            }
+            merged.push(package);
+        }
+        merged.extend(leaves_iter);
+        list = merged;
+    }
+
+    // Длина кода символа равна числу выбранных элементов, в которые он входит.
+    for (_, symbols) in list.iter().take(2 * leaves.len() - 2) {
+        for &symbol in symbols {
+            lengths[symbol] += 1;
+        }
+    }
+    lengths
+}
+
+/// Канонические коды Хаффмана по их длинам, с уже обращенным порядком бит для записи в поток.
+fn canonical_codes(lengths: &[u8]) -> Vec<u32> {
+    let max_bits = lengths.iter().cloned().max().unwrap_or(0) as usize;
+    let mut count = vec![0u32; max_bits + 1];
+    for &length in lengths.iter().filter(|&&l| l > 0) {
+        count[length as usize] += 1;
+    }
+
+    let mut next = vec![0u32; max_bits + 2];
+    let mut code = 0;
+    for bits in 1..=max_bits {
+        code = (code + count[bits - 1]) << 1;
+        next[bits] = code;
+    }
+
+    lengths
+        .iter()
+        .map(|&length| {
+            if length == 0 {
+                return 0;
+            }
+            let code = next[length as usize];
+            next[length as usize] += 1;
+            code.reverse_bits() >> (32 - length as u32)
+        })
+        .collect()
+}
+
+/// Кодирует последовательность длин кодов алфавитом длин кодов с повторами 16, 17 и 18.
+///
+/// Каждый элемент - символ, значение дополнительных бит и их число.
+fn run_length_encode(lengths: &[u8]) -> Vec<(usize, u32, u32)> {
+    let mut result = vec![];
+    let mut i = 0;
+
+    while i < lengths.len() {
+        let length = lengths[i];
+        let run = lengths[i..].iter().take_while(|&&l| l == length).count();
+
+        if length == 0 && run >= 3 {
+            let run = run.min(138);
+            if run <= 10 {
+                result.push((17, run as u32 - 3, 3));
+            } else {
+                result.push((18, run as u32 - 11, 7));
+            }
+            i += run;
+        } else if length != 0 && run >= 4 {
+            result.push((length as usize, 0, 0));
+            let run = (run - 1).min(6);
+            result.push((16, run as u32 - 3, 2));
+            i += run + 1;
+        } else {
+            result.push((length as usize, 0, 0));
+            i += 1;
+        }
+    }
+
+    result
+}
+
+/// Коды блока с динамическими кодами Хаффмана и размер блока в битах.
+struct DynamicBlock {
+    literal_lengths: Vec<u8>,
+    distance_lengths: Vec<u8>,
+    code_length_lengths: Vec<u8>,
+    encoded_lengths: Vec<(usize, u32, u32)>,
+    hlit: usize,
+    hdist: usize,
+    hclen: usize,
+    bits: usize,
+}
+
+impl DynamicBlock {
+    fn new(tokens: &[Token]) -> Self {
+        let mut literal_freqs = vec![0u32; 286];
+        let mut distance_freqs = vec![0u32; 30];
+        literal_freqs[END_OF_BLOCK] = 1;
+        for token in tokens {
+            match *token {
+                Token::Literal(byte) => literal_freqs[byte as usize] += 1,
+                Token::Match { length, distance } => {
+                    literal_freqs[257 + symbol(&LENGTH_BASE, &LENGTH_EXTRA, length).0] += 1;
+                    distance_freqs[symbol(&DIST_BASE, &DIST_EXTRA, distance).0] += 1;
+                }
+            }
+        }
+        let literal_lengths = code_lengths(&literal_freqs, 15);
+        let distance_lengths = code_lengths(&distance_freqs, 15);
+        let hlit = 257.max(literal_lengths.iter().rposition(|&l| l > 0).unwrap_or(0) + 1);
+        let hdist = 1.max(distance_lengths.iter().rposition(|&l| l > 0).unwrap_or(0) + 1);
+
+        let mut all_lengths = literal_lengths[..hlit].to_vec();
+        all_lengths.extend_from_slice(&distance_lengths[..hdist]);
+        let encoded_lengths = run_length_encode(&all_lengths);
+
+        let mut code_length_freqs = vec![0u32; 19];
+        for &(symbol, _, _) in &encoded_lengths {
+            code_length_freqs[symbol] += 1;
+        }
+        let code_length_lengths = code_lengths(&code_length_freqs, 7);
+        let hclen = 4.max(CODE_LENGTH_ORDER.iter().rposition(|&s| code_length_lengths[s] > 0).unwrap_or(0) + 1);
+
+        let mut bits = 3 + 5 + 5 + 4 + 3 * hclen;
+        for &(symbol, _, extra_bits) in &encoded_lengths {
+            bits += code_length_lengths[symbol] as usize + extra_bits as usize;
+        }
+        for (symbol, &freq) in literal_freqs.iter().enumerate() {
+            let extra = if symbol > END_OF_BLOCK { LENGTH_EXTRA[symbol - 257] as usize } else { 0 };
+            bits += freq as usize * (literal_lengths[symbol] as usize + extra);
+        }
+        for token in tokens {
+            if let Token::Match { distance, .. } = *token {
+                let (code, _, extra) = symbol(&DIST_BASE, &DIST_EXTRA, distance);
+                bits += distance_lengths[code] as usize + extra as usize;
+            }
+        }
+
+        DynamicBlock {
+            literal_lengths,
+            distance_lengths,
+            code_length_lengths,
+            encoded_lengths,
+            hlit,
+            hdist,
+            hclen,
+            bits,
+        }
+    }
+
+    fn write(&self, writer: &mut BitWriter, tokens: &[Token], last: bool) {
+        writer.write(last as u32, 1);
+        writer.write(2, 2);
+        writer.write((self.hlit - 257) as u32, 5);
+        writer.write((self.hdist - 1) as u32, 5);
+        writer.write((self.hclen - 4) as u32, 4);
+        for &symbol in &CODE_LENGTH_ORDER[..self.hclen] {
+            writer.write(self.code_length_lengths[symbol] as u32, 3);
+        }
+
+        let code_length_codes = canonical_codes(&self.code_length_lengths);
+        for &(symbol, extra, extra_bits) in &self.encoded_lengths {
+            writer.write(code_length_codes[symbol], self.code_length_lengths[symbol] as u32);
+            writer.write(extra, extra_bits);
+        }
+
+        let literal_codes = canonical_codes(&self.literal_lengths);
+        let distance_codes = canonical_codes(&self.distance_lengths);
+        for token in tokens {
+            match *token {
+                Token::Literal(byte) => {
+                    let byte = byte as usize;
+                    writer.write(literal_codes[byte], self.literal_lengths[byte] as u32);
+                }
+                Token::Match { length, distance } => {
+                    let (code, extra, extra_bits) = symbol(&LENGTH_BASE, &LENGTH_EXTRA, length);
+                    writer.write(literal_codes[257 + code], self.literal_lengths[257 + code] as u32);
+                    writer.write(extra, extra_bits);
+
+                    let (code, extra, extra_bits) = symbol(&DIST_BASE, &DIST_EXTRA, distance);
+                    writer.write(distance_codes[code], self.distance_lengths[code] as u32);
+                    writer.write(extra, extra_bits);
+                }
+            }
+        }
+        writer.write(literal_codes[END_OF_BLOCK], self.literal_lengths[END_OF_BLOCK] as u32);
+    }
+}
+
+/// Записывает данные блоками без сжатия.
+fn write_stored(writer: &mut BitWriter, data: &[u8], last: bool) {
+    let mut chunks = data.chunks(MAX_STORED).peekable();
+
+    loop {
+        let chunk = chunks.next().unwrap_or(&[]);
+        let is_last = chunks.peek().is_none();
+        writer.write((last && is_last) as u32, 1);
+        writer.write(0, 2);
+        writer.align();
+        writer.write(chunk.len() as u32, 16);
+        writer.write(!chunk.len() as u32 & 0xFFFF, 16);
+        writer.bytes.extend_from_slice(chunk);
+        if is_last {
+            break;
+        }
+    }
+}
+
+/// Сжимает данные в поток Deflate.
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let tokens = lz77(data);
+    let mut writer = BitWriter::new();
+    if tokens.is_empty() {
+        write_stored(&mut writer, &[], true);
+        return writer.finish();
+    }
+
+    let mut position = 0;
+    let blocks: Vec<&[Token]> = tokens.chunks(BLOCK_TOKENS).collect();
+    for (index, &block_tokens) in blocks.iter().enumerate() {
+        let last = index + 1 == blocks.len();
+        let size: usize = block_tokens
+            .iter()
+            .map(|token| match *token {
+                Token::Literal(_) => 1,
+                Token::Match { length, .. } => length as usize,
+            })
+            .sum();
+
+        let block = DynamicBlock::new(block_tokens);
+        let stored_bits = (size / MAX_STORED + 1) * 40 + size * 8 + 7;
+        if block.bits < stored_bits {
+            block.write(&mut writer, block_tokens, last);
+        } else {
+            write_stored(&mut writer, &data[position..position + size], last);
+        }
+        position += size;
+    }
+
+    writer.finish()
+}
+
+/// Контрольная сумма Adler-32 потока zlib.
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+
+    // За 5552 байта сумма гарантированно не переполняет u32.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+
+    b << 16 | a
+}
+
+/// Сжимает данные в поток zlib: заголовок, поток Deflate и контрольная сумма Adler-32.
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    // Окно 32 КБ, уровень сжатия по умолчанию; FCHECK делает заголовок кратным 31.
+    let mut result = vec![0x78, 0x9C];
+    result.extend_from_slice(&deflate(data));
+    result.extend_from_slice(&adler32(data).to_be_bytes());
+    result
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::{adler32, code_lengths, deflate, zlib_compress, LENGTH_BASE, LENGTH_EXTRA, DIST_BASE, DIST_EXTRA};
+
+    /// Простой распаковщик Deflate для проверки сжатия.
+    struct Inflater<'a> {
+        data: &'a [u8],
+        pos: usize,
+        bit: u32,
+    }
+
+    impl Inflater<'_> {
+        fn bits(&mut self, count: u32) -> u32 {
+            let mut value = 0;
+            for i in 0..count {
+                let byte = self.data[self.pos];
+                value |= ((byte >> self.bit) as u32 & 1) << i;
+                self.bit += 1;
+                if self.bit == 8 {
+                    self.bit = 0;
+                    self.pos += 1;
+                }
+            }
+            value
+        }
+
+        /// Декодирует символ по длинам канонических кодов, читая код побитно.
+        fn decode(&mut self, lengths: &[u8]) -> usize {
+            let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+            for bits in 1..=15 {
+                code |= self.bits(1) as i32;
+                let count = lengths.iter().filter(|&&l| l == bits).count() as i32;
+                if code - count < first {
+                    let mut sorted: Vec<usize> = (0..lengths.len()).filter(|&s| lengths[s] > 0).collect();
+                    sorted.sort_by_key(|&s| (lengths[s], s));
+                    return sorted[(index + code - first) as usize];
+                }
+                index += count;
+                first = (first + count) << 1;
+                code <<= 1;
+            }
+            panic!("invalid Huffman code");
+        }
+
+        fn inflate(mut self) -> Vec<u8> {
+            let mut out: Vec<u8> = vec![];
+            loop {
+                let last = self.bits(1);
+                match self.bits(2) {
+                    0 => {
+                        if self.bit > 0 {
+                            self.bit = 0;
+                            self.pos += 1;
+                        }
+                        let len = u16::from_le_bytes([self.data[self.pos], self.data[self.pos + 1]]) as usize;
+                        let nlen = u16::from_le_bytes([self.data[self.pos + 2], self.data[self.pos + 3]]) as usize;
+                        assert_eq!(len, !nlen & 0xFFFF);
+                        out.extend_from_slice(&self.data[self.pos + 4..self.pos + 4 + len]);
+                        self.pos += 4 + len;
+                    }
+                    2 => {
+                        let hlit = self.bits(5) as usize + 257;
+                        let hdist = self.bits(5) as usize + 1;
+                        let hclen = self.bits(4) as usize + 4;
+                        let mut code_length_lengths = [0u8; 19];
+                        for &symbol in &super::CODE_LENGTH_ORDER[..hclen] {
+                            code_length_lengths[symbol] = self.bits(3) as u8;
+                        }
+
+                        let mut lengths = vec![];
+                        while lengths.len() < hlit + hdist {
+                            match self.decode(&code_length_lengths) {
+                                16 => {
+                                    let previous = *lengths.last().unwrap();
+                                    let repeat = 3 + self.bits(2);
+                                    lengths.extend((0..repeat).map(|_| previous));
+                                }
+                                17 => lengths.extend((0..3 + self.bits(3)).map(|_| 0)),
+                                18 => lengths.extend((0..11 + self.bits(7)).map(|_| 0)),
+                                length => lengths.push(length as u8),
+                            }
+                        }
+                        let (literal_lengths, distance_lengths) = lengths.split_at(hlit);
+
+                        loop {
+                            let symbol = self.decode(literal_lengths);
+                            if symbol < 256 {
+                                out.push(symbol as u8);
+                            } else if symbol == 256 {
+                                break;
+                            } else {
+                                let code = symbol - 257;
+                                let length = LENGTH_BASE[code] as usize + self.bits(LENGTH_EXTRA[code] as u32) as usize;
+                                let code = self.decode(distance_lengths);
+                                let distance = DIST_BASE[code] as usize + self.bits(DIST_EXTRA[code] as u32) as usize;
+                                for _ in 0..length {
+                                    out.push(out[out.len() - distance]);
+                                }
+                            }
+                        }
+                    }
+                    kind => panic!("unexpected block type {}", kind),
+                }
+                if last == 1 {
+                    return out;
+                }
+            }
+        }
+    }
+
+    /// Распаковывает поток Deflate без заголовка zlib.
+    pub(crate) fn inflate(data: &[u8]) -> Vec<u8> {
+        Inflater { data, pos: 0, bit: 0 }.inflate()
+    }
+
+    #[test]
+    fn check_deflate_round_trip() {
+        let text = b"Hello, hello, hello! Deflate finds repeats: abcabcabcabcabc.".repeat(40);
+        let mut noise = vec![];
+        let mut x = 12345u32;
+        for _ in 0..100_000 {
+            x = x.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            noise.push((x >> 16) as u8);
+        }
+        let mut mixed = noise[..5000].to_vec();
+        mixed.extend_from_slice(&[0u8; 70_000]);
+        mixed.extend_from_slice(&text);
+
+        for data in [vec![], vec![7], text.clone(), noise, mixed] {
+            let compressed = deflate(&data);
+            assert_eq!(data, inflate(&compressed));
+        }
+        assert!(deflate(&text).len() < text.len() / 10);
+    }
+
+    #[test]
+    fn check_length_limited_codes() {
+        // Частоты Фибоначчи дают в обычном коде Хаффмана длины до n - 1.
+        let mut freqs = vec![1u32, 1];
+        while freqs.len() < 30 {
+            freqs.push(freqs[freqs.len() - 1] + freqs[freqs.len() - 2]);
+        }
+        let lengths = code_lengths(&freqs, 15);
+
+        assert_eq!(15, *lengths.iter().max().unwrap());
+        let kraft: f64 = lengths.iter().map(|&l| 0.5f64.powi(l as i32)).sum();
+        assert!((kraft - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn check_zlib_stream() {
+        assert_eq!(0x11E6_0398, adler32(b"Wikipedia"));
+
+        let compressed = zlib_compress(b"Wikipedia");
+        assert_eq!(0, u16::from_be_bytes([compressed[0], compressed[1]]) % 31);
+        assert_eq!(&0x11E6_0398u32.to_be_bytes(), &compressed[compressed.len() - 4..]);
+        assert_eq!(b"Wikipedia".to_vec(), inflate(&compressed[2..]));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::deflate::zlib_compress;
+use crate::image::Image;
+use std::io;
+use std::io::Write;
+
+/// Тип значений каналов OpenEXR.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExrPixelType {
+    /// 16-битные числа с плавающей точкой.
+    Half,
+    /// 32-битные числа с плавающей точкой.
+    Float,
+}
+
+/// Сжатие блоков строк OpenEXR.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExrCompression {
+    None,
+    /// Deflate блоками по 16 строк с предсказанием разностей байт.
+    Zip,
+}
+
+impl ExrPixelType {
+    fn code(self) -> i32 {
+        match self {
+            ExrPixelType::Half => 1,
+            ExrPixelType::Float => 2,
+        }
+    }
+}
+
+impl ExrCompression {
+    fn code(self) -> u8 {
+        match self {
+            ExrCompression::None => 0,
+            ExrCompression::Zip => 3,
+        }
+    }
+
+    /// Число строк в одном блоке.
+    fn lines_per_block(self) -> usize {
+        match self {
+            ExrCompression::None => 1,
+            ExrCompression::Zip => 16,
+        }
+    }
+}
+
+/// Переводит число в 16-битный формат IEEE 754 с округлением к ближайшему четному.
+///
+/// Слишком большие значения становятся бесконечностью, слишком малые - денормализованными числами или нулем.
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x007F_FFFF;
+
+    if exponent == 0xFF {
+        // Бесконечность сохраняется, у NaN остается хотя бы один бит мантиссы.
+        let nan = if mantissa != 0 { 0x0200 } else { 0 };
+        return sign | 0x7C00 | nan;
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1F {
+        return sign | 0x7C00;
+    }
+
+    if half_exponent <= 0 {
+        if half_exponent < -10 {
+            return sign;
+        }
+        // Денормализованное число: неявная единица становится явной, мантисса сдвигается.
+        let mantissa = mantissa | 0x0080_0000;
+        let shift = (14 - half_exponent) as u32;
+        let half = mantissa >> shift;
+        let rest = mantissa & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round = (rest > halfway || (rest == halfway && half & 1 == 1)) as u32;
+        return sign | (half + round) as u16;
+    }
+
+    let half = ((half_exponent as u32) << 10) | (mantissa >> 13);
+    let rest = mantissa & 0x1FFF;
+    // Перенос при округлении может увеличить порядок, вплоть до бесконечности, что и требуется.
+    let round = (rest > 0x1000 || (rest == 0x1000 && half & 1 == 1)) as u32;
+    sign | (half + round) as u16
+}
+
+/// Подготовка данных блока к сжатию ZIP: байты с четными и нечетными номерами разделяются,
+/// после чего каждый байт заменяется разностью с предыдущим.
+fn zip_predict(data: &[u8]) -> Vec<u8> {
+    let mut reordered: Vec<u8> = data.iter().step_by(2).cloned().collect();
+    reordered.extend(data.iter().skip(1).step_by(2));
+
+    let mut previous = reordered.first().cloned().unwrap_or(0);
+    for value in reordered.iter_mut().skip(1) {
+        let current = *value;
+        *value = current.wrapping_sub(previous).wrapping_add(128);
+        previous = current;
+    }
+    reordered
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Записывает изображение в однослойный OpenEXR с построчным хранением и каналами `R`, `G` и `B`.
+///
+/// Значения пишутся в линейном пространстве без ограничения яркости.
+pub fn write_exr<W: Write>(
+    out: &mut W,
+    image: &Image,
+    pixel_type: ExrPixelType,
+    compression: ExrCompression,
+) -> io::Result<()> {
+    let (width, height) = (image.width, image.height);
+    let ints = |values: &[i32]| values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+
+    let mut channels = vec![];
+    // Каналы перечисляются по алфавиту, в том же порядке они идут в данных строки.
+    for name in ["B", "G", "R"] {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        channels.extend_from_slice(&pixel_type.code().to_le_bytes());
+        // pLinear и три зарезервированных байта, затем прореживание по x и y.
+        channels.extend_from_slice(&[0, 0, 0, 0]);
+        channels.extend_from_slice(&ints(&[1, 1]));
+    }
+    channels.push(0);
+
+    let window = ints(&[0, 0, width as i32 - 1, height as i32 - 1]);
+    let mut header = vec![0x76, 0x2F, 0x31, 0x01, 2, 0, 0, 0];
+    write_attribute(&mut header, "channels", "chlist", &channels);
+    write_attribute(&mut header, "compression", "compression", &[compression.code()]);
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    write_attribute(&mut header, "pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+    write_attribute(
+        &mut header,
+        "screenWindowCenter",
+        "v2f",
+        &[0.0f32.to_le_bytes(), 0.0f32.to_le_bytes()].concat(),
+    );
+    write_attribute(&mut header, "screenWindowWidth", "float", &1.0f32.to_le_bytes());
+    header.push(0);
+
+    let lines = compression.lines_per_block();
+    let mut blocks = vec![];
+    for first_line in (0..height).step_by(lines) {
+        let mut raw = vec![];
+        for y in first_line..(first_line + lines).min(height) {
+            for channel in [2, 1, 0] {
+                for x in 0..width {
+                    let value = image.get(x, y)[channel];
+                    match pixel_type {
+                        ExrPixelType::Half => raw.extend_from_slice(&f32_to_f16(value).to_le_bytes()),
+                        ExrPixelType::Float => raw.extend_from_slice(&value.to_le_bytes()),
+                    }
+                }
+            }
+        }
+
+        // Если сжатие не помогло, блок хранится как есть: читатель узнает об этом по размеру.
+        let data = match compression {
+            ExrCompression::None => raw,
+            ExrCompression::Zip => {
+                let compressed = zlib_compress(&zip_predict(&raw));
+                if compressed.len() < raw.len() {
+                    compressed
+                } else {
+                    raw
+                }
+            }
+        };
+
+        let mut block = (first_line as i32).to_le_bytes().to_vec();
+        block.extend_from_slice(&(data.len() as i32).to_le_bytes());
+        block.extend_from_slice(&data);
+        blocks.push(block);
+    }
+
+    // Таблица смещений блоков от начала файла следует сразу за заголовком.
+    let mut offset = (header.len() + 8 * blocks.len()) as u64;
+    for block in &blocks {
+        header.extend_from_slice(&offset.to_le_bytes());
+        offset += block.len() as u64;
+    }
+
+    out.write_all(&header)?;
+    for block in &blocks {
+        out.write_all(block)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{f32_to_f16, write_exr, zip_predict, ExrCompression, ExrPixelType};
+    use crate::image::Image;
+    use std::convert::TryInto;
+
+    #[test]
+    fn check_half_conversion() {
+        assert_eq!(0x3C00, f32_to_f16(1.0));
+        assert_eq!(0xC000, f32_to_f16(-2.0));
+        assert_eq!(0x3555, f32_to_f16(1.0 / 3.0));
+        assert_eq!(0x7BFF, f32_to_f16(65504.0));
+        assert_eq!(0x7C00, f32_to_f16(65520.0));
+        assert_eq!(0x7C00, f32_to_f16(f32::INFINITY));
+        assert_eq!(0x7E00, f32_to_f16(f32::NAN) & 0x7E00);
+        assert_eq!(0x0001, f32_to_f16(2f32.powi(-24)));
+        assert_eq!(0x0400, f32_to_f16(2f32.powi(-14)));
+        assert_eq!(0x8000, f32_to_f16(-1e-10));
+        // 1 + 2^-11 лежит ровно посередине и округляется к четной мантиссе.
+        assert_eq!(0x3C00, f32_to_f16(1.0 + 2f32.powi(-11)));
+    }
+
+    #[test]
+    fn check_zip_predictor() {
+        assert_eq!(vec![1, 130, 127, 126], zip_predict(&[1, 2, 3, 0]));
+    }
+
+    #[test]
+    fn check_exr_layout() {
+        let mut image = Image::new(2, 3);
+        image.pixels[5] = [100.0, 0.5, -0.25].into();
+        let mut data = vec![];
+        write_exr(&mut data, &image, ExrPixelType::Float, ExrCompression::None).unwrap();
+
+        assert_eq!([0x76, 0x2F, 0x31, 0x01, 2, 0, 0, 0], data[..8]);
+
+        // Три строки - три блока; последний блок содержит каналы B, G, R второго пикселя нижней строки.
+        let table = data.len() - 3 * (8 + 2 * 3 * 4) - 3 * 8;
+        let last = u64::from_le_bytes(data[table + 16..table + 24].try_into().unwrap()) as usize;
+        assert_eq!(2, i32::from_le_bytes(data[last..last + 4].try_into().unwrap()));
+        assert_eq!(24, i32::from_le_bytes(data[last + 4..last + 8].try_into().unwrap()));
+        let value = |i: usize| f32::from_le_bytes(data[last + 8 + 4 * i..last + 12 + 4 * i].try_into().unwrap());
+        assert_eq!((-0.25, 0.5, 100.0), (value(1), value(3), value(5)));
+    }
+}
@@ -0,0 +1,69 @@
+use crate::geom::Vec3;
+use crate::image::Image;
+
+/// Буфер кадра: для каждого пикселя хранится сумма цветов сэмплов и их количество.
+///
+/// Пиксели идут построчно сверху вниз, слева направо. Итоговый цвет пикселя - среднее его сэмплов,
+/// поэтому разные пиксели могут получить разное число сэмплов.
+pub struct Film {
+    pub width: usize,
+    pub height: usize,
+    sums: Vec<Vec3>,
+    counts: Vec<u32>,
+}
+
+impl Film {
+    /// Создает пустой буфер заданного размера.
+    pub fn new(width: usize, height: usize) -> Self {
+        Film {
+            width,
+            height,
+            sums: vec![Vec3::default(); width * height],
+            counts: vec![0; width * height],
+        }
+    }
+
+    /// Добавляет к пикселю в столбце `x` и строке `y` сумму `sum` из `count` сэмплов.
+    pub fn add_samples(&mut self, x: usize, y: usize, sum: Vec3, count: u32) {
+        let index = y * self.width + x;
+        self.sums[index] += sum;
+        self.counts[index] += count;
+    }
+
+    /// Средний цвет пикселя; черный, если у пикселя нет сэмплов.
+    pub fn pixel(&self, x: usize, y: usize) -> Vec3 {
+        let index = y * self.width + x;
+        match self.counts[index] {
+            0 => Vec3::default(),
+            count => self.sums[index] / count as f32,
+        }
+    }
+
+    /// Изображение из средних цветов пикселей в линейном пространстве, без ограничения яркости.
+    pub fn to_image(&self) -> Image {
+        let mut image = Image::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                image.pixels[y * self.width + x] = self.pixel(x, y);
+            }
+        }
+        image
+    }
+
+    /// Число сэмплов пикселя.
+    pub fn sample_count(&self, x: usize, y: usize) -> u32 {
+        self.counts[y * self.width + x]
+    }
+
+    /// Отладочное изображение числа сэмплов: яркость пикселя - его число сэмплов,
+    /// деленное на наибольшее по кадру.
+    pub fn sample_map(&self) -> Image {
+        let max = self.counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+        let mut image = Image::new(self.width, self.height);
+        for (pixel, &count) in image.pixels.iter_mut().zip(&self.counts) {
+            let value = count as f32 / max;
+            *pixel = Vec3::new(value, value, value);
+        }
+        image
+    }
+}
@@ -9,7 +9,7 @@ pub struct Vec3(pub [f32; 3]);
 impl Vec3 {
     /// Создает новый вектор.
     pub fn new(x: f32, y: f32, z: f32) -> Self {
-        Self { 0: [x, y, z] }
+        Self([x, y, z])
     }
 
     /// Возвращает длину вектора.
@@ -32,7 +32,7 @@ impl Vec3 {
 /// Конструктор по умолчанию.
 impl Default for Vec3 {
     fn default() -> Self {
-        Vec3 { 0: [0.0, 0.0, 0.0] }
+        Vec3([0.0, 0.0, 0.0])
     }
 }
 
@@ -56,9 +56,7 @@ impl Neg for Vec3 {
     type Output = Vec3;
 
     fn neg(self) -> Self::Output {
-        Vec3 {
-            0: [-self.0[0], -self.0[1], -self.0[2]],
-        }
+        Vec3([-self.0[0], -self.0[1], -self.0[2]])
     }
 }
 
@@ -85,13 +83,11 @@ impl Add for Vec3 {
     type Output = Vec3;
 
     fn add(self, rhs: Self) -> Self::Output {
-        Self {
-            0: [
-                self.0[0] + rhs.0[0],
-                self.0[1] + rhs.0[1],
-                self.0[2] + rhs.0[2],
-            ],
-        }
+        Self([
+            self.0[0] + rhs.0[0],
+            self.0[1] + rhs.0[1],
+            self.0[2] + rhs.0[2],
+        ])
     }
 }
 
@@ -102,13 +98,11 @@ impl Add<f32> for Vec3 {
     type Output = Vec3;
 
     fn add(self, rhs: f32) -> Self::Output {
-        Self {
-            0: [
-                self.0[0] + rhs,
-                self.0[1] + rhs,
-                self.0[2] + rhs,
-            ],
-        }
+        Self([
+            self.0[0] + rhs,
+            self.0[1] + rhs,
+            self.0[2] + rhs,
+        ])
     }
 }
 
@@ -128,13 +122,11 @@ impl Sub for Vec3 {
     type Output = Vec3;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        Self {
-            0: [
-                self.0[0] - rhs.0[0],
-                self.0[1] - rhs.0[1],
-                self.0[2] - rhs.0[2],
-            ],
-        }
+        Self([
+            self.0[0] - rhs.0[0],
+            self.0[1] - rhs.0[1],
+            self.0[2] - rhs.0[2],
+        ])
     }
 }
 
@@ -154,13 +146,11 @@ impl Mul for Vec3 {
     type Output = Vec3;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        Self {
-            0: [
-                self.0[0] * rhs.0[0],
-                self.0[1] * rhs.0[1],
-                self.0[2] * rhs.0[2],
-            ],
-        }
+        Self([
+            self.0[0] * rhs.0[0],
+            self.0[1] * rhs.0[1],
+            self.0[2] * rhs.0[2],
+        ])
     }
 }
 
@@ -203,13 +193,11 @@ impl Div for Vec3 {
     type Output = Vec3;
 
     fn div(self, rhs: Self) -> Self::Output {
-        Self {
-            0: [
-                self.0[0] / rhs.0[0],
-                self.0[1] / rhs.0[1],
-                self.0[2] / rhs.0[2],
-            ],
-        }
+        Self([
+            self.0[0] / rhs.0[0],
+            self.0[1] / rhs.0[1],
+            self.0[2] / rhs.0[2],
+        ])
     }
 }
 
@@ -271,18 +259,16 @@ pub fn dot(a: Vec3, b: Vec3) -> f32 {
 /// [Подробнее]: https://ru.wikipedia.org/wiki/%D0%92%D0%B5%D0%BA%D1%82%D0%BE%D1%80%D0%BD%D0%BE%D0%B5_%D0%BF%D1%80%D0%BE%D0%B8%D0%B7%D0%B2%D0%B5%D0%B4%D0%B5%D0%BD%D0%B8%D0%B5
 #[allow(dead_code)]
 pub fn cross(a: Vec3, b: Vec3) -> Vec3 {
-    Vec3 {
-        0: [
-            a.0[1] * b.0[2] - a.0[2] * b.0[1],
-            a.0[2] * b.0[0] - a.0[0] * b.0[2],
-            a.0[0] * b.0[1] - a.0[1] * b.0[0],
-        ],
-    }
+    Vec3([
+        a.0[1] * b.0[2] - a.0[2] * b.0[1],
+        a.0[2] * b.0[0] - a.0[0] * b.0[2],
+        a.0[0] * b.0[1] - a.0[1] * b.0[0],
+    ])
 }
 
 impl From<[f32; 3]> for Vec3 {
     fn from(a: [f32; 3]) -> Self {
-        Self {0: a}
+        Self(a)
     }
 }
 
@@ -290,3 +276,169 @@ impl From<[f32; 3]> for Vec3 {
 pub fn unit_vector(a: Vec3) -> Vec3 {
     a / a.length()
 }
+
+/// Ортонормированный базис `(u, v, w)`.
+#[derive(Copy, Clone, Debug)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    /// Строит базис, в котором `w` направлен вдоль `n`.
+    ///
+    /// Построение без ветвлений из статьи Duff et al., "Building an Orthonormal Basis, Revisited".
+    pub fn from_w(n: Vec3) -> Self {
+        let w = unit_vector(n);
+        let sign = 1f32.copysign(w[2]);
+        let a = -1.0 / (sign + w[2]);
+        let b = w[0] * w[1] * a;
+
+        Onb {
+            u: Vec3::new(1.0 + sign * w[0] * w[0] * a, sign * b, -sign * w[0]),
+            v: Vec3::new(b, sign + w[1] * w[1] * a, -w[1]),
+            w,
+        }
+    }
+
+    /// Переводит вектор с координатами `a` в этом базисе в мировую систему координат.
+    pub fn local(&self, a: Vec3) -> Vec3 {
+        a[0] * self.u + a[1] * self.v + a[2] * self.w
+    }
+}
+
+/// Матрица 4x4 для аффинных преобразований точек и векторов.
+///
+/// Хранится по строкам, точки и векторы считаются столбцами: `p' = M * p`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Mat4(pub [[f32; 4]; 4]);
+
+impl Mat4 {
+    /// Единичная матрица.
+    pub fn identity() -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Mat4(m)
+    }
+
+    /// Матрица переноса на вектор `offset`.
+    pub fn translation(offset: Vec3) -> Self {
+        let mut m = Self::identity();
+        for i in 0..3 {
+            m.0[i][3] = offset[i];
+        }
+        m
+    }
+
+    /// Матрица масштабирования с коэффициентами по осям из вектора `scale`.
+    pub fn scaling(scale: Vec3) -> Self {
+        let mut m = Self::identity();
+        for i in 0..3 {
+            m.0[i][i] = scale[i];
+        }
+        m
+    }
+
+    /// Матрица поворота на угол `degrees` вокруг оси `axis`, проходящей через начало координат.
+    ///
+    /// Поворот выполняется против часовой стрелки, если смотреть с конца оси (формула Родрига).
+    pub fn rotation(axis: Vec3, degrees: f32) -> Self {
+        let a = unit_vector(axis);
+        let (sin, cos) = (degrees * std::f32::consts::PI / 180.0).sin_cos();
+        let t = 1.0 - cos;
+        let (x, y, z) = (a[0], a[1], a[2]);
+
+        Mat4([
+            [t * x * x + cos, t * x * y - sin * z, t * x * z + sin * y, 0.0],
+            [t * x * y + sin * z, t * y * y + cos, t * y * z - sin * x, 0.0],
+            [t * x * z - sin * y, t * y * z + sin * x, t * z * z + cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Транспонированная матрица.
+    pub fn transpose(&self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.0[j][i];
+            }
+        }
+        Mat4(m)
+    }
+
+    /// Обратная матрица, вычисленная методом Гаусса-Жордана с выбором главного элемента.
+    ///
+    /// Возвращает `None` для вырожденной матрицы.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.0;
+        let mut inv = Self::identity().0;
+
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let k = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= k;
+                inv[col][j] *= k;
+            }
+
+            for row in 0..4 {
+                if row != col {
+                    let factor = a[row][col];
+                    for j in 0..4 {
+                        a[row][j] -= factor * a[col][j];
+                        inv[row][j] -= factor * inv[col][j];
+                    }
+                }
+            }
+        }
+
+        Some(Mat4(inv))
+    }
+
+    /// Преобразует точку: учитываются и линейная часть, и перенос.
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        let m = &self.0;
+        Vec3::new(
+            m[0][0] * p[0] + m[0][1] * p[1] + m[0][2] * p[2] + m[0][3],
+            m[1][0] * p[0] + m[1][1] * p[1] + m[1][2] * p[2] + m[1][3],
+            m[2][0] * p[0] + m[2][1] * p[1] + m[2][2] * p[2] + m[2][3],
+        )
+    }
+
+    /// Преобразует вектор: перенос не учитывается.
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.0;
+        Vec3::new(
+            m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+            m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+            m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+        )
+    }
+}
+
+/// Оператор умножения матриц.
+///
+/// Преобразование `a * b` сначала применяет `b`, затем `a`.
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.0[i][k] * rhs.0[k][j]).sum();
+            }
+        }
+        Mat4(m)
+    }
+}
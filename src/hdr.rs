@@ -0,0 +1,161 @@
+use crate::geom::Vec3;
+use crate::image::Image;
+use std::io;
+use std::io::Write;
+
+/// Кодирует цвет в формат RGBE: три мантиссы и общий двоичный порядок.
+///
+/// Отрицательные компоненты заменяются нулем, так как формат хранит только неотрицательную яркость.
+fn rgbe(color: Vec3) -> [u8; 4] {
+    let [r, g, b] = color.0.map(|x| x.max(0.0));
+    let max = r.max(g).max(b);
+    if max < 1e-32 || !max.is_finite() {
+        return [0, 0, 0, 0];
+    }
+
+    // Порядок e такой, что max = m * 2^e при m из [0.5; 1).
+    let exponent = max.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2f32.powi(exponent);
+    let byte = |x: f32| ((x * scale) as u32).min(255) as u8;
+
+    [byte(r), byte(g), byte(b), (exponent + 128).clamp(0, 255) as u8]
+}
+
+/// Сжимает одну компоненту строки по правилам нового RLE формата Radiance.
+///
+/// Серии от четырех одинаковых байт записываются как `128 + длина` и значение,
+/// остальные байты - группами до 128 штук с длиной группы в начале.
+fn rle_component(values: &[u8], out: &mut Vec<u8>) {
+    const MIN_RUN: usize = 4;
+    let mut i = 0;
+
+    while i < values.len() {
+        // Ищется начало ближайшей серии, чтобы записать байты до нее как есть.
+        let mut run_start = i;
+        let mut run_length = 0;
+        while run_start < values.len() {
+            run_length = values[run_start..]
+                .iter()
+                .take(127)
+                .take_while(|&&v| v == values[run_start])
+                .count();
+            if run_length >= MIN_RUN {
+                break;
+            }
+            run_start += run_length;
+        }
+        if run_length < MIN_RUN {
+            run_start = values.len();
+        }
+
+        while i < run_start {
+            let count = (run_start - i).min(128);
+            out.push(count as u8);
+            out.extend_from_slice(&values[i..i + count]);
+            i += count;
+        }
+
+        if run_start < values.len() {
+            out.push(128 + run_length as u8);
+            out.push(values[run_start]);
+            i = run_start + run_length;
+        }
+    }
+}
+
+/// Записывает изображение в формате Radiance HDR (RGBE) в линейном пространстве.
+///
+/// Строки шириной от 8 до 32767 точек сжимаются RLE по компонентам, остальные записываются без сжатия.
+pub fn write_hdr<W: Write>(out: &mut W, image: &Image) -> io::Result<()> {
+    write!(
+        out,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        image.height, image.width
+    )?;
+
+    let rle = (8..0x8000).contains(&image.width);
+    let mut data = Vec::with_capacity(image.width * image.height * 4);
+    for row in image.pixels.chunks(image.width.max(1)) {
+        let pixels: Vec<[u8; 4]> = row.iter().map(|&color| rgbe(color)).collect();
+        if !rle {
+            data.extend(pixels.iter().flatten());
+            continue;
+        }
+
+        data.extend_from_slice(&[2, 2, (image.width >> 8) as u8, image.width as u8]);
+        for component in 0..4 {
+            let values: Vec<u8> = pixels.iter().map(|p| p[component]).collect();
+            rle_component(&values, &mut data);
+        }
+    }
+
+    out.write_all(&data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{rgbe, rle_component, write_hdr};
+    use crate::geom::Vec3;
+    use crate::image::Image;
+
+    fn from_rgbe(p: &[u8]) -> Vec3 {
+        if p[3] == 0 {
+            return Vec3::default();
+        }
+        let scale = 2f32.powi(p[3] as i32 - 136);
+        Vec3::new(p[0] as f32 + 0.5, p[1] as f32 + 0.5, p[2] as f32 + 0.5) * scale
+    }
+
+    fn unrle(data: &[u8], count: usize) -> (Vec<u8>, usize) {
+        let (mut values, mut pos) = (vec![], 0);
+        while values.len() < count {
+            let n = data[pos] as usize;
+            if n > 128 {
+                values.extend(std::iter::repeat_n(data[pos + 1], n - 128));
+                pos += 2;
+            } else {
+                values.extend_from_slice(&data[pos + 1..pos + 1 + n]);
+                pos += 1 + n;
+            }
+        }
+        (values, pos)
+    }
+
+    #[test]
+    fn check_rgbe_keeps_hdr_values() {
+        for &color in &[
+            Vec3::new(1.0, 0.5, 0.25),
+            Vec3::new(1500.0, 3.0, 0.0),
+            Vec3::new(0.001, 0.002, 0.003),
+        ] {
+            let decoded = from_rgbe(&rgbe(color));
+            for c in 0..3 {
+                assert!((decoded[c] - color[c]).abs() <= color[0].max(color[1]).max(color[2]) / 128.0);
+            }
+        }
+        assert_eq!([0, 0, 0, 0], rgbe(Vec3::new(-1.0, 0.0, f32::NAN)));
+    }
+
+    #[test]
+    fn check_rle_round_trip() {
+        let mut values = vec![1, 2, 3, 3, 3, 7, 7, 7, 7, 7, 7, 9];
+        values.extend(std::iter::repeat_n(5, 300));
+        values.extend((0..200).map(|i| i as u8));
+        let mut data = vec![];
+        rle_component(&values, &mut data);
+
+        assert_eq!((values.clone(), data.len()), unrle(&data, values.len()));
+        assert!(data.len() < values.len());
+    }
+
+    #[test]
+    fn check_hdr_scanline_header() {
+        let image = Image::new(10, 2);
+        let mut data = vec![];
+        write_hdr(&mut data, &image).unwrap();
+
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 10\n";
+        assert_eq!(header, &data[..header.len()]);
+        assert_eq!([2, 2, 0, 10], data[header.len()..header.len() + 4]);
+    }
+}
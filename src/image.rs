@@ -0,0 +1,26 @@
+use crate::geom::Vec3;
+
+/// Изображение в памяти: цвета пикселей построчно сверху вниз, слева направо.
+///
+/// Компоненты цвета хранятся как числа с плавающей точкой, обычно в диапазоне `[0; 1]`.
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec3>,
+}
+
+impl Image {
+    /// Создает черное изображение заданного размера.
+    pub fn new(width: usize, height: usize) -> Self {
+        Image {
+            width,
+            height,
+            pixels: vec![Vec3::default(); width * height],
+        }
+    }
+
+    /// Цвет пикселя в столбце `x` и строке `y`, считая от верхнего края.
+    pub fn get(&self, x: usize, y: usize) -> Vec3 {
+        self.pixels[y * self.width + x]
+    }
+}
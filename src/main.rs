@@ -1,26 +1,69 @@
+mod aabb;
 mod bodies;
+mod bvh;
 mod camera;
+mod cli;
+mod deflate;
+mod exr;
+mod film;
 mod geom;
+mod hdr;
+mod image;
 mod materials;
+mod obj;
+mod mesh;
+mod pdf;
+mod perlin;
+mod pfm;
+mod ply;
+mod png;
 mod ppm;
 mod ray;
+mod render;
+mod rng;
+mod sampler;
+mod scene;
+mod scenes;
+mod stl;
+mod texture;
+mod tonemap;
+mod toml;
+mod transform;
 mod utils;
+mod volume;
 
-use crate::bodies::{HitRecord, Hittable, Sphere};
-use crate::camera::Camera;
+use crate::aabb::{surrounding_box, Aabb};
+use crate::bodies::{HitRecord, Hittable};
+use crate::bvh::SahBvh;
+use crate::cli::{parse_args, usage, Command, Options, OutputFormat, SceneSource};
 use crate::geom::{unit_vector, Vec3};
-use crate::materials::{Dielectric, Lambert, Metal, Material};
-use crate::ppm::{write_color, write_ppm_header};
+use crate::image::Image;
+use crate::materials::ScatterRecord;
+use crate::exr::write_exr;
+use crate::hdr::write_hdr;
+use crate::pfm::write_pfm;
+use crate::png::write_png;
+use crate::ppm::{write_ppm, write_ppm_ascii};
+use crate::pdf::{HittablePdf, MixturePdf, Pdf};
 use crate::ray::Ray;
-use crate::utils::{lerp, random_range};
-use std::rc::Rc;
+use crate::render::{render, AdaptiveSampling, RenderSettings};
+use crate::scene::load_scene;
+use crate::scenes::builtin_scene;
+use crate::tonemap::ToneMapping;
+use crate::rng::{mix, Pcg32};
+use crate::sampler::Sampler;
+use crate::utils::lerp;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, io, process};
 
 mod color {
     use crate::geom::Vec3;
 
-    pub const BLACK: Vec3 = Vec3 { 0: [0.0, 0.0, 0.0] };
-    pub const WHITE: Vec3 = Vec3 { 0: [1.0, 1.0, 1.0] };
-    pub const LIGHT_BLUE: Vec3 = Vec3 { 0: [0.5, 0.7, 1.0] };
+    pub const BLACK: Vec3 = Vec3([0.0, 0.0, 0.0]);
+    pub const WHITE: Vec3 = Vec3([1.0, 1.0, 1.0]);
+    pub const LIGHT_BLUE: Vec3 = Vec3([0.5, 0.7, 1.0]);
 }
 
 /// Типаж для описания вектора как точки в пространстве с координатами `x`, `y` и `z`.
@@ -65,144 +108,390 @@ impl Point for Vec3 {
     }
 }
 
+/// Фон сцены - цвет, который видит луч, не попавший ни в один объект.
+#[derive(Copy, Clone)]
+pub enum Background {
+    /// Однотонный фон.
+    Solid(Vec3),
+    /// Вертикальный градиент неба от цвета `bottom` у горизонта к цвету `top` в зените.
+    Gradient { bottom: Vec3, top: Vec3 },
+}
+
+impl Background {
+    /// Цвет фона в направлении луча.
+    pub fn color(&self, ray: &Ray) -> Vec3 {
+        match *self {
+            Background::Solid(color) => color,
+            Background::Gradient { bottom, top } => {
+                let unit_direction = unit_vector(ray.direction());
+                let t = 0.5 * (unit_direction.y() + 1.0);
+
+                lerp(bottom, top, t)
+            }
+        }
+    }
+}
+
+/// Вероятность продолжения пути в русской рулетке не больше этой величины,
+/// поэтому даже яркий путь рано или поздно обрывается.
+const MAX_SURVIVAL_PROBABILITY: f32 = 0.95;
+
 /// Вычисляет цвет точки на экране.
-fn ray_color<T>(ray: &Ray, world: &T, depth: i32) -> Vec3
+///
+/// Путь луча прослеживается в цикле: после каждого отражения его вклад (`throughput`) умножается
+/// на ослабление материала. Путь обрывается после `depth` отражений, а начиная с отражения
+/// `roulette_depth` продолжается с вероятностью по наибольшей компоненте вклада, который при этом
+/// делится на эту вероятность. Поэтому ожидаемый цвет тот же, что и без рулетки.
+///
+/// Направление рассеяния `ScatterRecord::Diffuse` выбирается поровну по плотности материала и на один
+/// из источников света `lights`, а вклад делится на плотность этой смеси. Без источников света
+/// направления выбираются только по плотности материала.
+fn ray_color<T>(
+    mut ray: Ray,
+    world: &T,
+    lights: &World,
+    background: &Background,
+    depth: i32,
+    roulette_depth: i32,
+    sampler: &mut dyn Sampler,
+) -> Vec3
 where
     T: Hittable,
 {
-    if depth == 0 {
-        return color::BLACK;
-    }
-
-    let record = world.hit(ray, 0.001, f32::MAX);
-    match record {
-        Some(hit) => match hit.material.scatter(ray, &hit) {
-            Some((scattered, attenuation)) => attenuation * ray_color(&scattered, world, depth - 1),
-            None => color::BLACK,
-        },
-        None => {
-            let unit_direction = unit_vector(ray.direction());
-            let t = 0.5 * (unit_direction.y() + 1.0);
+    let mut color = color::BLACK;
+    let mut throughput = color::WHITE;
+
+    for bounce in 0..depth {
+        let hit = match world.hit(&ray, 0.001, f32::MAX, sampler) {
+            Some(hit) => hit,
+            None => return color + throughput * background.color(&ray),
+        };
+
+        color += throughput * hit.material.emitted(hit.u, hit.v, hit.point);
+        match hit.material.scatter(&ray, &hit, sampler) {
+            None => return color,
+            Some(ScatterRecord::Specular { ray: scattered, attenuation }) => {
+                throughput *= attenuation;
+                ray = scattered;
+            }
+            Some(ScatterRecord::Diffuse { attenuation, pdf: material_pdf }) => {
+                let light_pdf = HittablePdf::new(lights, hit.point);
+                let mixture = MixturePdf::new(&light_pdf, material_pdf.as_ref());
+                let pdf: &dyn Pdf = if lights.0.is_empty() { material_pdf.as_ref() } else { &mixture };
+
+                let scattered = Ray::new(hit.point, pdf.generate(sampler), ray.time());
+                let pdf_value = pdf.value(scattered.direction());
+                if pdf_value.is_nan() || pdf_value <= 0.0 {
+                    return color;
+                }
+                throughput *= attenuation * (hit.material.scattering_pdf(&ray, &hit, &scattered) / pdf_value);
+                ray = scattered;
+            }
+        }
 
-            lerp(color::WHITE, color::LIGHT_BLUE, t)
+        if bounce + 1 >= roulette_depth {
+            let survival = throughput.r().max(throughput.g()).max(throughput.b()).min(MAX_SURVIVAL_PROBABILITY);
+            if sampler.next_1d() >= survival {
+                return color;
+            }
+            throughput /= survival;
         }
     }
+
+    color
 }
 
 /// Массив объектов трехмерной сцены.
 pub struct World(Vec<Box<dyn Hittable>>);
 
 impl Hittable for World {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut dyn Sampler) -> Option<HitRecord> {
         let mut rec: Option<HitRecord> = None;
         let mut closest_so_far = t_max;
 
         for object in &self.0 {
-            if let Some(obj) = object.hit(ray, t_min, closest_so_far) {
+            if let Some(obj) = object.hit(ray, t_min, closest_so_far, sampler) {
                 closest_so_far = obj.t;
                 rec = Some(obj);
             }
         }
         rec
     }
-}
 
-fn random_scene() -> World {
-    let mut scene: Vec<Box<dyn Hittable>> = vec![];
-
-    // Шар - земля.
-    scene.push(Box::new(Sphere{center: [0.0, -1000.0, 0.0].into(), radius: 1000.0,
-        material: Rc::new(Lambert{albedo: [0.5, 0.5, 0.5].into()})
-    }));
-
-    // Случайно рассыпанные шарики.
-    for a in -11..11 {
-        for b in -11..11 {
-            let material_rate = random_range(0.0, 1.0);
-            let x = random_range(0.0, 1.0);
-            let z = random_range(0.0, 1.0);
-            let center: Vec3 = [a as f32 + 0.9 * x, 0.2, b as f32 + 0.9 * z].into();
-
-            if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
-                let x = random_range(0.0, 1.0);
-                let y = random_range(0.0, 1.0);
-                let z = random_range(0.0, 1.0);
-                let fuzz = random_range(0.0, 1.0);
-
-                let material: Rc<dyn Material>;
-                if material_rate < 0.8 {
-                    material = Rc::new(Lambert{albedo: [x * x, y * y, z * z].into()});
-                } else if material_rate < 0.95 {
-                    material = Rc::new(Metal::with_albedo_fuzz([0.5 * (1.0 + x), 0.5 * (1.0 + y), 0.5 * (1.0 + z)].into(), fuzz));
-                } else {
-                    material = Rc::new(Dielectric{ir: 1.5});
-                }
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        let mut objects = self.0.iter();
+        let first = objects.next()?.bounding_box(time0, time1)?;
 
-                scene.push(Box::new(Sphere{center, radius: 0.2, material}));
-            }
+        objects.try_fold(first, |acc, object| {
+            object
+                .bounding_box(time0, time1)
+                .map(|bbox| surrounding_box(acc, bbox))
+        })
+    }
+
+    /// Направление выбирается на случайный объект списка, поэтому плотность - среднее плотностей объектов.
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        if self.0.is_empty() {
+            return 0.0;
         }
+        self.0.iter().map(|object| object.pdf_value(origin, direction)).sum::<f32>() / self.0.len() as f32
     }
 
-    // Три больших шарика в центре.
-    scene.push(Box::new(Sphere{center: [0.0, 1.0, 0.0].into(), radius: 1.0,
-        material: Rc::new(Dielectric{ir: 1.5})
-    }));
-    scene.push(Box::new(Sphere{center: [-4.0, 1.0, 0.0].into(), radius: 1.0,
-        material: Rc::new(Lambert{albedo: [0.4, 0.2, 0.1].into()})
-    }));
-    scene.push(Box::new(Sphere{center: [4.0, 1.0, 0.0].into(), radius: 1.0,
-        material: Rc::new(Metal::with_albedo_fuzz([0.7, 0.6, 0.5].into(), 0.0))
-    }));
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        if self.0.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let index = ((sampler.next_1d() * self.0.len() as f32) as usize).min(self.0.len() - 1);
+        self.0[index].random(origin, sampler)
+    }
+}
 
-    World(scene)
+/// Зерно из текущего времени и номера процесса для запусков без `--seed`.
+fn clock_seed() -> u64 {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+    mix(nanos ^ ((process::id() as u64) << 32))
 }
 
 fn main() {
-    // Image
-    let aspect_ratio: f32 = 3.0 / 2.0;
-    let image_width = 1200;
-    let image_height = (image_width as f32 / aspect_ratio) as i32;
-    let samples_per_pixel = 500;
-    let depth = 50;
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Command::Render(options)) => options,
+        Ok(Command::Help) => {
+            print!("{}", usage());
+            return;
+        }
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, usage());
+            process::exit(2);
+        }
+    };
+
+    // Без явного зерна оно выбирается случайно и печатается, чтобы удачный кадр можно было повторить.
+    let seed = options.seed.unwrap_or_else(clock_seed);
+    eprintln!("Seed: {}", seed);
+
+    let mut scene = match &options.scene {
+        SceneSource::File(path) => load_scene(path).unwrap_or_else(|err| {
+            eprintln!("Can not load {}: {}", path, err);
+            process::exit(1);
+        }),
+        SceneSource::Builtin(number) => builtin_scene(*number, &mut Pcg32::new(seed, 0)).unwrap_or_else(|err| {
+            eprintln!("Can not load scene {}: {}", number, err);
+            process::exit(1);
+        }),
+    };
+    if let Err(err) = options.apply(&mut scene) {
+        eprintln!("error: {}", err);
+        process::exit(2);
+    }
 
-    // World
-    let world = random_scene();
+    let image_width = scene.image_width;
+    let image_height = scene.image_height();
+    let samples_per_pixel = scene.samples_per_pixel;
+    let world = SahBvh::new(scene.world, scene.camera.time0, scene.camera.time1);
+    eprintln!("BVH: {}", world.stats());
 
     // Camera
-    let lookfrom: Vec3 = [13.0, 2.0, 3.0].into();
-    let lookat: Vec3 = [0.0, 0.0, 0.0].into();
-    let vup: Vec3 = [0.0, 1.0, 0.0].into();
-    let dist_to_focus = 10.0;
-    let aperture = 0.1;
-
-    let camera = Camera::new(
-        lookfrom,
-        lookat,
-        vup,
-        20.0,
-        aspect_ratio,
-        aperture,
-        dist_to_focus,
-    );
+    let camera = scene.camera.build(scene.aspect_ratio);
 
     // Render
-    write_ppm_header(image_width, image_height);
-
-    for j in (0..image_height).rev() {
-        eprintln!("Scanlines remaining: {}", j + 1);
-        for i in 0..image_width {
-            let mut pixel = Vec3::default();
-            for _ in 0..samples_per_pixel {
-                let x = random_range(0.0, 1.0);
-                let y = random_range(0.0, 1.0);
-                let u = (i as f32 + x) / (image_width - 1) as f32;
-                let v = (j as f32 + y) / (image_height - 1) as f32;
-                let ray = camera.get_ray(u, v);
-
-                pixel += ray_color(&ray, &world, depth);
-            }
-            write_color(pixel, samples_per_pixel);
+    let settings = RenderSettings {
+        image_width,
+        image_height,
+        samples_per_pixel,
+        depth: scene.depth,
+        roulette_depth: scene.roulette_depth,
+        threads: options.threads.unwrap_or_else(RenderSettings::default_threads),
+        tile_size: 16,
+        seed,
+        sampler: options.sampler,
+        adaptive: options.adaptive_threshold.map(|threshold| AdaptiveSampling {
+            min_samples: options.min_samples.unwrap_or(16),
+            threshold,
+        }),
+    };
+    let film = render(&world, &scene.lights, &camera, &scene.background, &settings);
+    if settings.adaptive.is_some() {
+        let total: u64 = (0..film.height)
+            .flat_map(|y| (0..film.width).map(move |x| (x, y)))
+            .map(|(x, y)| film.sample_count(x, y) as u64)
+            .sum();
+        eprintln!("Average samples per pixel: {:.1}", total as f64 / (film.width * film.height) as f64);
+    }
+    let image = film.to_image();
+
+    let written = match &options.output {
+        Some(path) => File::create(path).and_then(|file| {
+            write_image(BufWriter::new(file), &image, options.format, &options.tone_mapping, &options)
+        }),
+        None => write_image(BufWriter::new(io::stdout().lock()), &image, options.format, &options.tone_mapping, &options),
+    };
+    if let Err(err) = written {
+        eprintln!("Can not write {}: {}", options.output.as_deref().unwrap_or("image"), err);
+        process::exit(1);
+    }
+
+    if let Some((path, format)) = &options.sample_map {
+        // Карта сэмплов уже лежит в [0; 1], поэтому экспозиция и тональная компрессия кадра к ней не применяются.
+        let map = film.sample_map();
+        let written = File::create(path)
+            .and_then(|file| write_image(BufWriter::new(file), &map, *format, &ToneMapping::default(), &options));
+        if let Err(err) = written {
+            eprintln!("Can not write {}: {}", path, err);
+            process::exit(1);
         }
     }
     eprintln!("Done.");
 }
+
+/// Записывает изображение в формате `format`.
+fn write_image<W: Write>(
+    mut out: W,
+    image: &Image,
+    format: OutputFormat,
+    tone_mapping: &ToneMapping,
+    options: &Options,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Ppm => write_ppm(&mut out, image, tone_mapping)?,
+        OutputFormat::PpmAscii => write_ppm_ascii(&mut out, image, tone_mapping)?,
+        OutputFormat::Pfm => write_pfm(&mut out, image)?,
+        OutputFormat::Png => write_png(&mut out, image, tone_mapping)?,
+        OutputFormat::Hdr => write_hdr(&mut out, image)?,
+        OutputFormat::Exr => write_exr(&mut out, image, options.exr_pixel_type, options.exr_compression)?,
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{color, ray_color, Background, World};
+    use crate::bodies::{Hittable, Sphere, XZRect};
+    use crate::geom::Vec3;
+    use crate::materials::{DiffuseLight, Lambert, Material, Metal, ScatterRecord};
+    use crate::ray::Ray;
+    use crate::sampler::{IndependentSampler, Sampler};
+    use std::sync::Arc;
+
+    /// Рекурсивный вариант `ray_color` без русской рулетки и без выборки направлений на свет.
+    fn recursive_ray_color(ray: &Ray, world: &World, background: &Background, depth: i32, sampler: &mut dyn Sampler) -> Vec3 {
+        if depth == 0 {
+            return color::BLACK;
+        }
+
+        match world.hit(ray, 0.001, f32::MAX, sampler) {
+            Some(hit) => {
+                let emitted = hit.material.emitted(hit.u, hit.v, hit.point);
+                match hit.material.scatter(ray, &hit, sampler) {
+                    Some(ScatterRecord::Specular { ray: scattered, attenuation }) => {
+                        emitted + attenuation * recursive_ray_color(&scattered, world, background, depth - 1, sampler)
+                    }
+                    Some(ScatterRecord::Diffuse { attenuation, pdf }) => {
+                        let scattered = Ray::new(hit.point, pdf.generate(sampler), ray.time());
+                        let weight = hit.material.scattering_pdf(ray, &hit, &scattered) / pdf.value(scattered.direction());
+                        emitted + weight * attenuation * recursive_ray_color(&scattered, world, background, depth - 1, sampler)
+                    }
+                    None => emitted,
+                }
+            }
+            None => background.color(ray),
+        }
+    }
+
+    fn sphere(center: [f32; 3], radius: f32, material: Arc<dyn Material>) -> Box<dyn Hittable> {
+        Box::new(Sphere { center: center.into(), radius, material })
+    }
+
+    /// Шары на полу и светящийся шар над ними.
+    fn world() -> World {
+        World(vec![
+            sphere([0.0, -100.5, 0.0], 100.0, Arc::new(Lambert::with_color([0.8, 0.8, 0.8].into()))),
+            sphere([0.0, 0.0, 0.0], 0.5, Arc::new(Lambert::with_color([0.7, 0.3, 0.3].into()))),
+            sphere([1.0, 0.0, 0.0], 0.5, Arc::new(Metal::with_albedo_fuzz([0.8, 0.6, 0.2].into(), 0.3))),
+            sphere([-1.0, 1.0, 0.0], 0.3, Arc::new(DiffuseLight::with_color([4.0, 4.0, 4.0].into()))),
+        ])
+    }
+
+    fn ray(x: f32) -> Ray {
+        Ray { orig: [0.0, 0.3, 3.0].into(), dir: Vec3::new(x, -0.3, -3.0), time: 0.0 }
+    }
+
+    const SAMPLES: u32 = 20000;
+
+    /// Среднее значение цвета и дисперсия его красной компоненты по `SAMPLES` сэмплам.
+    fn estimate(color: &mut dyn FnMut(&mut IndependentSampler) -> Vec3) -> (Vec3, f32) {
+        let mut sampler = IndependentSampler::new(1);
+        let mut sum = Vec3::default();
+        let mut sum_squared = 0.0;
+        for index in 0..SAMPLES {
+            sampler.start_sample(0, 0, index);
+            let value = color(&mut sampler);
+            sum += value;
+            sum_squared += value[0] * value[0];
+        }
+        let mean = sum / SAMPLES as f32;
+        (mean, sum_squared / SAMPLES as f32 - mean[0] * mean[0])
+    }
+
+    fn assert_close(actual: Vec3, expected: Vec3, message: &str) {
+        for channel in 0..3 {
+            let error = (actual[channel] - expected[channel]).abs() / expected[channel];
+            assert!(error < 0.03, "{}: {:?} vs {:?}", message, actual, expected);
+        }
+    }
+
+    #[test]
+    fn check_background_color() {
+        let direction = |y: f32| Ray::new(Vec3::default(), Vec3::new(1.0, y, 0.0), 0.0);
+        let solid = Background::Solid(color::LIGHT_BLUE);
+        assert_eq!(color::LIGHT_BLUE, solid.color(&direction(5.0)));
+        assert_eq!(color::LIGHT_BLUE, solid.color(&direction(-5.0)));
+
+        let gradient = Background::Gradient { bottom: color::WHITE, top: color::LIGHT_BLUE };
+        let close = |a: Vec3, b: Vec3| (a - b).length() < 1e-3;
+        assert!(close(color::WHITE, gradient.color(&Ray::new(Vec3::default(), Vec3::new(0.0, -2.0, 0.0), 0.0))));
+        assert!(close(color::LIGHT_BLUE, gradient.color(&Ray::new(Vec3::default(), Vec3::new(0.0, 2.0, 0.0), 0.0))));
+        assert!(close(Vec3::new(0.75, 0.85, 1.0), gradient.color(&direction(0.0))));
+
+        // Луч, который ни во что не попал, приносит цвет фона.
+        let sampler = &mut IndependentSampler::new(0);
+        let empty = World(vec![]);
+        assert_eq!(color::LIGHT_BLUE, ray_color(direction(1.0), &empty, &empty, &solid, 20, 5, sampler));
+    }
+
+    #[test]
+    fn check_russian_roulette_keeps_mean() {
+        let world = world();
+        let background = Background::Gradient { bottom: color::WHITE, top: color::LIGHT_BLUE };
+        let no_lights = World(vec![]);
+
+        for x in [-1.0, 0.0, 0.6, 1.2] {
+            let (expected, _) = estimate(&mut |sampler| recursive_ray_color(&ray(x), &world, &background, 20, sampler));
+            let (actual, _) = estimate(&mut |sampler| ray_color(ray(x), &world, &no_lights, &background, 20, 1, sampler));
+            assert_close(actual, expected, &format!("ray {}", x));
+        }
+    }
+
+    #[test]
+    fn check_light_sampling_keeps_mean() {
+        let mut world = world();
+        let light = Arc::new(DiffuseLight::with_color([15.0, 15.0, 15.0].into()));
+        world.0.push(Box::new(XZRect { x0: 0.5, x1: 1.0, z0: -0.25, z1: 0.25, k: 2.0, material: light.clone() }));
+        // От копий источников нужна только форма, материал у них может быть любым.
+        let lights = World(vec![
+            sphere([-1.0, 1.0, 0.0], 0.3, light.clone()),
+            Box::new(XZRect { x0: 0.5, x1: 1.0, z0: -0.25, z1: 0.25, k: 2.0, material: light }),
+        ]);
+        let background = Background::Solid(color::BLACK);
+
+        for x in [0.0, 0.6, -0.3] {
+            let (expected, variance) =
+                estimate(&mut |sampler| ray_color(ray(x), &world, &World(vec![]), &background, 20, 5, sampler));
+            let (actual, light_variance) =
+                estimate(&mut |sampler| ray_color(ray(x), &world, &lights, &background, 20, 5, sampler));
+            // Без выборки на свет оценка очень шумная, поэтому допуск - четыре стандартные ошибки разности.
+            let tolerance = 4.0 * ((variance + light_variance) / SAMPLES as f32).sqrt();
+            assert!((actual[0] - expected[0]).abs() < tolerance, "ray {}: {:?} vs {:?}", x, actual, expected);
+            assert!(light_variance < variance, "ray {}: variance {} vs {}", x, light_variance, variance);
+        }
+    }
+}
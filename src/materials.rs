@@ -1,29 +1,74 @@
 use crate::bodies::HitRecord;
 use crate::geom::{dot, unit_vector, Vec3};
+use crate::pdf::{CosinePdf, Pdf, SpherePdf};
 use crate::ray::Ray;
-use crate::utils::{random_in_unit_sphere, random_unit_vector, random_range};
+use crate::sampler::Sampler;
+use crate::utils::random_in_unit_sphere;
 use crate::color;
+use crate::texture::{SolidColor, Texture};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+/// Результат рассеяния луча материалом.
+pub enum ScatterRecord {
+    /// Зеркальное отражение или преломление: направление определено материалом, и плотность не нужна.
+    Specular { ray: Ray, attenuation: Vec3 },
+    /// Рассеяние по плотности `pdf`. Направление выбирает интегратор, который может смешать эту плотность
+    /// с выборкой направлений на источники света.
+    Diffuse { attenuation: Vec3, pdf: Box<dyn Pdf> },
+}
 
 /// Типаж реализует взаимодействие поверхности тела со светом.
-pub trait Material {
-    fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<(Ray, Vec3)>;
+pub trait Material: Send + Sync {
+    fn scatter(&self, ray: &Ray, record: &HitRecord, sampler: &mut dyn Sampler) -> Option<ScatterRecord>;
+
+    /// Плотность по телесному углу, с которой материал рассеивает луч `ray` в направлении луча `scattered`.
+    ///
+    /// Нужна только для рассеяния `ScatterRecord::Diffuse`.
+    fn scattering_pdf(&self, _ray: &Ray, _record: &HitRecord, _scattered: &Ray) -> f32 {
+        0.0
+    }
+
+    /// Свет, излучаемый поверхностью в точке `p` с координатами развертки `(u, v)`.
+    ///
+    /// По умолчанию поверхность ничего не излучает.
+    fn emitted(&self, _u: f32, _v: f32, _p: Vec3) -> Vec3 {
+        color::BLACK
+    }
 }
 
 /// Описывает рассеивающее тело.
 pub struct Lambert {
-    pub albedo: Vec3,
+    pub albedo: Arc<dyn Texture>,
+}
+
+impl Lambert {
+    /// Создает рассеивающий материал с текстурой.
+    pub fn new(albedo: Arc<dyn Texture>) -> Self {
+        Lambert { albedo }
+    }
+
+    /// Создает рассеивающий материал одного цвета.
+    pub fn with_color(albedo: Vec3) -> Self {
+        Lambert { albedo: Arc::new(SolidColor::new(albedo)) }
+    }
 }
 
 /// Реализует рассеяние света по закону Ламберта.
 impl Material for Lambert {
-    fn scatter(&self, _: &Ray, record: &HitRecord) -> Option<(Ray, Vec3)> {
-        let dir = record.normal + random_unit_vector();
-        let scattered = Ray {
-            orig: record.point,
-            dir,
-        };
+    fn scatter(&self, _ray: &Ray, record: &HitRecord, _sampler: &mut dyn Sampler) -> Option<ScatterRecord> {
+        // Цвет вершин сетки окрашивает текстуру.
+        let mut albedo = self.albedo.value(record.u, record.v, record.point);
+        if let Some(color) = record.vertex_color {
+            albedo *= color;
+        }
 
-        Some((scattered, self.albedo))
+        Some(ScatterRecord::Diffuse { attenuation: albedo, pdf: Box::new(CosinePdf::new(record.normal)) })
+    }
+
+    fn scattering_pdf(&self, _ray: &Ray, record: &HitRecord, scattered: &Ray) -> f32 {
+        let cosine = dot(record.normal, unit_vector(scattered.direction()));
+        cosine.max(0.0) / PI
     }
 }
 
@@ -35,6 +80,7 @@ pub struct Metal {
 
 impl Metal {
     /// Создает металлический материал с идеальной отражающей поверхностью.
+    #[allow(dead_code)]
     pub fn with_albedo(albedo: Vec3) -> Self {
         Metal { albedo, fuzz: 0.0 }
     }
@@ -43,28 +89,21 @@ impl Metal {
     ///
     /// Степень матовости определяется вторым параметром в диапазоне `[0; 1]`.
     pub fn with_albedo_fuzz(albedo: Vec3, fuzz: f32) -> Self {
-        let f = if fuzz < 0.0 {
-            0.0
-        } else if fuzz > 1.0 {
-            1.0
-        } else {
-            fuzz
-        };
-
-        Metal { albedo, fuzz: f }
+        Metal { albedo, fuzz: fuzz.clamp(0.0, 1.0) }
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<(Ray, Vec3)> {
+    fn scatter(&self, ray: &Ray, record: &HitRecord, sampler: &mut dyn Sampler) -> Option<ScatterRecord> {
         let reflected = reflect(unit_vector(ray.direction()), record.normal);
         let scattered = Ray {
             orig: record.point,
-            dir: reflected + self.fuzz * random_in_unit_sphere(),
+            dir: reflected + self.fuzz * random_in_unit_sphere(sampler),
+            time: ray.time(),
         };
 
         if dot(scattered.direction(), record.normal) > 0.0 {
-            Some((scattered, self.albedo))
+            Some(ScatterRecord::Specular { ray: scattered, attenuation: self.albedo })
         } else {
             None
         }
@@ -77,7 +116,7 @@ pub struct Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord) -> Option<(Ray, Vec3)> {
+    fn scatter(&self, ray: &Ray, hit: &HitRecord, sampler: &mut dyn Sampler) -> Option<ScatterRecord> {
         let attenuation = color::WHITE;
         let refraction_ratio = if hit.front_face { 1.0 / self.ir } else { self.ir };
         let unit_direction = unit_vector(ray.direction());
@@ -86,14 +125,59 @@ impl Material for Dielectric {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
 
-        let direction;
-        if cannot_refract || schlick(cos_theta, self.ir) > random_range(0.0, 1.0) {
-            direction = reflect(unit_direction, hit.normal);
+        let direction = if cannot_refract || schlick(cos_theta, self.ir) > sampler.next_1d() {
+            reflect(unit_direction, hit.normal)
         } else {
-            direction = refract(unit_direction, hit.normal, refraction_ratio);
-        }
+            refract(unit_direction, hit.normal, refraction_ratio)
+        };
+
+        Some(ScatterRecord::Specular { ray: Ray{orig: hit.point, dir: direction, time: ray.time()}, attenuation })
+    }
+}
+
+/// Описывает светящееся тело. Свет не отражается, а только излучается.
+pub struct DiffuseLight {
+    pub emit: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    /// Создает источник света одного цвета.
+    ///
+    /// Компоненты цвета могут быть больше единицы, чтобы источник освещал сцену ярче.
+    pub fn with_color(emit: Vec3) -> Self {
+        DiffuseLight { emit: Arc::new(SolidColor::new(emit)) }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _: &Ray, _: &HitRecord, _: &mut dyn Sampler) -> Option<ScatterRecord> {
+        None
+    }
+
+    fn emitted(&self, u: f32, v: f32, p: Vec3) -> Vec3 {
+        self.emit.value(u, v, p)
+    }
+}
+
+/// Фазовая функция изотропной среды: свет рассеивается равновероятно во всех направлениях.
+pub struct Isotropic {
+    pub albedo: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Arc<dyn Texture>) -> Self {
+        Isotropic { albedo }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, _ray: &Ray, record: &HitRecord, _sampler: &mut dyn Sampler) -> Option<ScatterRecord> {
+        let attenuation = self.albedo.value(record.u, record.v, record.point);
+        Some(ScatterRecord::Diffuse { attenuation, pdf: Box::new(SpherePdf) })
+    }
 
-        Some((Ray{orig: hit.point, dir: direction}, attenuation))
+    fn scattering_pdf(&self, _ray: &Ray, _record: &HitRecord, _scattered: &Ray) -> f32 {
+        1.0 / (4.0 * PI)
     }
 }
 
@@ -102,7 +186,6 @@ fn reflect(vec: Vec3, normal: Vec3) -> Vec3 {
     vec - 2.0 * dot(vec, normal) * normal
 }
 
-/// Описывает закон преломления луча на поверхности тела.
 // fn refract(vec: Vec3, normal: Vec3, ni_over_nt: f32) -> Option<Vec3> {
 //     let uv = unit_vector(vec);
 //     let dt = dot(uv, normal);
@@ -115,6 +198,7 @@ fn reflect(vec: Vec3, normal: Vec3) -> Vec3 {
 //     }
 // }
 
+/// Описывает закон преломления луча на поверхности тела.
 fn refract(uv: Vec3, n: Vec3, etai_over_etat: f32) -> Vec3 {
     let cos_theta = dot(-uv, n).min(1.0);
     let r_out_perp =  etai_over_etat * (uv + cos_theta * n);
@@ -130,3 +214,49 @@ pub fn schlick(cosine: f32, ref_index: f32) -> f32 {
 
     r0 + (1.0 - r0) * f32::powi(1.0 - cosine, 5)
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Dielectric, DiffuseLight, Material, Metal, ScatterRecord};
+    use crate::bodies::{Hittable, Sphere};
+    use crate::geom::Vec3;
+    use crate::ray::Ray;
+    use crate::sampler::IndependentSampler;
+    use crate::texture::CheckerTexture;
+    use std::sync::Arc;
+
+    #[test]
+    fn check_scattered_ray_keeps_time() {
+        let materials: Vec<Arc<dyn Material>> = vec![
+            Arc::new(Metal::with_albedo_fuzz(Vec3::new(0.8, 0.8, 0.8), 0.3)),
+            Arc::new(Dielectric { ir: 1.5 }),
+        ];
+        let sampler = &mut IndependentSampler::new(0);
+
+        for material in materials {
+            let sphere = Sphere { center: Vec3::new(0.0, 0.0, -2.0), radius: 1.0, material };
+            let ray = Ray::new(Vec3::default(), Vec3::new(0.0, 0.0, -1.0), 0.75);
+            let rec = sphere.hit(&ray, 0.001, f32::MAX, sampler).unwrap();
+            match rec.material.scatter(&ray, &rec, sampler) {
+                Some(ScatterRecord::Specular { ray: scattered, .. }) => assert_eq!(0.75, scattered.time()),
+                _ => panic!("specular scattering expected"),
+            }
+        }
+    }
+
+    #[test]
+    fn check_diffuse_light() {
+        let even = Vec3::new(4.0, 4.0, 4.0);
+        let odd = Vec3::new(0.0, 2.0, 0.0);
+        let light: Arc<dyn Material> = Arc::new(DiffuseLight { emit: Arc::new(CheckerTexture::with_colors(even, odd)) });
+        assert_eq!(even, light.emitted(0.0, 0.0, Vec3::new(0.05, 0.05, 0.05)));
+        assert_eq!(odd, light.emitted(0.0, 0.0, Vec3::new(-0.05, 0.05, 0.05)));
+
+        let sampler = &mut IndependentSampler::new(0);
+        let sphere = Sphere { center: Vec3::new(0.0, 0.0, -2.0), radius: 1.0, material: light };
+        let ray = Ray::new(Vec3::default(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = sphere.hit(&ray, 0.001, f32::MAX, sampler).unwrap();
+        assert!(rec.material.scatter(&ray, &rec, sampler).is_none());
+        assert_eq!(Vec3::new(15.0, 15.0, 15.0), DiffuseLight::with_color(Vec3::new(15.0, 15.0, 15.0)).emitted(0.3, 0.7, rec.point));
+    }
+}
@@ -0,0 +1,250 @@
+use crate::aabb::{surrounding_box, Aabb};
+use crate::bodies::{HitRecord, Hittable};
+use crate::bvh::SahBvh;
+use crate::geom::{cross, dot, unit_vector, Vec3};
+use crate::materials::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::World;
+use std::sync::Arc;
+
+/// Минимальный определитель, при котором луч считается не параллельным треугольнику.
+const EPSILON: f32 = 1e-8;
+/// Толщина ограничивающего параллелепипеда треугольника, лежащего в координатной плоскости.
+const THIN_BOX: f32 = 0.0001;
+
+/// Буферы вершин и индексов треугольной сетки с общим материалом.
+///
+/// Нормали и текстурные координаты задаются для каждой вершины и могут отсутствовать.
+/// Без нормалей треугольники затеняются плоско, без координат `(u, v)` используются барицентрические.
+/// Цвета вершин, если заданы, окрашивают рассеивающий материал сетки.
+pub struct MeshData {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f32, f32)>,
+    pub colors: Vec<Vec3>,
+    pub indices: Vec<[usize; 3]>,
+    pub material: Arc<dyn Material>,
+}
+
+impl MeshData {
+    /// Создает сетку без нормалей и текстурных координат.
+    pub fn new(positions: Vec<Vec3>, indices: Vec<[usize; 3]>, material: Arc<dyn Material>) -> Self {
+        MeshData {
+            positions,
+            normals: vec![],
+            uvs: vec![],
+            colors: vec![],
+            indices,
+            material,
+        }
+    }
+
+    /// Вычисляет нормали вершин как сумму нормалей прилегающих треугольников, взвешенных по площади.
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vec3::default(); self.positions.len()];
+        for face in &self.indices {
+            let [p0, p1, p2] = face.map(|i| self.positions[i]);
+            // Длина векторного произведения равна удвоенной площади треугольника.
+            let n = cross(p1 - p0, p2 - p0);
+            for &i in face {
+                normals[i] += n;
+            }
+        }
+
+        self.normals = normals
+            .into_iter()
+            .map(|n| if n.length_squared() > 0.0 { unit_vector(n) } else { n })
+            .collect();
+    }
+}
+
+/// Треугольник сетки, ссылающийся на общие буферы по номеру грани.
+pub struct Triangle {
+    mesh: Arc<MeshData>,
+    face: usize,
+}
+
+impl Triangle {
+    fn vertices(&self) -> [Vec3; 3] {
+        self.mesh.indices[self.face].map(|i| self.mesh.positions[i])
+    }
+}
+
+impl Hittable for Triangle {
+    /// Пересечение по алгоритму Моллера-Трумбора.
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, _sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        let [p0, p1, p2] = self.vertices();
+        let edge1 = p1 - p0;
+        let edge2 = p2 - p0;
+
+        let pvec = cross(ray.direction(), edge2);
+        let det = dot(edge1, pvec);
+        if det.abs() < EPSILON {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        let tvec = ray.origin() - p0;
+        let b1 = dot(tvec, pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let qvec = cross(tvec, edge1);
+        let b2 = dot(ray.direction(), qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = dot(edge2, qvec) * inv_det;
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+
+        let b0 = 1.0 - b1 - b2;
+        let [i0, i1, i2] = self.mesh.indices[self.face];
+        let (u, v) = if self.mesh.uvs.is_empty() {
+            (b1, b2)
+        } else {
+            let (uv0, uv1, uv2) = (self.mesh.uvs[i0], self.mesh.uvs[i1], self.mesh.uvs[i2]);
+            (b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0, b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1)
+        };
+
+        let outward_normal = unit_vector(cross(edge1, edge2));
+        let mut rec = HitRecord::with_front_face(t, ray.at(t), self.mesh.material.clone(), outward_normal, ray, u, v);
+
+        // Сторона треугольника определяется геометрической нормалью,
+        // а для освещения используется нормаль, интерполированная по вершинам.
+        if !self.mesh.normals.is_empty() {
+            let n = b0 * self.mesh.normals[i0] + b1 * self.mesh.normals[i1] + b2 * self.mesh.normals[i2];
+            if n.length_squared() > 0.0 {
+                let shading_normal = unit_vector(n);
+                rec.normal = if rec.front_face { shading_normal } else { -shading_normal };
+            }
+        }
+
+        if !self.mesh.colors.is_empty() {
+            rec.vertex_color = Some(b0 * self.mesh.colors[i0] + b1 * self.mesh.colors[i1] + b2 * self.mesh.colors[i2]);
+        }
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _: f32, _: f32) -> Option<Aabb> {
+        let [p0, p1, p2] = self.vertices();
+        let bbox = surrounding_box(surrounding_box(Aabb::new(p0, p0), Aabb::new(p1, p1)), Aabb::new(p2, p2));
+        let pad = Vec3::new(THIN_BOX, THIN_BOX, THIN_BOX);
+
+        Some(Aabb::new(bbox.min - pad, bbox.max + pad))
+    }
+}
+
+/// Треугольная сетка с собственной иерархией ограничивающих объемов.
+///
+/// Все треугольники ссылаются на одни и те же буферы вершин и один материал.
+pub struct TriangleMesh {
+    bvh: SahBvh,
+}
+
+impl TriangleMesh {
+    /// Создает сетку из буферов.
+    ///
+    /// Паникует, если индекс вершины выходит за пределы буферов.
+    pub fn new(data: MeshData) -> Self {
+        let vertex_count = data.positions.len();
+        assert!(
+            data.indices.iter().flatten().all(|&i| i < vertex_count),
+            "Triangle index out of range"
+        );
+        assert!(data.normals.is_empty() || data.normals.len() == vertex_count, "Normal count does not match vertices");
+        assert!(data.uvs.is_empty() || data.uvs.len() == vertex_count, "UV count does not match vertices");
+        assert!(data.colors.is_empty() || data.colors.len() == vertex_count, "Color count does not match vertices");
+
+        let mesh = Arc::new(data);
+        let triangles: Vec<Box<dyn Hittable>> = (0..mesh.indices.len())
+            .map(|face| Box::new(Triangle { mesh: mesh.clone(), face }) as Box<dyn Hittable>)
+            .collect();
+
+        TriangleMesh {
+            bvh: SahBvh::new(World(triangles), 0.0, 1.0),
+        }
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        self.bvh.hit(ray, t_min, t_max, sampler)
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        self.bvh.bounding_box(time0, time1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MeshData, TriangleMesh};
+    use crate::bodies::Hittable;
+    use crate::geom::{dot, Vec3};
+    use crate::materials::Lambert;
+    use crate::ray::Ray;
+    use crate::sampler::IndependentSampler;
+    use std::sync::Arc;
+
+    fn quad() -> MeshData {
+        let positions = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ];
+        MeshData::new(positions, vec![[0, 1, 2], [0, 2, 3]], Arc::new(Lambert::with_color([0.5, 0.5, 0.5].into())))
+    }
+
+    #[test]
+    fn check_triangle_hit_and_barycentric_uv() {
+        let mesh = TriangleMesh::new(quad());
+        let ray = Ray::new([0.75, 0.25, 1.0].into(), [0.0, 0.0, -1.0].into(), 0.0);
+        let rec = mesh.hit(&ray, 0.001, f32::MAX, &mut IndependentSampler::new(0)).unwrap();
+
+        assert!((rec.t - 1.0).abs() < 1e-6);
+        assert!(rec.front_face);
+        assert_eq!(Vec3::new(0.0, 0.0, 1.0), rec.normal);
+        assert!((rec.u - 0.5).abs() < 1e-6 && (rec.v - 0.25).abs() < 1e-6);
+
+        let miss = Ray::new([1.5, 0.5, 1.0].into(), [0.0, 0.0, -1.0].into(), 0.0);
+        assert!(mesh.hit(&miss, 0.001, f32::MAX, &mut IndependentSampler::new(0)).is_none());
+    }
+
+    #[test]
+    fn check_back_face_and_uv_interpolation() {
+        let mut data = quad();
+        data.uvs = vec![(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)];
+        let mesh = TriangleMesh::new(data);
+        let ray = Ray::new([0.25, 0.5, -2.0].into(), [0.0, 0.0, 1.0].into(), 0.0);
+        let rec = mesh.hit(&ray, 0.001, f32::MAX, &mut IndependentSampler::new(0)).unwrap();
+
+        assert!(!rec.front_face);
+        assert_eq!(Vec3::new(0.0, 0.0, -1.0), rec.normal);
+        assert!((rec.u - 0.5).abs() < 1e-6 && (rec.v - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn check_smooth_normals() {
+        // Крыша из двух граней под углом 90 градусов: нормаль на ребре направлена вверх.
+        let positions = vec![
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, -1.0),
+            Vec3::new(0.0, 1.0, 1.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        ];
+        let mut data = MeshData::new(positions, vec![[0, 2, 1], [3, 1, 2]], Arc::new(Lambert::with_color([0.5, 0.5, 0.5].into())));
+        data.compute_normals();
+        let mesh = TriangleMesh::new(data);
+
+        let ray = Ray::new([-0.001, 5.0, 0.0].into(), [0.0, -1.0, 0.0].into(), 0.0);
+        let rec = mesh.hit(&ray, 0.001, f32::MAX, &mut IndependentSampler::new(0)).unwrap();
+        assert!(dot(rec.normal, Vec3::new(0.0, 1.0, 0.0)) > 0.999);
+    }
+}
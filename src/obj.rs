@@ -0,0 +1,525 @@
+use crate::geom::Vec3;
+use crate::materials::{Dielectric, DiffuseLight, Lambert, Material, Metal};
+use crate::mesh::MeshData;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::{fmt, fs, io};
+
+/// Ошибка загрузки файла OBJ или MTL.
+#[derive(Debug)]
+pub enum ObjError {
+    /// Файл не удалось прочитать.
+    Io { file: PathBuf, error: io::Error },
+    /// Ошибка в строке `line` файла (строки нумеруются с единицы).
+    Parse { file: PathBuf, line: usize, message: String },
+}
+
+impl Display for ObjError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { file, error } => write!(f, "{}: {}", file.display(), error),
+            ObjError::Parse { file, line, message } => write!(f, "{}:{}: {}", file.display(), line, message),
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Позиция в разбираемом файле для сообщений об ошибках.
+struct Location<'a> {
+    file: &'a Path,
+    line: usize,
+}
+
+impl Location<'_> {
+    fn error<T, S: Into<String>>(&self, message: S) -> Result<T, ObjError> {
+        Err(ObjError::Parse {
+            file: self.file.to_path_buf(),
+            line: self.line,
+            message: message.into(),
+        })
+    }
+}
+
+fn read_file(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|error| ObjError::Io { file: path.to_path_buf(), error })
+}
+
+/// Разбирает `count` чисел с плавающей точкой после ключевого слова.
+fn parse_floats<'a, I>(loc: &Location, keyword: &str, args: I, count: usize) -> Result<Vec<f32>, ObjError>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let values = args
+        .take(count)
+        .map(|s| s.parse::<f32>().or_else(|_| loc.error(format!("invalid number '{}' in '{}'", s, keyword))))
+        .collect::<Result<Vec<f32>, ObjError>>()?;
+
+    if values.len() < count {
+        return loc.error(format!("'{}' expects {} numbers, found {}", keyword, count, values.len()));
+    }
+    Ok(values)
+}
+
+/// Параметры материала из файла MTL.
+struct MtlMaterial {
+    kd: Vec3,
+    ks: Vec3,
+    ke: Vec3,
+    ns: f32,
+    ni: f32,
+    d: f32,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        MtlMaterial {
+            kd: Vec3::new(0.8, 0.8, 0.8),
+            ks: Vec3::default(),
+            ke: Vec3::default(),
+            ns: 0.0,
+            ni: 1.0,
+            d: 1.0,
+        }
+    }
+}
+
+fn max_component(v: Vec3) -> f32 {
+    v[0].max(v[1]).max(v[2])
+}
+
+impl MtlMaterial {
+    /// Подбирает ближайший материал трассировщика.
+    ///
+    /// Светящиеся материалы (`Ke`) становятся [`DiffuseLight`], прозрачные (`d < 1`) - [`Dielectric`]
+    /// с показателем преломления `Ni`, материалы с преобладающим зеркальным цветом (`Ks`) - [`Metal`],
+    /// размытость которого растет с уменьшением показателя блеска `Ns`. Остальные - [`Lambert`] с цветом `Kd`.
+    ///
+    /// [`DiffuseLight`]: ../materials/struct.DiffuseLight.html
+    /// [`Dielectric`]: ../materials/struct.Dielectric.html
+    /// [`Metal`]: ../materials/struct.Metal.html
+    /// [`Lambert`]: ../materials/struct.Lambert.html
+    fn to_material(&self) -> Arc<dyn Material> {
+        if max_component(self.ke) > 0.0 {
+            Arc::new(DiffuseLight::with_color(self.ke))
+        } else if self.d < 1.0 {
+            let ir = if self.ni > 1.0 { self.ni } else { 1.5 };
+            Arc::new(Dielectric { ir })
+        } else if max_component(self.ks) > max_component(self.kd) {
+            // Шероховатость модели Блинна-Фонга: чем выше блеск, тем четче отражение.
+            let fuzz = (2.0 / (self.ns + 2.0)).sqrt();
+            Arc::new(Metal::with_albedo_fuzz(self.ks, fuzz))
+        } else {
+            Arc::new(Lambert::with_color(self.kd))
+        }
+    }
+}
+
+/// Разбирает библиотеку материалов MTL.
+pub fn parse_mtl(source: &str, file: &Path) -> Result<HashMap<String, Arc<dyn Material>>, ObjError> {
+    let mut parsed: Vec<(String, MtlMaterial)> = vec![];
+
+    for (index, line) in source.lines().enumerate() {
+        let loc = Location { file, line: index + 1 };
+        let line = line.split('#').next().unwrap_or("");
+        let mut args = line.split_whitespace();
+        let keyword = match args.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        if keyword == "newmtl" {
+            let name = args.collect::<Vec<_>>().join(" ");
+            if name.is_empty() {
+                return loc.error("'newmtl' without a name");
+            }
+            parsed.push((name, MtlMaterial::default()));
+            continue;
+        }
+
+        let current = match parsed.last_mut() {
+            Some((_, material)) => material,
+            None => match keyword {
+                "Kd" | "Ks" | "Ke" | "Ns" | "Ni" | "d" | "Tr" => {
+                    return loc.error(format!("'{}' before 'newmtl'", keyword));
+                }
+                _ => continue,
+            },
+        };
+
+        match keyword {
+            "Kd" => current.kd = Vec3::from(color(&loc, keyword, args)?),
+            "Ks" => current.ks = Vec3::from(color(&loc, keyword, args)?),
+            "Ke" => current.ke = Vec3::from(color(&loc, keyword, args)?),
+            "Ns" => current.ns = parse_floats(&loc, keyword, args, 1)?[0],
+            "Ni" => current.ni = parse_floats(&loc, keyword, args, 1)?[0],
+            "d" => current.d = parse_floats(&loc, keyword, args, 1)?[0],
+            "Tr" => current.d = 1.0 - parse_floats(&loc, keyword, args, 1)?[0],
+            // Текстурные карты и модели освещения не поддерживаются.
+            _ => {}
+        }
+    }
+
+    Ok(parsed
+        .into_iter()
+        .map(|(name, material)| (name, material.to_material()))
+        .collect())
+}
+
+/// Цвет из одного или трех чисел: `Kd 0.5` означает серый цвет `0.5 0.5 0.5`.
+fn color<'a, I>(loc: &Location, keyword: &str, args: I) -> Result<[f32; 3], ObjError>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let args: Vec<&str> = args.collect();
+    if args.len() == 1 {
+        let v = parse_floats(loc, keyword, args.into_iter(), 1)?[0];
+        return Ok([v, v, v]);
+    }
+
+    let v = parse_floats(loc, keyword, args.into_iter(), 3)?;
+    Ok([v[0], v[1], v[2]])
+}
+
+/// Вершина грани: индексы позиции, текстурных координат и нормали, начиная с нуля.
+type FaceVertex = (usize, Option<usize>, Option<usize>);
+
+/// Строящаяся группа граней: вершины с уникальными сочетаниями индексов и треугольники.
+struct ObjectBuilder {
+    material: Arc<dyn Material>,
+    vertices: HashMap<FaceVertex, usize>,
+    order: Vec<FaceVertex>,
+    indices: Vec<[usize; 3]>,
+}
+
+impl ObjectBuilder {
+    fn new(material: Arc<dyn Material>) -> Self {
+        ObjectBuilder {
+            material,
+            vertices: HashMap::new(),
+            order: vec![],
+            indices: vec![],
+        }
+    }
+
+    fn vertex(&mut self, key: FaceVertex) -> usize {
+        let order = &mut self.order;
+        *self.vertices.entry(key).or_insert_with(|| {
+            order.push(key);
+            order.len() - 1
+        })
+    }
+
+    fn build(self, positions: &[Vec3], uvs: &[(f32, f32)], normals: &[Vec3]) -> Option<MeshData> {
+        if self.indices.is_empty() {
+            return None;
+        }
+
+        let mut mesh = MeshData::new(
+            self.order.iter().map(|&(v, _, _)| positions[v]).collect(),
+            self.indices,
+            self.material,
+        );
+        if self.order.iter().all(|&(_, vt, _)| vt.is_some()) {
+            mesh.uvs = self.order.iter().map(|&(_, vt, _)| uvs[vt.unwrap()]).collect();
+        }
+        if self.order.iter().all(|&(_, _, vn)| vn.is_some()) {
+            mesh.normals = self.order.iter().map(|&(_, _, vn)| normals[vn.unwrap()]).collect();
+        } else if self.order.iter().any(|&(_, _, vn)| vn.is_some()) {
+            // Нормали заданы не для всех вершин - вычисляем их заново для всей группы.
+            mesh.compute_normals();
+        }
+
+        Some(mesh)
+    }
+}
+
+/// Переводит индекс OBJ (с единицы или отрицательный - от конца списка) в индекс с нуля.
+fn resolve_index(loc: &Location, token: &str, count: usize) -> Result<usize, ObjError> {
+    let index: i64 = match token.parse() {
+        Ok(index) => index,
+        Err(_) => return loc.error(format!("invalid index '{}'", token)),
+    };
+
+    let resolved = if index > 0 { index - 1 } else { count as i64 + index };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return loc.error(format!("index {} out of range (1..{})", index, count));
+    }
+    Ok(resolved as usize)
+}
+
+/// Разбирает модель OBJ.
+///
+/// Многоугольники разбиваются на треугольники веером из первой вершины.
+/// Библиотеки материалов `mtllib` загружаются функцией `load_mtl` по имени из файла.
+/// Грани без материала получают `default_material`, ссылка `usemtl` на неизвестный материал - ошибка.
+pub fn parse_obj<F>(
+    source: &str,
+    file: &Path,
+    default_material: Arc<dyn Material>,
+    mut load_mtl: F,
+) -> Result<Vec<MeshData>, ObjError>
+where
+    F: FnMut(&str) -> Result<HashMap<String, Arc<dyn Material>>, ObjError>,
+{
+    let mut positions: Vec<Vec3> = vec![];
+    let mut uvs: Vec<(f32, f32)> = vec![];
+    let mut normals: Vec<Vec3> = vec![];
+    let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+    let mut objects: Vec<MeshData> = vec![];
+
+    let mut material_name = String::new();
+    let mut current = ObjectBuilder::new(default_material.clone());
+
+    for (index, line) in source.lines().enumerate() {
+        let loc = Location { file, line: index + 1 };
+        let line = line.split('#').next().unwrap_or("");
+        let mut args = line.split_whitespace();
+        let keyword = match args.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        match keyword {
+            "v" => {
+                let v = parse_floats(&loc, keyword, args, 3)?;
+                positions.push(Vec3::new(v[0], v[1], v[2]));
+            }
+            "vt" => {
+                // Вторая координата необязательна, третья не используется.
+                let args: Vec<&str> = args.collect();
+                let u = parse_floats(&loc, keyword, args.iter().cloned(), 1)?[0];
+                let v = match args.get(1) {
+                    Some(_) => parse_floats(&loc, keyword, args[1..].iter().cloned(), 1)?[0],
+                    None => 0.0,
+                };
+                uvs.push((u, v));
+            }
+            "vn" => {
+                let v = parse_floats(&loc, keyword, args, 3)?;
+                normals.push(Vec3::new(v[0], v[1], v[2]));
+            }
+            "f" => {
+                let mut face: Vec<usize> = vec![];
+                for token in args {
+                    let mut parts = token.split('/');
+                    let v = resolve_index(&loc, parts.next().unwrap_or(""), positions.len())?;
+                    let vt = match parts.next() {
+                        Some(s) if !s.is_empty() => Some(resolve_index(&loc, s, uvs.len())?),
+                        _ => None,
+                    };
+                    let vn = match parts.next() {
+                        Some(s) if !s.is_empty() => Some(resolve_index(&loc, s, normals.len())?),
+                        _ => None,
+                    };
+                    face.push(current.vertex((v, vt, vn)));
+                }
+
+                if face.len() < 3 {
+                    return loc.error(format!("face with {} vertices", face.len()));
+                }
+                for i in 1..face.len() - 1 {
+                    current.indices.push([face[0], face[i], face[i + 1]]);
+                }
+            }
+            "g" | "o" | "usemtl" => {
+                // Новая группа начинает отдельную сетку с прежним материалом.
+                if keyword == "usemtl" {
+                    material_name = args.collect::<Vec<_>>().join(" ");
+                }
+
+                let material = match materials.get(&material_name) {
+                    Some(material) => material.clone(),
+                    None if keyword == "usemtl" && !material_name.is_empty() => {
+                        return loc.error(format!("unknown material '{}'", material_name));
+                    }
+                    None => default_material.clone(),
+                };
+                let finished = std::mem::replace(&mut current, ObjectBuilder::new(material));
+                objects.extend(finished.build(&positions, &uvs, &normals));
+            }
+            "mtllib" => {
+                for library in args {
+                    materials.extend(load_mtl(library)?);
+                }
+            }
+            // Группы сглаживания, линии, точки и прочие инструкции не поддерживаются.
+            _ => {}
+        }
+    }
+
+    objects.extend(current.build(&positions, &uvs, &normals));
+    Ok(objects)
+}
+
+/// Загружает модель OBJ и библиотеки материалов, на которые она ссылается.
+///
+/// Пути библиотек материалов отсчитываются от каталога файла модели.
+pub fn load_obj<P: AsRef<Path>>(path: P, default_material: Arc<dyn Material>) -> Result<Vec<MeshData>, ObjError> {
+    let path = path.as_ref();
+    let source = read_file(path)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+    parse_obj(&source, path, default_material, |library| {
+        let mtl_path = directory.join(library);
+        let mtl_source = read_file(&mtl_path)?;
+        parse_mtl(&mtl_source, &mtl_path)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_mtl, parse_obj, ObjError};
+    use crate::bodies::{Hittable, XYRect};
+    use crate::geom::{unit_vector, Vec3};
+    use crate::materials::{Lambert, Material, ScatterRecord};
+    use crate::mesh::MeshData;
+    use crate::ray::Ray;
+    use crate::sampler::{IndependentSampler, Sampler};
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    fn parse(source: &str) -> Result<Vec<MeshData>, ObjError> {
+        parse_obj(source, Path::new("test.obj"), Arc::new(Lambert::with_color([0.5, 0.5, 0.5].into())), |_| {
+            Ok(HashMap::new())
+        })
+    }
+
+    #[test]
+    fn check_polygon_fan_and_negative_indices() {
+        let source = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 2 0\n# pentagon\nf -5 -4 -3 -2 -1\n";
+        let objects = parse(source).unwrap();
+
+        assert_eq!(1, objects.len());
+        assert_eq!(vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]], objects[0].indices);
+        assert_eq!(Vec3::new(0.0, 2.0, 0.0), objects[0].positions[4]);
+    }
+
+    #[test]
+    fn check_texture_and_normal_indices() {
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nvn 0 0 1\n\
+                      f 1/1/1 2/2/1 3/3/1\nf 1//1 2//1 3//1\n";
+        let objects = parse(source).unwrap();
+        let mesh = &objects[0];
+
+        // Вершины с текстурными координатами и без них различаются.
+        assert_eq!(6, mesh.positions.len());
+        assert!(mesh.uvs.is_empty());
+        assert_eq!(vec![Vec3::new(0.0, 0.0, 1.0); 6], mesh.normals);
+    }
+
+    #[test]
+    fn check_groups_and_materials() {
+        let source = "mtllib scene.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\ng first\nusemtl red\nf 1 2 3\ng second\nf 3 2 1\n";
+        let objects = parse_obj(
+            source,
+            Path::new("test.obj"),
+            Arc::new(Lambert::with_color([0.5, 0.5, 0.5].into())),
+            |library| {
+                assert_eq!("scene.mtl", library);
+                parse_mtl("newmtl red\nKd 1 0 0\n", Path::new("scene.mtl"))
+            },
+        )
+        .unwrap();
+
+        // Каждая группа - отдельная сетка, и материал `usemtl` переходит во вторую группу.
+        assert_eq!(2, objects.len());
+        assert_eq!(vec![[0, 1, 2]], objects[0].indices);
+        assert_eq!(vec![[0, 1, 2]], objects[1].indices);
+        assert_eq!(Vec3::new(0.0, 1.0, 0.0), objects[1].positions[0]);
+        assert!(Arc::ptr_eq(&objects[0].material, &objects[1].material));
+    }
+
+    #[test]
+    fn check_errors_report_line() {
+        let err = parse("v 0 0 0\nv 1 0 0\n\nf 1 2 3\n").err().unwrap();
+        assert_eq!("test.obj:4: index 3 out of range (1..2)", err.to_string());
+
+        let err = parse("v 0 zero 0\n").err().unwrap();
+        assert_eq!("test.obj:1: invalid number 'zero' in 'v'", err.to_string());
+
+        let err = parse("v 0 0 0\nf 1 1\n").err().unwrap();
+        assert_eq!("test.obj:2: face with 2 vertices", err.to_string());
+
+        let err = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl missing\nf 1 2 3\n").err().unwrap();
+        assert_eq!("test.obj:4: unknown material 'missing'", err.to_string());
+
+        let err = parse_mtl("Kd 1 1 1\n", Path::new("a.mtl")).err().unwrap();
+        assert_eq!("a.mtl:1: 'Kd' before 'newmtl'", err.to_string());
+    }
+
+    #[test]
+    fn check_mtl_material_mapping() {
+        let source = "newmtl matte\nKd 0.5 0.5 0.5\n\
+                      newmtl mirror\nKd 0 0 0\nKs 0.9 0.9 0.9\nNs 1000\n\
+                      newmtl glass\nNi 1.45\nd 0.1\n\
+                      newmtl lamp\nKe 4 4 4\n";
+        let materials = parse_mtl(source, Path::new("a.mtl")).unwrap();
+        let lamp: &Arc<dyn Material> = &materials["lamp"];
+
+        assert_eq!(4, materials.len());
+        assert_eq!(Vec3::new(4.0, 4.0, 4.0), lamp.emitted(0.0, 0.0, Vec3::default()));
+        assert_eq!(Vec3::default(), materials["matte"].emitted(0.0, 0.0, Vec3::default()));
+
+        // Все материалы освещаются через одну и ту же точку плоскости `z = -1` с нормалью `+Z`.
+        let sampler = &mut IndependentSampler::new(0);
+        let scatter = |name: &str, direction: Vec3, sampler: &mut IndependentSampler| {
+            let plane = XYRect { x0: -10.0, x1: 10.0, y0: -10.0, y1: 10.0, k: -1.0, material: materials[name].clone() };
+            let ray = Ray::new(Vec3::default(), direction, 0.0);
+            let rec = plane.hit(&ray, 0.001, f32::MAX, sampler).unwrap();
+            rec.material.scatter(&ray, &rec, sampler)
+        };
+        let down = Vec3::new(0.0, 0.0, -1.0);
+
+        match scatter("matte", down, sampler) {
+            Some(ScatterRecord::Diffuse { attenuation, .. }) => assert_eq!(Vec3::new(0.5, 0.5, 0.5), attenuation),
+            _ => panic!("matte must scatter diffusely"),
+        }
+
+        // Металл отражает навстречу нормали с размытием sqrt(2 / (Ns + 2)).
+        let fuzz = (2.0f32 / 1002.0).sqrt();
+        let mut deviations = vec![];
+        for index in 0..200 {
+            sampler.start_sample(0, 0, index);
+            match scatter("mirror", down, sampler) {
+                Some(ScatterRecord::Specular { ray, attenuation }) => {
+                    assert_eq!(Vec3::new(0.9, 0.9, 0.9), attenuation);
+                    deviations.push((unit_vector(ray.direction()) + down).length());
+                }
+                _ => panic!("mirror must reflect"),
+            }
+        }
+        assert!(deviations.iter().all(|&d| d <= fuzz * 1.01));
+        assert!(deviations.iter().any(|&d| d > fuzz / 2.0));
+
+        // Стекло преломляет по закону Снеллиуса с показателем Ni: sin 0.6 переходит в 0.6 / 1.45.
+        let oblique = Vec3::new(0.6, 0.0, -0.8);
+        let mut refracted = 0;
+        for index in 0..200 {
+            sampler.start_sample(0, 0, index);
+            match scatter("glass", oblique, sampler) {
+                Some(ScatterRecord::Specular { ray, attenuation }) => {
+                    assert_eq!(Vec3::new(1.0, 1.0, 1.0), attenuation);
+                    let direction = unit_vector(ray.direction());
+                    if direction[2] < 0.0 {
+                        refracted += 1;
+                        assert!((direction[0] - 0.6 / 1.45).abs() < 1e-5, "{}", direction);
+                    }
+                }
+                _ => panic!("glass must be specular"),
+            }
+        }
+        assert!(refracted > 150);
+    }
+}
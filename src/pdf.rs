@@ -0,0 +1,169 @@
+use crate::bodies::Hittable;
+use crate::geom::{dot, unit_vector, Onb, Vec3};
+use crate::sampler::Sampler;
+use crate::utils::{random_cosine_direction, random_unit_vector};
+use std::f32::consts::PI;
+
+/// Плотность вероятности на сфере направлений вместе со способом выбирать направления с этой плотностью.
+pub trait Pdf {
+    /// Плотность по телесному углу в направлении `direction`.
+    fn value(&self, direction: Vec3) -> f32;
+
+    /// Случайное направление с этой плотностью.
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3;
+}
+
+/// Плотность, пропорциональная косинусу угла с нормалью, - идеальная для рассеяния по Ламберту.
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(normal: Vec3) -> Self {
+        CosinePdf { uvw: Onb::from_w(normal) }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: Vec3) -> f32 {
+        let cosine = dot(unit_vector(direction), self.uvw.w);
+        cosine.max(0.0) / PI
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.uvw.local(random_cosine_direction(sampler))
+    }
+}
+
+/// Равномерная плотность на всей сфере направлений.
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: Vec3) -> f32 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        random_unit_vector(sampler)
+    }
+}
+
+/// Направления из точки `origin` на объект, который умеет выбирать свои точки (обычно источник света).
+pub struct HittablePdf<'a> {
+    object: &'a dyn Hittable,
+    origin: Vec3,
+}
+
+impl<'a> HittablePdf<'a> {
+    pub fn new(object: &'a dyn Hittable, origin: Vec3) -> Self {
+        HittablePdf { object, origin }
+    }
+}
+
+impl Pdf for HittablePdf<'_> {
+    fn value(&self, direction: Vec3) -> f32 {
+        self.object.pdf_value(self.origin, direction)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.object.random(self.origin, sampler)
+    }
+}
+
+/// Смесь двух плотностей с равными весами.
+pub struct MixturePdf<'a> {
+    first: &'a dyn Pdf,
+    second: &'a dyn Pdf,
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(first: &'a dyn Pdf, second: &'a dyn Pdf) -> Self {
+        MixturePdf { first, second }
+    }
+}
+
+impl Pdf for MixturePdf<'_> {
+    fn value(&self, direction: Vec3) -> f32 {
+        0.5 * self.first.value(direction) + 0.5 * self.second.value(direction)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        if sampler.next_1d() < 0.5 {
+            self.first.generate(sampler)
+        } else {
+            self.second.generate(sampler)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CosinePdf, HittablePdf, MixturePdf, Pdf, SpherePdf};
+    use crate::bodies::{Hittable, Sphere, XZRect};
+    use crate::geom::{dot, Onb, Vec3};
+    use crate::materials::Lambert;
+    use crate::sampler::{IndependentSampler, Sampler};
+    use crate::utils::random_unit_vector;
+    use std::f32::consts::PI;
+    use std::sync::Arc;
+
+    #[test]
+    fn check_onb() {
+        for n in [Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -3.0), Vec3::new(1.0, 2.0, -0.5)] {
+            let uvw = Onb::from_w(n);
+            for (a, b) in [(uvw.u, uvw.v), (uvw.v, uvw.w), (uvw.w, uvw.u)] {
+                assert!(dot(a, b).abs() < 1e-6);
+            }
+            for axis in [uvw.u, uvw.v, uvw.w] {
+                assert!((axis.length() - 1.0).abs() < 1e-6);
+            }
+            assert!((uvw.local(Vec3::new(0.0, 0.0, 2.0)) - 2.0 * uvw.w).length() < 1e-6);
+        }
+    }
+
+    /// Проверяет, что интеграл плотности по сфере равен единице и что у направлений `generate` плотность положительна.
+    fn check_pdf(pdf: &dyn Pdf, name: &str) {
+        const SAMPLES: u32 = 100_000;
+        let mut sampler = IndependentSampler::new(3);
+        let mut integral = 0.0;
+        for index in 0..SAMPLES {
+            sampler.start_sample(0, 0, index);
+            integral += 4.0 * PI * pdf.value(random_unit_vector(&mut sampler));
+        }
+        integral /= SAMPLES as f32;
+        assert!((integral - 1.0).abs() < 0.03, "{}: integral {}", name, integral);
+
+        for index in 0..1000 {
+            sampler.start_sample(1, 0, index);
+            let direction = pdf.generate(&mut sampler);
+            assert!(pdf.value(direction) > 0.0, "{}: {} has zero density", name, direction);
+        }
+    }
+
+    #[test]
+    fn check_densities_are_normalized() {
+        let material = Arc::new(Lambert::with_color(Vec3::new(1.0, 1.0, 1.0)));
+        let rect = XZRect { x0: -1.0, x1: 2.0, z0: -1.0, z1: 1.0, k: 2.0, material: material.clone() };
+        let sphere = Sphere { center: Vec3::new(1.0, 1.0, -3.0), radius: 1.5, material };
+        let origin = Vec3::new(0.5, 0.0, 0.0);
+
+        let cosine = CosinePdf::new(Vec3::new(0.0, 1.0, 1.0));
+        let rect_pdf = HittablePdf::new(&rect, origin);
+        let sphere_pdf = HittablePdf::new(&sphere, origin);
+        check_pdf(&cosine, "cosine");
+        check_pdf(&SpherePdf, "sphere");
+        check_pdf(&rect_pdf, "rectangle");
+        check_pdf(&sphere_pdf, "sphere light");
+        check_pdf(&MixturePdf::new(&rect_pdf, &cosine), "mixture");
+
+        // Изнутри сферы конуса нет, и направления на нее выбираются по всей сфере направлений.
+        let inside = Vec3::new(1.5, 0.5, -2.5);
+        check_pdf(&HittablePdf::new(&sphere, inside), "inside sphere light");
+        assert_eq!(1.0 / (4.0 * PI), sphere.pdf_value(inside, Vec3::new(0.0, 0.0, 1.0)));
+
+        // Прямо над точкой плотность равна квадрату расстояния до прямоугольника, деленному на его площадь.
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        assert!((rect.pdf_value(origin, up) - 4.0 / 6.0).abs() < 1e-5);
+        assert_eq!(0.0, rect.pdf_value(origin, -up));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::geom::{dot, unit_vector, Vec3};
+use crate::rng::Pcg32;
+use crate::Point;
+
+const POINT_COUNT: usize = 256;
+
+/// Генератор градиентного шума Перлина.
+///
+/// Содержит таблицу случайных единичных векторов и три таблицы перестановок по осям.
+/// Таблицы заполняются из генератора с начальным значением `seed`, поэтому шум воспроизводим.
+pub struct Perlin {
+    ranvec: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Pcg32::new(seed, 0);
+        let ranvec = (0..POINT_COUNT)
+            .map(|_| {
+                let x = rng.range(-1.0, 1.0);
+                let y = rng.range(-1.0, 1.0);
+                let z = rng.range(-1.0, 1.0);
+                unit_vector(Vec3::new(x, y, z))
+            })
+            .collect();
+
+        let perm_x = generate_perm(&mut rng);
+        let perm_y = generate_perm(&mut rng);
+        let perm_z = generate_perm(&mut rng);
+
+        Perlin { ranvec, perm_x, perm_y, perm_z }
+    }
+
+    /// Значение шума в точке `p` в диапазоне `[-1; 1]`.
+    pub fn noise(&self, p: Vec3) -> f32 {
+        let u = p.x() - p.x().floor();
+        let v = p.y() - p.y().floor();
+        let w = p.z() - p.z().floor();
+
+        let i = p.x().floor() as i32;
+        let j = p.y().floor() as i32;
+        let k = p.z().floor() as i32;
+
+        let mut c = [[[Vec3::default(); 2]; 2]; 2];
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, cell) in row.iter_mut().enumerate() {
+                    let index = self.perm_x[((i + di as i32) & 255) as usize]
+                        ^ self.perm_y[((j + dj as i32) & 255) as usize]
+                        ^ self.perm_z[((k + dk as i32) & 255) as usize];
+                    *cell = self.ranvec[index];
+                }
+            }
+        }
+
+        trilinear_interp(&c, u, v, w)
+    }
+
+    /// Турбулентность - сумма модулей шума `depth` октав с удвоением частоты и уменьшением веса вдвое.
+    pub fn turb(&self, p: Vec3, depth: usize) -> f32 {
+        let mut accum = 0.0;
+        let mut temp_p = p;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            accum += weight * self.noise(temp_p);
+            weight *= 0.5;
+            temp_p *= 2.0;
+        }
+
+        accum.abs()
+    }
+}
+
+/// Создает случайную перестановку чисел `[0; POINT_COUNT)` методом Фишера-Йетса.
+fn generate_perm(rng: &mut Pcg32) -> Vec<usize> {
+    let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+
+    for i in (1..POINT_COUNT).rev() {
+        let target = rng.next_u32() as usize % (i + 1);
+        p.swap(i, target);
+    }
+
+    p
+}
+
+/// Трилинейная интерполяция скалярных произведений градиентов со сглаживанием Эрмита.
+fn trilinear_interp(c: &[[[Vec3; 2]; 2]; 2], u: f32, v: f32, w: f32) -> f32 {
+    let uu = u * u * (3.0 - 2.0 * u);
+    let vv = v * v * (3.0 - 2.0 * v);
+    let ww = w * w * (3.0 - 2.0 * w);
+    let mut accum = 0.0;
+
+    for (i, plane) in c.iter().enumerate() {
+        for (j, row) in plane.iter().enumerate() {
+            for (k, gradient) in row.iter().enumerate() {
+                let (fi, fj, fk) = (i as f32, j as f32, k as f32);
+                let weight = Vec3::new(u - fi, v - fj, w - fk);
+                accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                    * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                    * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                    * dot(*gradient, weight);
+            }
+        }
+    }
+
+    accum
+}
+
+#[cfg(test)]
+mod test {
+    use super::Perlin;
+    use crate::geom::Vec3;
+
+    #[test]
+    fn check_same_seed_gives_same_noise() {
+        let a = Perlin::new(1);
+        let b = Perlin::new(1);
+        let p = Vec3::new(1.3, -4.7, 2.25);
+
+        assert_eq!(a.noise(p), b.noise(p));
+        assert_eq!(a.turb(p, 7), b.turb(p, 7));
+    }
+
+    #[test]
+    fn check_different_seeds_give_different_noise() {
+        let p = Vec3::new(1.3, -4.7, 2.25);
+
+        assert_ne!(Perlin::new(1).noise(p), Perlin::new(2).noise(p));
+    }
+
+    #[test]
+    fn check_noise_is_zero_on_lattice_and_bounded() {
+        let perlin = Perlin::new(5);
+        assert_eq!(0.0, perlin.noise(Vec3::new(3.0, -2.0, 7.0)));
+
+        for i in 0..1000 {
+            let t = i as f32 * 0.137;
+            let n = perlin.noise(Vec3::new(t, 0.5 * t, -t));
+            assert!((-1.0..=1.0).contains(&n));
+        }
+    }
+}
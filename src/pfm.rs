@@ -0,0 +1,41 @@
+use crate::image::Image;
+use std::io;
+use std::io::Write;
+
+/// Записывает изображение в формате PFM (Portable Float Map) без ограничения яркости.
+///
+/// Значения хранятся как есть, в линейном пространстве, числами `f32` little-endian, на что указывает
+/// отрицательный масштаб в заголовке. Строки идут снизу вверх.
+pub fn write_pfm<W: Write>(out: &mut W, image: &Image) -> io::Result<()> {
+    write!(out, "PF\n{} {}\n-1.0\n", image.width, image.height)?;
+
+    let mut data = Vec::with_capacity(image.width * image.height * 12);
+    for y in (0..image.height).rev() {
+        for x in 0..image.width {
+            for &value in &image.get(x, y).0 {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+    out.write_all(&data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::write_pfm;
+    use crate::image::Image;
+
+    #[test]
+    fn check_pfm_keeps_hdr_values_bottom_up() {
+        let mut image = Image::new(1, 2);
+        image.pixels[0] = [12.5, 0.0, -1.0].into();
+        let mut data = vec![];
+        write_pfm(&mut data, &image).unwrap();
+
+        let header = b"PF\n1 2\n-1.0\n";
+        assert_eq!(header, &data[..header.len()]);
+        assert_eq!(header.len() + 24, data.len());
+        // Верхняя строка изображения записывается последней.
+        assert_eq!(12.5f32.to_le_bytes(), data[header.len() + 12..header.len() + 16]);
+    }
+}
@@ -0,0 +1,475 @@
+use crate::geom::Vec3;
+use crate::materials::Material;
+use crate::mesh::MeshData;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+use std::{fmt, fs, io};
+
+/// Ошибка загрузки файла PLY.
+#[derive(Debug)]
+pub enum PlyError {
+    /// Ошибка ввода-вывода при чтении файла.
+    Io(io::Error),
+    /// Ошибка в строке `line` заголовка.
+    Header { line: usize, message: String },
+    /// Ошибка в данных после заголовка.
+    Data(String),
+}
+
+impl Display for PlyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io(err) => write!(f, "I/O error: {}", err),
+            PlyError::Header { line, message } => write!(f, "header line {}: {}", line, message),
+            PlyError::Data(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for PlyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PlyError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PlyError {
+    fn from(err: io::Error) -> Self {
+        PlyError::Io(err)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// Скалярный тип свойства.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => ScalarType::Int8,
+            "uchar" | "uint8" => ScalarType::UInt8,
+            "short" | "int16" => ScalarType::Int16,
+            "ushort" | "uint16" => ScalarType::UInt16,
+            "int" | "int32" => ScalarType::Int32,
+            "uint" | "uint32" => ScalarType::UInt32,
+            "float" | "float32" => ScalarType::Float32,
+            "double" | "float64" => ScalarType::Float64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+
+    /// Максимальное значение целого типа, на которое делятся компоненты цвета.
+    fn color_scale(self) -> f64 {
+        match self {
+            ScalarType::Int8 => i8::MAX as f64,
+            ScalarType::UInt8 => u8::MAX as f64,
+            ScalarType::Int16 => i16::MAX as f64,
+            ScalarType::UInt16 => u16::MAX as f64,
+            ScalarType::Int32 => i32::MAX as f64,
+            ScalarType::UInt32 => u32::MAX as f64,
+            ScalarType::Float32 | ScalarType::Float64 => 1.0,
+        }
+    }
+}
+
+/// Свойство элемента: скаляр или список со счетчиком типа `count`.
+#[derive(Debug)]
+struct Property {
+    name: String,
+    kind: ScalarType,
+    count: Option<ScalarType>,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Читает значения из данных после заголовка в текстовом или двоичном виде.
+struct DataReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    format: Format,
+}
+
+impl DataReader<'_> {
+    fn eof() -> PlyError {
+        PlyError::Data(String::from("unexpected end of file"))
+    }
+
+    fn read(&mut self, kind: ScalarType) -> Result<f64, PlyError> {
+        if self.format == Format::Ascii {
+            while self.pos < self.data.len() && self.data[self.pos].is_ascii_whitespace() {
+                self.pos += 1;
+            }
+            let start = self.pos;
+            while self.pos < self.data.len() && !self.data[self.pos].is_ascii_whitespace() {
+                self.pos += 1;
+            }
+            if start == self.pos {
+                return Err(Self::eof());
+            }
+
+            let token = String::from_utf8_lossy(&self.data[start..self.pos]);
+            return token
+                .parse()
+                .map_err(|_| PlyError::Data(format!("invalid number '{}'", token)));
+        }
+
+        let size = kind.size();
+        let bytes = self.data.get(self.pos..self.pos + size).ok_or_else(Self::eof)?;
+        self.pos += size;
+
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(bytes);
+        if self.format == Format::BinaryBigEndian {
+            buf[..size].reverse();
+        }
+
+        Ok(match kind {
+            ScalarType::Int8 => buf[0] as i8 as f64,
+            ScalarType::UInt8 => buf[0] as f64,
+            ScalarType::Int16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ScalarType::UInt16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+            ScalarType::Int32 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ScalarType::UInt32 => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ScalarType::Float32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            ScalarType::Float64 => f64::from_le_bytes(buf),
+        })
+    }
+
+    /// Читает все свойства одного экземпляра элемента.
+    fn read_element(&mut self, element: &Element) -> Result<Vec<Vec<f64>>, PlyError> {
+        let mut values = Vec::with_capacity(element.properties.len());
+        for property in &element.properties {
+            match property.count {
+                None => values.push(vec![self.read(property.kind)?]),
+                Some(count_type) => {
+                    let count = self.read(count_type)?;
+                    if count < 0.0 {
+                        return Err(PlyError::Data(format!("negative list size in '{}'", property.name)));
+                    }
+                    let list = (0..count as usize)
+                        .map(|_| self.read(property.kind))
+                        .collect::<Result<Vec<f64>, PlyError>>()?;
+                    values.push(list);
+                }
+            }
+        }
+        Ok(values)
+    }
+}
+
+/// Разбирает заголовок и возвращает формат, элементы и смещение начала данных.
+fn parse_header(data: &[u8]) -> Result<(Format, Vec<Element>, usize), PlyError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    let mut pos = 0;
+    let mut line_number = 0;
+
+    loop {
+        let end = data[pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map(|i| pos + i)
+            .ok_or(PlyError::Header { line: line_number + 1, message: String::from("missing 'end_header'") })?;
+        let line = String::from_utf8_lossy(&data[pos..end]).trim().to_string();
+        pos = end + 1;
+        line_number += 1;
+
+        let error = |message: String| PlyError::Header { line: line_number, message };
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        if line_number == 1 {
+            if line != "ply" {
+                return Err(error(String::from("not a PLY file")));
+            }
+            continue;
+        }
+
+        match words.first().cloned() {
+            Some("format") => {
+                format = Some(match words.get(1).cloned() {
+                    Some("ascii") => Format::Ascii,
+                    Some("binary_little_endian") => Format::BinaryLittleEndian,
+                    Some("binary_big_endian") => Format::BinaryBigEndian,
+                    other => return Err(error(format!("unknown format '{}'", other.unwrap_or("")))),
+                });
+            }
+            Some("element") => {
+                if words.len() != 3 {
+                    return Err(error(String::from("expected 'element <name> <count>'")));
+                }
+                let count = words[2]
+                    .parse()
+                    .map_err(|_| error(format!("invalid element count '{}'", words[2])))?;
+                elements.push(Element { name: words[1].to_string(), count, properties: vec![] });
+            }
+            Some("property") => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| error(String::from("property before any element")))?;
+                let unknown = |name: &str| error(format!("unknown property type '{}'", name));
+
+                let property = if words.get(1) == Some(&"list") {
+                    if words.len() != 5 {
+                        return Err(error(String::from("expected 'property list <count type> <item type> <name>'")));
+                    }
+                    Property {
+                        name: words[4].to_string(),
+                        kind: ScalarType::parse(words[3]).ok_or_else(|| unknown(words[3]))?,
+                        count: Some(ScalarType::parse(words[2]).ok_or_else(|| unknown(words[2]))?),
+                    }
+                } else {
+                    if words.len() != 3 {
+                        return Err(error(String::from("expected 'property <type> <name>'")));
+                    }
+                    Property {
+                        name: words[2].to_string(),
+                        kind: ScalarType::parse(words[1]).ok_or_else(|| unknown(words[1]))?,
+                        count: None,
+                    }
+                };
+                element.properties.push(property);
+            }
+            Some("end_header") => {
+                let format = format.ok_or_else(|| error(String::from("missing 'format' line")))?;
+                return Ok((format, elements, pos));
+            }
+            Some("comment") | Some("obj_info") | None => {}
+            Some(other) => return Err(error(format!("unknown header keyword '{}'", other))),
+        }
+    }
+}
+
+/// Индекс скалярного свойства по одному из имен.
+fn find_property(element: &Element, names: &[&str]) -> Option<usize> {
+    element
+        .properties
+        .iter()
+        .position(|p| p.count.is_none() && names.contains(&p.name.as_str()))
+}
+
+/// Разбирает сетку PLY из памяти.
+///
+/// Используются элементы `vertex` (координаты, необязательные нормали `nx ny nz`, цвета `red green blue`
+/// и текстурные координаты `u v` или `s t`) и `face` (список индексов вершин). Многоугольники
+/// разбиваются на треугольники веером. Прочие элементы пропускаются.
+pub fn parse_ply(data: &[u8], material: Arc<dyn Material>) -> Result<MeshData, PlyError> {
+    let (format, elements, start) = parse_header(data)?;
+    let mut reader = DataReader { data, pos: start, format };
+    let mut mesh = MeshData::new(vec![], vec![], material);
+
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => read_vertices(&mut reader, element, &mut mesh)?,
+            "face" => read_faces(&mut reader, element, &mut mesh)?,
+            _ => {
+                for _ in 0..element.count {
+                    reader.read_element(element)?;
+                }
+            }
+        }
+    }
+
+    let vertex_count = mesh.positions.len();
+    if let Some(index) = mesh.indices.iter().flatten().find(|&&i| i >= vertex_count) {
+        return Err(PlyError::Data(format!("vertex index {} out of range ({} vertices)", index, vertex_count)));
+    }
+    if mesh.indices.is_empty() {
+        return Err(PlyError::Data(String::from("no faces")));
+    }
+
+    Ok(mesh)
+}
+
+fn read_vertices(reader: &mut DataReader, element: &Element, mesh: &mut MeshData) -> Result<(), PlyError> {
+    let xyz = ["x", "y", "z"]
+        .iter()
+        .map(|name| find_property(element, &[name]))
+        .collect::<Option<Vec<usize>>>()
+        .ok_or_else(|| PlyError::Data(String::from("vertex element without x, y, z properties")))?;
+    let normal: Option<Vec<usize>> = ["nx", "ny", "nz"].iter().map(|name| find_property(element, &[name])).collect();
+    let color: Option<Vec<usize>> = [["red", "r"], ["green", "g"], ["blue", "b"]]
+        .iter()
+        .map(|names| find_property(element, names))
+        .collect();
+    let uv: Option<Vec<usize>> = [["u", "s"], ["v", "t"]]
+        .iter()
+        .map(|names| find_property(element, names))
+        .collect();
+
+    let value3 = |values: &[Vec<f64>], indices: &[usize], scale: &[f64]| {
+        Vec3::new(
+            (values[indices[0]][0] / scale[0]) as f32,
+            (values[indices[1]][0] / scale[1]) as f32,
+            (values[indices[2]][0] / scale[2]) as f32,
+        )
+    };
+    let color_scale: Vec<f64> = color
+        .iter()
+        .flatten()
+        .map(|&i| element.properties[i].kind.color_scale())
+        .collect();
+
+    for _ in 0..element.count {
+        let values = reader.read_element(element)?;
+        mesh.positions.push(value3(&values, &xyz, &[1.0; 3]));
+        if let Some(normal) = &normal {
+            mesh.normals.push(value3(&values, normal, &[1.0; 3]));
+        }
+        if let Some(color) = &color {
+            mesh.colors.push(value3(&values, color, &color_scale));
+        }
+        if let Some(uv) = &uv {
+            mesh.uvs.push((values[uv[0]][0] as f32, values[uv[1]][0] as f32));
+        }
+    }
+
+    Ok(())
+}
+
+fn read_faces(reader: &mut DataReader, element: &Element, mesh: &mut MeshData) -> Result<(), PlyError> {
+    let list = element
+        .properties
+        .iter()
+        .position(|p| p.count.is_some() && (p.name == "vertex_indices" || p.name == "vertex_index"))
+        .ok_or_else(|| PlyError::Data(String::from("face element without vertex_indices list")))?;
+
+    for _ in 0..element.count {
+        let values = reader.read_element(element)?;
+        let face: Vec<usize> = values[list].iter().map(|&i| i as usize).collect();
+        if values[list].iter().any(|&i| i < 0.0) {
+            return Err(PlyError::Data(String::from("negative vertex index")));
+        }
+        if face.len() < 3 {
+            return Err(PlyError::Data(format!("face with {} vertices", face.len())));
+        }
+        for i in 1..face.len() - 1 {
+            mesh.indices.push([face[0], face[i], face[i + 1]]);
+        }
+    }
+
+    Ok(())
+}
+
+/// Загружает сетку из файла PLY. Все грани получают материал `material`.
+pub fn load_ply<P: AsRef<Path>>(path: P, material: Arc<dyn Material>) -> Result<MeshData, PlyError> {
+    parse_ply(&fs::read(path)?, material)
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_ply;
+    use crate::geom::Vec3;
+    use crate::materials::Lambert;
+    use std::sync::Arc;
+
+    fn material() -> Arc<Lambert> {
+        Arc::new(Lambert::with_color([1.0, 1.0, 1.0].into()))
+    }
+
+    #[test]
+    fn check_ascii_ply_with_colors_and_quad() {
+        let source = "ply\nformat ascii 1.0\ncomment test\nelement vertex 4\n\
+                      property float x\nproperty float y\nproperty float z\n\
+                      property uchar red\nproperty uchar green\nproperty uchar blue\n\
+                      element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+                      0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 255 255 255\n4 0 1 2 3\n";
+        let mesh = parse_ply(source.as_bytes(), material()).unwrap();
+
+        assert_eq!(4, mesh.positions.len());
+        assert_eq!(vec![[0, 1, 2], [0, 2, 3]], mesh.indices);
+        assert_eq!(Vec3::new(0.0, 0.0, 1.0), mesh.colors[2]);
+        assert!(mesh.normals.is_empty());
+    }
+
+    fn binary_ply(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian { "binary_big_endian" } else { "binary_little_endian" };
+        let mut data = format!(
+            "ply\nformat {} 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+             property float nx\nproperty float ny\nproperty float nz\nelement edge 1\nproperty int a\n\
+             element face 1\nproperty list uchar uint vertex_indices\nend_header\n",
+            format
+        )
+        .into_bytes();
+
+        let float = |v: f32| if big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+        for p in &[[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 2.0, 0.0]] {
+            for &v in p.iter().chain([0.0f32, 0.0, 1.0].iter()) {
+                data.extend_from_slice(&float(v));
+            }
+        }
+        data.extend_from_slice(&[7, 7, 7, 7]);
+        data.push(3);
+        for i in 0u32..3 {
+            data.extend_from_slice(&if big_endian { i.to_be_bytes() } else { i.to_le_bytes() });
+        }
+        data
+    }
+
+    #[test]
+    fn check_binary_ply_both_endians() {
+        for &big_endian in &[false, true] {
+            let mesh = parse_ply(&binary_ply(big_endian), material()).unwrap();
+
+            assert_eq!(Vec3::new(2.0, 0.0, 0.0), mesh.positions[1]);
+            assert_eq!(vec![Vec3::new(0.0, 0.0, 1.0); 3], mesh.normals);
+            assert_eq!(vec![[0, 1, 2]], mesh.indices);
+        }
+    }
+
+    #[test]
+    fn check_malformed_ply() {
+        let err = |source: &[u8]| parse_ply(source, material()).err().unwrap().to_string();
+
+        assert_eq!("header line 1: not a PLY file", err(b"plx\n"));
+        assert_eq!("header line 3: unknown property type 'float3'", err(b"ply\nelement vertex 1\nproperty float3 x\n"));
+        assert_eq!("header line 2: missing 'end_header'", err(b"ply\nformat ascii 1.0"));
+        assert_eq!(
+            "unexpected end of file",
+            err(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nend_header\n1 2\n")
+        );
+
+        let mut truncated = binary_ply(false);
+        truncated.truncate(truncated.len() - 2);
+        assert_eq!("unexpected end of file", err(&truncated));
+
+        // Облако точек без граней нельзя отрисовать как сетку.
+        assert_eq!(
+            "no faces",
+            err(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nend_header\n1 2 3\n")
+        );
+    }
+}
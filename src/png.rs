@@ -0,0 +1,196 @@
+use crate::deflate::zlib_compress;
+use crate::image::Image;
+use crate::tonemap::ToneMapping;
+use std::io;
+use std::io::Write;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// Таблица CRC-32 для многочлена 0xEDB88320, вычисляемая при компиляции.
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+}
+
+/// Контрольная сумма CRC-32, которой PNG защищает каждый блок.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data
+        .iter()
+        .fold(!0u32, |crc, &byte| CRC_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+/// Записывает блок PNG: длину, тип, данные и CRC типа и данных.
+fn write_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let mut checked = kind.to_vec();
+    checked.extend_from_slice(data);
+
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(&checked)?;
+    out.write_all(&crc32(&checked).to_be_bytes())
+}
+
+/// Предсказатель Паэта: из левого, верхнего и верхнего левого соседа выбирается ближайший к `a + b - c`.
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Фильтрует строку всеми пятью фильтрами PNG и возвращает вариант с наименьшей суммой модулей байт.
+///
+/// Эта эвристика из спецификации PNG обычно заметно улучшает сжатие гладких изображений.
+fn filter_row(row: &[u8], prior: &[u8], bpp: usize) -> Vec<u8> {
+    let mut best: Option<(u64, Vec<u8>)> = None;
+
+    for filter in 0..5u8 {
+        let mut filtered = Vec::with_capacity(row.len() + 1);
+        filtered.push(filter);
+        for i in 0..row.len() {
+            let a = if i >= bpp { row[i - bpp] } else { 0 };
+            let b = prior[i];
+            let c = if i >= bpp { prior[i - bpp] } else { 0 };
+            let predicted = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                _ => paeth(a, b, c),
+            };
+            filtered.push(row[i].wrapping_sub(predicted));
+        }
+
+        let cost = filtered[1..].iter().map(|&x| (x as i8).unsigned_abs() as u64).sum();
+        if best.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost) {
+            best = Some((cost, filtered));
+        }
+    }
+
+    best.map(|(_, filtered)| filtered).unwrap_or_default()
+}
+
+/// Записывает изображение в формате PNG: 8 бит на компоненту, RGB, без чересстрочности.
+pub fn write_png<W: Write>(out: &mut W, image: &Image, tone_mapping: &ToneMapping) -> io::Result<()> {
+    const BPP: usize = 3;
+    let stride = image.width * BPP;
+    let rgb = tone_mapping.quantize(image);
+
+    let mut header = vec![];
+    header.extend_from_slice(&(image.width as u32).to_be_bytes());
+    header.extend_from_slice(&(image.height as u32).to_be_bytes());
+    // Глубина 8 бит, тип цвета 2 (RGB), сжатие Deflate, адаптивная фильтрация, без чересстрочности.
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut raw = Vec::with_capacity((stride + 1) * image.height);
+    let zero_row = vec![0u8; stride];
+    for y in 0..image.height {
+        let row = &rgb[y * stride..(y + 1) * stride];
+        let prior = if y > 0 { &rgb[(y - 1) * stride..y * stride] } else { &zero_row[..] };
+        raw.extend_from_slice(&filter_row(row, prior, BPP));
+    }
+
+    out.write_all(&SIGNATURE)?;
+    write_chunk(out, b"IHDR", &header)?;
+    write_chunk(out, b"IDAT", &zlib_compress(&raw))?;
+    write_chunk(out, b"IEND", &[])
+}
+
+#[cfg(test)]
+mod test {
+    use super::{crc32, paeth, write_png};
+    use crate::deflate::test::inflate;
+    use crate::image::Image;
+    use crate::tonemap::ToneMapping;
+
+    #[test]
+    fn check_crc32() {
+        assert_eq!(0xCBF4_3926, crc32(b"123456789"));
+        assert_eq!(0xAE42_6082, crc32(b"IEND"));
+    }
+
+    /// Восстанавливает строки изображения по фильтрам PNG.
+    fn unfilter(raw: &[u8], stride: usize, bpp: usize) -> Vec<u8> {
+        let mut pixels: Vec<u8> = vec![];
+        for (y, line) in raw.chunks(stride + 1).enumerate() {
+            for i in 0..stride {
+                let a = if i >= bpp { pixels[y * stride + i - bpp] } else { 0 };
+                let b = if y > 0 { pixels[(y - 1) * stride + i] } else { 0 };
+                let c = if i >= bpp && y > 0 { pixels[(y - 1) * stride + i - bpp] } else { 0 };
+                let predicted = match line[0] {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((a as u16 + b as u16) / 2) as u8,
+                    4 => paeth(a, b, c),
+                    filter => panic!("unknown filter {}", filter),
+                };
+                pixels.push(line[i + 1].wrapping_add(predicted));
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn check_png_structure() {
+        let mut image = Image::new(3, 2);
+        image.pixels[4] = [1.0, 0.25, 0.0].into();
+        let mut data = vec![];
+        write_png(&mut data, &image, &ToneMapping::default()).unwrap();
+
+        assert_eq!(b"\x89PNG\r\n\x1a\n", &data[..8]);
+        assert_eq!(b"\0\0\0\x0dIHDR\0\0\0\x03\0\0\0\x02\x08\x02\0\0\0", &data[8..29]);
+        assert_eq!(b"\0\0\0\0IEND\xae\x42\x60\x82", &data[data.len() - 12..]);
+    }
+
+    #[test]
+    fn check_png_pixels() {
+        // Градиенты и шум, чтобы строки выбирали разные фильтры.
+        let (width, height) = (7, 6);
+        let mut image = Image::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let noise = ((x * 7 + y * 13) % 5) as f32 / 5.0;
+                image.pixels[y * width + x] = [x as f32 / 6.0, y as f32 / 5.0, noise].into();
+            }
+        }
+        let tone_mapping = ToneMapping::default();
+        let mut data = vec![];
+        write_png(&mut data, &image, &tone_mapping).unwrap();
+
+        let mut idat = vec![];
+        let mut pos = 8;
+        while pos < data.len() {
+            let length = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
+            let checked = &data[pos + 4..pos + 8 + length];
+            let crc = &data[pos + 8 + length..pos + 12 + length];
+            assert_eq!(&crc32(checked).to_be_bytes(), crc);
+            if &checked[..4] == b"IDAT" {
+                idat.extend_from_slice(&checked[4..]);
+            }
+            pos += 12 + length;
+        }
+
+        // Поток zlib: два байта заголовка, данные Deflate и четыре байта Adler-32.
+        let raw = inflate(&idat[2..idat.len() - 4]);
+        assert_eq!((3 * width + 1) * height, raw.len());
+        assert_eq!(tone_mapping.quantize(&image), unfilter(&raw, 3 * width, 3));
+    }
+}
@@ -1,41 +1,277 @@
-use crate::utils::clamp;
-use crate::Color;
-use crate::geom::Vec3;
+use crate::image::Image;
+use crate::tonemap::ToneMapping;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::path::Path;
+use std::{fmt, fs, io};
 
-/// Преобразует цветовые компоненты пикселя к [`u8`] и печатает на экран.
-///
-/// [`u8`]: https://doc.rust-lang.org/std/primitive.u8.html
-pub fn write_color(pixel: Vec3, samples: i32)
-{
-    let mut pixel = pixel / samples as f32;
+/// Конец строки текстового PPM. В Windows принят `\r\n`, макрос `writeln!` же всегда выводит `\n`.
+const LINE_END: &str = if cfg!(target_family = "windows") { "\r\n" } else { "\n" };
+
+/// Записывает изображение в двоичном формате PPM (`P6`) по байту на компоненту.
+pub fn write_ppm<W: Write>(out: &mut W, image: &Image, tone_mapping: &ToneMapping) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", image.width, image.height)?;
+    out.write_all(&tone_mapping.quantize(image))
+}
+
+/// Записывает изображение в текстовом формате PPM (`P3`): по пикселю в строке.
+pub fn write_ppm_ascii<W: Write>(out: &mut W, image: &Image, tone_mapping: &ToneMapping) -> io::Result<()> {
+    write!(out, "P3{}{} {}{}255{}", LINE_END, image.width, image.height, LINE_END, LINE_END)?;
+    for rgb in tone_mapping.quantize(image).chunks(3) {
+        write!(out, "{} {} {}{}", rgb[0], rgb[1], rgb[2], LINE_END)?;
+    }
+    Ok(())
+}
+
+/// Ошибка чтения файла PPM.
+#[derive(Debug)]
+pub enum PpmError {
+    /// Ошибка ввода-вывода при чтении файла.
+    Io(io::Error),
+    /// Файл не начинается с `P3` или `P6`.
+    BadMagic,
+    /// Файл закончился раньше, чем были прочитаны все данные.
+    UnexpectedEof,
+    /// В заголовке или данных встретилось не число.
+    InvalidNumber(String),
+    /// Нулевой размер изображения или размер, при котором число компонент не помещается в `usize`.
+    InvalidSize(usize, usize),
+    /// Максимальное значение компоненты вне диапазона `[1; 65535]`.
+    InvalidMaxval(u32),
+    /// Значение компоненты больше максимального значения из заголовка.
+    ValueOutOfRange(u32, u32),
+}
+
+impl Display for PpmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PpmError::Io(err) => write!(f, "I/O error: {}", err),
+            PpmError::BadMagic => write!(f, "not a PPM file: expected P3 or P6 magic number"),
+            PpmError::UnexpectedEof => write!(f, "unexpected end of file"),
+            PpmError::InvalidNumber(token) => write!(f, "invalid number '{}'", token),
+            PpmError::InvalidSize(w, h) => write!(f, "invalid image size {}x{}", w, h),
+            PpmError::InvalidMaxval(maxval) => write!(f, "invalid maximum color value {}", maxval),
+            PpmError::ValueOutOfRange(value, maxval) => {
+                write!(f, "color value {} exceeds maximum {}", value, maxval)
+            }
+        }
+    }
+}
+
+impl Error for PpmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PpmError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
-    // Корректировка гаммы γ = 1/2
-    pixel.0.iter_mut().for_each(|x| { *x = x.sqrt(); });
+impl From<io::Error> for PpmError {
+    fn from(err: io::Error) -> Self {
+        PpmError::Io(err)
+    }
+}
 
-    let ir = clamp(u8::MAX as f32 * pixel.r());
-    let ig = clamp(u8::MAX as f32 * pixel.g());
-    let ib = clamp(u8::MAX as f32 * pixel.b());
+/// Разбирает заголовок и данные PPM, пропуская пробельные символы и комментарии.
+struct PpmReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
 
-    // В операционной системе Windows макрос println! выводит концы строк в Unix стиле
-    if cfg!(target_family = "windows") {
-        print!("{} {} {}\r\n", ir, ig, ib);
+impl<'a> PpmReader<'a> {
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(&c) = self.data.get(self.pos) {
+            if c == b'#' {
+                while self.pos < self.data.len() && self.data[self.pos] != b'\n' && self.data[self.pos] != b'\r' {
+                    self.pos += 1;
+                }
+            } else if c.is_ascii_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
     }
-    if cfg!(target_family = "unix") {
-        println!("{} {} {}", ir, ig, ib);
+
+    fn token(&mut self) -> Result<&'a [u8], PpmError> {
+        self.skip_whitespace_and_comments();
+        let start = self.pos;
+        while self.pos < self.data.len() && !self.data[self.pos].is_ascii_whitespace() && self.data[self.pos] != b'#' {
+            self.pos += 1;
+        }
+
+        if start == self.pos {
+            Err(PpmError::UnexpectedEof)
+        } else {
+            Ok(&self.data[start..self.pos])
+        }
+    }
+
+    fn number(&mut self) -> Result<u32, PpmError> {
+        let token = self.token()?;
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| PpmError::InvalidNumber(String::from_utf8_lossy(token).into_owned()))
     }
 }
 
-// В операционной системе Windows макрос println! выводит концы строк в Unix стиле
-#[cfg(target_family = "windows")]
-pub fn write_ppm_header(image_width: i32, image_height: i32) {
-    print!("P3\r\n");
-    print!("{} {}\r\n", image_width, image_height);
-    print!("255\r\n");
+/// Читает изображение из файла PPM формата `P3` (текстовый) или `P6` (двоичный).
+pub fn read_ppm<P: AsRef<Path>>(path: P) -> Result<Image, PpmError> {
+    parse_ppm(&fs::read(path)?)
+}
+
+/// Разбирает изображение PPM из памяти.
+///
+/// Компоненты цвета приводятся к диапазону `[0; 1]` делением на максимальное значение из заголовка.
+pub fn parse_ppm(data: &[u8]) -> Result<Image, PpmError> {
+    let mut reader = PpmReader { data, pos: 0 };
+
+    let binary = match reader.token() {
+        Ok(b"P3") => false,
+        Ok(b"P6") => true,
+        _ => return Err(PpmError::BadMagic),
+    };
+
+    let width = reader.number()? as usize;
+    let height = reader.number()? as usize;
+    if width == 0 || height == 0 {
+        return Err(PpmError::InvalidSize(width, height));
+    }
+
+    let maxval = reader.number()?;
+    if maxval == 0 || maxval > u16::MAX as u32 {
+        return Err(PpmError::InvalidMaxval(maxval));
+    }
+
+    let count = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(3))
+        .ok_or(PpmError::InvalidSize(width, height))?;
+    // В двоичном формате после максимального значения следует ровно один пробельный символ.
+    if binary {
+        reader.pos += 1;
+    }
+    // Каждая компонента занимает хотя бы байт даже в текстовом формате, поэтому размер из заголовка
+    // проверяется по длине файла до выделения памяти.
+    let bytes_per_value = if binary && maxval >= 256 { 2 } else { 1 };
+    let needed = count.checked_mul(bytes_per_value).ok_or(PpmError::InvalidSize(width, height))?;
+    if needed > data.len().saturating_sub(reader.pos) {
+        return Err(PpmError::UnexpectedEof);
+    }
+
+    let mut values = Vec::with_capacity(count);
+    if binary {
+        let raster = &data[reader.pos..reader.pos + needed];
+        if bytes_per_value == 1 {
+            values.extend(raster.iter().map(|&b| b as u32));
+        } else {
+            values.extend(raster.chunks(2).map(|b| u16::from_be_bytes([b[0], b[1]]) as u32));
+        }
+    } else {
+        for _ in 0..count {
+            values.push(reader.number()?);
+        }
+    }
+
+    let scale = 1.0 / maxval as f32;
+    let mut image = Image::new(width, height);
+    for (pixel, rgb) in image.pixels.iter_mut().zip(values.chunks(3)) {
+        for (c, &value) in rgb.iter().enumerate() {
+            if value > maxval {
+                return Err(PpmError::ValueOutOfRange(value, maxval));
+            }
+            pixel[c] = value as f32 * scale;
+        }
+    }
+
+    Ok(image)
 }
 
-#[cfg(target_family = "unix")]
-pub fn write_ppm_header(image_width: i32, image_height: i32) {
-    println!("P3");
-    println!("{} {}", image_width, image_height);
-    println!("255");
+#[cfg(test)]
+mod test {
+    use super::{parse_ppm, write_ppm, write_ppm_ascii, PpmError};
+    use crate::geom::Vec3;
+    use crate::image::Image;
+    use crate::tonemap::ToneMapping;
+
+    fn assert_close(expected: Vec3, actual: Vec3) {
+        assert!((expected - actual).length() < 1e-6, "{} != {}", expected, actual);
+    }
+
+    #[test]
+    fn check_ascii_ppm_with_comments() {
+        let data = b"P3\n# created by hand\n2 1 # size\n255\n255 0 0\n# second pixel\n0 51 255\n";
+        let image = parse_ppm(data).unwrap();
+
+        assert_eq!((2, 1), (image.width, image.height));
+        assert_eq!(Vec3::new(1.0, 0.0, 0.0), image.get(0, 0));
+        assert_close(Vec3::new(0.0, 0.2, 1.0), image.get(1, 0));
+    }
+
+    #[test]
+    fn check_binary_ppm_with_small_maxval() {
+        let mut data = b"P6 1 2 15\n".to_vec();
+        data.extend_from_slice(&[15, 0, 3, 0, 15, 0]);
+        let image = parse_ppm(&data).unwrap();
+
+        assert_close(Vec3::new(1.0, 0.0, 0.2), image.get(0, 0));
+        assert_eq!(Vec3::new(0.0, 1.0, 0.0), image.get(0, 1));
+    }
+
+    #[test]
+    fn check_binary_ppm_with_16_bit_values() {
+        let mut data = b"P6\n1 1\n65535\n".to_vec();
+        data.extend_from_slice(&[0xff, 0xff, 0x00, 0x00, 0x80, 0x00]);
+        let image = parse_ppm(&data).unwrap();
+
+        assert_eq!(1.0, image.get(0, 0)[0]);
+        assert_eq!(0.0, image.get(0, 0)[1]);
+        assert!((image.get(0, 0)[2] - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn check_malformed_ppm() {
+        assert!(matches!(parse_ppm(b"P5 1 1 255 0"), Err(PpmError::BadMagic)));
+        assert!(matches!(parse_ppm(b"P3 1 1 255 0 0"), Err(PpmError::UnexpectedEof)));
+        assert!(matches!(parse_ppm(b"P3 1 x 255"), Err(PpmError::InvalidNumber(_))));
+        assert!(matches!(parse_ppm(b"P3 0 1 255"), Err(PpmError::InvalidSize(0, 1))));
+        assert!(matches!(parse_ppm(b"P3 1 1 70000"), Err(PpmError::InvalidMaxval(70000))));
+        assert!(matches!(parse_ppm(b"P3 1 1 7 1 8 1"), Err(PpmError::ValueOutOfRange(8, 7))));
+        assert!(matches!(parse_ppm(b"P6 2 2 255\n\x01\x02"), Err(PpmError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn check_oversized_ppm_header() {
+        // Размер из заголовка не должен приводить к выделению памяти или переполнению.
+        assert!(matches!(parse_ppm(b"P6 100000 100000 255\n\x01\x02\x03"), Err(PpmError::UnexpectedEof)));
+        assert!(matches!(parse_ppm(b"P3 100000 100000 255 1 2 3"), Err(PpmError::UnexpectedEof)));
+        assert!(matches!(
+            parse_ppm(b"P6 4294967295 4294967295 65535\n\x01\x02"),
+            Err(PpmError::InvalidSize(4294967295, 4294967295))
+        ));
+    }
+
+    #[test]
+    fn check_written_ppm_reads_back() {
+        let mut image = Image::new(2, 1);
+        image.pixels[0] = Vec3::new(1.0, 0.25, 0.0);
+        image.pixels[1] = Vec3::new(4.0, -1.0, 0.0);
+
+        for binary in [false, true] {
+            let mut data = vec![];
+            if binary {
+                write_ppm(&mut data, &image, &ToneMapping::default()).unwrap();
+            } else {
+                write_ppm_ascii(&mut data, &image, &ToneMapping::default()).unwrap();
+            }
+            let read = parse_ppm(&data).unwrap();
+
+            // Кодирование sRGB переводит 0.25 в 137/255, яркость вне диапазона ограничивается.
+            assert_close(Vec3::new(1.0, 137.0 / 255.0, 0.0), read.get(0, 0));
+            assert_eq!(Vec3::new(1.0, 0.0, 0.0), read.get(1, 0));
+        }
+    }
 }
@@ -1,15 +1,18 @@
 use crate::geom::Vec3;
 
 /// Луч, направленный из точки `from` в направлении `dir`.
+///
+/// Поле `time` хранит момент времени, в который испущен луч, и используется для размытия движения.
 pub struct Ray {
     pub orig: Vec3,
     pub dir: Vec3,
+    pub time: f32,
 }
 
 impl Ray {
     /// Создает луч с началом в точке `from` и направленный в точку `to`.
-    pub fn new(orig: Vec3, dir: Vec3) -> Self {
-        Self { orig, dir }
+    pub fn new(orig: Vec3, dir: Vec3, time: f32) -> Self {
+        Self { orig, dir, time }
     }
 
     /// Координаты точки, из которой исходит луч.
@@ -22,6 +25,11 @@ impl Ray {
         self.dir
     }
 
+    /// Момент времени, в который испущен луч.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
     /// Координаты точки, лежащей на луче в отрезке `[orig; orig + dir]`.
     ///
     /// Параметр `t` принимает значения в диапазоне `[0; 1]`.
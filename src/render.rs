@@ -0,0 +1,313 @@
+use crate::bodies::Hittable;
+use crate::camera::Camera;
+use crate::film::Film;
+use crate::geom::Vec3;
+use crate::{ray_color, Background, World};
+use crate::sampler::{Sampler, SamplerKind};
+use crate::tonemap::luminance;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Параметры отрисовки изображения.
+pub struct RenderSettings {
+    pub image_width: i32,
+    pub image_height: i32,
+    pub samples_per_pixel: i32,
+    pub depth: i32,
+    /// Число отражений, после которого путь луча может оборваться русской рулеткой.
+    pub roulette_depth: i32,
+    /// Число рабочих потоков.
+    pub threads: usize,
+    /// Размер стороны квадратной плитки в пикселях.
+    pub tile_size: i32,
+    /// Начальное значение генератора случайных чисел.
+    ///
+    /// Числа каждого сэмпла зависят только от `seed`, пикселя и номера сэмпла,
+    /// поэтому результат не зависит от числа потоков и порядка, в котором они забирают плитки.
+    pub seed: u64,
+    /// Алгоритм выбора сэмплов.
+    pub sampler: SamplerKind,
+    /// Адаптивная выборка; `None` - каждый пиксель получает ровно `samples_per_pixel` сэмплов.
+    pub adaptive: Option<AdaptiveSampling>,
+}
+
+/// Настройки адаптивной выборки.
+///
+/// Пиксель получает не меньше `min_samples` сэмплов, после чего выборка останавливается, как только
+/// относительная ошибка среднего становится не больше `threshold`. Наибольшее число сэмплов -
+/// `samples_per_pixel` из настроек отрисовки.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AdaptiveSampling {
+    pub min_samples: u32,
+    /// Допустимое отношение стандартной ошибки среднего яркости к самой яркости.
+    pub threshold: f32,
+}
+
+/// Яркость, ниже которой ошибка почти черного пикселя считается относительно этого значения,
+/// иначе небольшой шум в темноте заставлял бы брать максимум сэмплов.
+const MIN_MEAN_LUMINANCE: f32 = 0.01;
+
+/// Среднее и дисперсия яркости сэмплов пикселя, которые обновляются по алгоритму Уэлфорда.
+#[derive(Default)]
+struct PixelStats {
+    count: u32,
+    mean: f32,
+    m2: f32,
+}
+
+impl PixelStats {
+    fn add(&mut self, value: f32) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Стандартная ошибка среднего, деленная на среднее.
+    ///
+    /// Для NaN-сэмплов тоже NaN, поэтому такой пиксель никогда не считается сошедшимся.
+    fn relative_error(&self) -> f32 {
+        if self.count < 2 {
+            return f32::INFINITY;
+        }
+        let variance = self.m2 / (self.count - 1) as f32;
+        (variance / self.count as f32).sqrt() / self.mean.max(MIN_MEAN_LUMINANCE)
+    }
+}
+
+impl RenderSettings {
+    /// Число потоков по умолчанию - по количеству доступных ядер.
+    pub fn default_threads() -> usize {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    }
+}
+
+/// Прямоугольный участок изображения в координатах выходного файла (строка 0 - верхняя).
+#[derive(Copy, Clone)]
+struct Tile {
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+}
+
+/// Разбивает изображение на плитки в порядке слева направо, сверху вниз.
+fn make_tiles(settings: &RenderSettings) -> Vec<Tile> {
+    let size = settings.tile_size.max(1);
+    let mut tiles = vec![];
+
+    for y0 in (0..settings.image_height).step_by(size as usize) {
+        for x0 in (0..settings.image_width).step_by(size as usize) {
+            tiles.push(Tile {
+                x0,
+                y0,
+                x1: (x0 + size).min(settings.image_width),
+                y1: (y0 + size).min(settings.image_height),
+            });
+        }
+    }
+
+    tiles
+}
+
+/// Вычисляет сумму сэмплов одного пикселя и их число.
+///
+/// Строка `row` отсчитывается от верхнего края изображения.
+#[allow(clippy::too_many_arguments)]
+fn render_pixel<T>(
+    world: &T,
+    lights: &World,
+    camera: &Camera,
+    background: &Background,
+    settings: &RenderSettings,
+    sampler: &mut dyn Sampler,
+    i: i32,
+    row: i32,
+) -> (Vec3, u32)
+where
+    T: Hittable,
+{
+    let j = settings.image_height - 1 - row;
+    let max_samples = settings.samples_per_pixel.max(0) as u32;
+    let min_samples = settings.adaptive.map_or(max_samples, |adaptive| adaptive.min_samples.min(max_samples));
+    let mut pixel = Vec3::default();
+    let mut stats = PixelStats::default();
+
+    while stats.count < max_samples {
+        sampler.start_sample(i as u32, row as u32, stats.count);
+        let (x, y) = sampler.next_2d();
+        // Пиксель занимает квадрат `[i; i + 1)`, поэтому изображение шириной в один пиксель тоже допустимо.
+        let u = (i as f32 + x) / settings.image_width as f32;
+        let v = (j as f32 + y) / settings.image_height as f32;
+        let ray = camera.get_ray(u, v, sampler);
+
+        let color = ray_color(ray, world, lights, background, settings.depth, settings.roulette_depth, sampler);
+        pixel += color;
+        stats.add(luminance(color));
+
+        if let Some(adaptive) = settings.adaptive {
+            if stats.count >= min_samples && stats.relative_error() <= adaptive.threshold {
+                break;
+            }
+        }
+    }
+
+    (pixel, stats.count)
+}
+
+/// Отрисовывает изображение в несколько потоков.
+///
+/// Изображение делится на плитки, которые потоки забирают из общей очереди.
+///
+/// По источникам света `lights` выбираются направления рассеяния; пустой список отключает такую выборку.
+pub fn render<T>(world: &T, lights: &World, camera: &Camera, background: &Background, settings: &RenderSettings) -> Film
+where
+    T: Hittable,
+{
+    let width = settings.image_width.max(0) as usize;
+    let height = settings.image_height.max(0) as usize;
+    let tiles = make_tiles(settings);
+    let next_tile = AtomicUsize::new(0);
+    let film = Mutex::new(Film::new(width, height));
+
+    thread::scope(|scope| {
+        for _ in 0..settings.threads.max(1) {
+            scope.spawn(|| {
+                // Генератор потока перезапускается в начале каждого сэмпла, поэтому его можно переиспользовать.
+                let mut sampler = settings.sampler.create(settings.seed, settings.samples_per_pixel.max(1) as u32);
+                loop {
+                    let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                    let tile = match tiles.get(index) {
+                        Some(tile) => *tile,
+                        None => break,
+                    };
+
+                    let mut pixels = Vec::with_capacity(((tile.x1 - tile.x0) * (tile.y1 - tile.y0)) as usize);
+                    for row in tile.y0..tile.y1 {
+                        for i in tile.x0..tile.x1 {
+                            pixels.push(render_pixel(world, lights, camera, background, settings, sampler.as_mut(), i, row));
+                        }
+                    }
+
+                    let mut film = film.lock().unwrap();
+                    let mut pixels = pixels.into_iter();
+                    for row in tile.y0..tile.y1 {
+                        for i in tile.x0..tile.x1 {
+                            let (sum, count) = pixels.next().unwrap_or_default();
+                            film.add_samples(i as usize, row as usize, sum, count);
+                        }
+                    }
+                    drop(film);
+
+                    eprintln!("Tiles remaining: {}", tiles.len().saturating_sub(index + 1));
+                }
+            });
+        }
+    });
+
+    film.into_inner().unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{render, AdaptiveSampling, RenderSettings};
+    use crate::bodies::{Hittable, Sphere};
+    use crate::camera::Camera;
+    use crate::film::Film;
+    use crate::materials::Lambert;
+    use crate::sampler::SamplerKind;
+    use crate::{color, Background, World};
+    use std::sync::Arc;
+
+    fn render_sphere(threads: usize, tile_size: i32, seed: u64, samples_per_pixel: i32, adaptive: Option<AdaptiveSampling>) -> Film {
+        let material = Arc::new(Lambert::with_color([0.5, 0.5, 0.5].into()));
+        let sphere: Box<dyn Hittable> = Box::new(Sphere { center: [0.0, 0.0, 0.0].into(), radius: 1.0, material });
+        let world = World(vec![sphere]);
+        let camera = Camera::new([0.0, 0.0, 4.0].into(), [0.0, 0.0, 0.0].into(), [0.0, 1.0, 0.0].into(), 40.0, 1.0, 0.1, 4.0, 0.0, 1.0);
+        let background = Background::Gradient { bottom: color::WHITE, top: color::LIGHT_BLUE };
+
+        let settings = RenderSettings {
+            image_width: 24,
+            image_height: 24,
+            samples_per_pixel,
+            depth: 8,
+            roulette_depth: 3,
+            threads,
+            tile_size,
+            seed,
+            sampler: SamplerKind::Sobol,
+            adaptive,
+        };
+        render(&world, &World(vec![]), &camera, &background, &settings)
+    }
+
+    #[test]
+    fn check_same_seed_gives_same_image() {
+        let image = |threads: usize, seed: u64| render_sphere(threads, 5, seed, 4, None).to_image().pixels;
+
+        let reference = image(1, 42);
+        assert_eq!(reference, image(4, 42));
+        assert_ne!(reference, image(4, 43));
+    }
+
+    #[test]
+    fn check_tiles_do_not_change_image() {
+        // Одна плитка на все изображение в одном потоке - это обычный построчный обход.
+        let bits = |threads: usize, tile_size: i32| -> Vec<u32> {
+            let image = render_sphere(threads, tile_size, 42, 4, None).to_image();
+            image.pixels.iter().flat_map(|pixel| (0..3).map(move |c| pixel[c].to_bits())).collect()
+        };
+
+        let scanline = bits(1, 24);
+        for &threads in &[1, 4] {
+            for &tile_size in &[1, 5, 7, 16, 24] {
+                assert!(scanline == bits(threads, tile_size), "threads {}, tile size {}", threads, tile_size);
+            }
+        }
+    }
+
+    #[test]
+    fn check_single_pixel_image() {
+        let material = Arc::new(Lambert::with_color([0.5, 0.5, 0.5].into()));
+        let world = World(vec![Box::new(Sphere { center: [0.0, 0.0, 0.0].into(), radius: 1.0, material }) as Box<dyn Hittable>]);
+        let camera = Camera::new([0.0, 0.0, 4.0].into(), [0.0, 0.0, 0.0].into(), [0.0, 1.0, 0.0].into(), 40.0, 1.0, 0.0, 4.0, 0.0, 1.0);
+        let background = Background::Solid(color::WHITE);
+        let settings = RenderSettings {
+            image_width: 1,
+            image_height: 1,
+            samples_per_pixel: 16,
+            depth: 8,
+            roulette_depth: 3,
+            threads: 1,
+            tile_size: 16,
+            seed: 1,
+            sampler: SamplerKind::Independent,
+            adaptive: None,
+        };
+
+        // Лучи единственного пикселя проходят через весь кадр, и сфера в центре его затемняет.
+        let pixel = render(&world, &World(vec![]), &camera, &background, &settings).to_image().pixels[0];
+        assert!((0..3).all(|c| pixel[c].is_finite() && pixel[c] < 1.0));
+    }
+
+    #[test]
+    fn check_adaptive_sampling() {
+        let adaptive = AdaptiveSampling { min_samples: 8, threshold: 0.02 };
+        let film = render_sphere(4, 5, 42, 256, Some(adaptive));
+        let counts: Vec<u32> = (0..24).flat_map(|y| (0..24).map(move |x| (x, y))).map(|(x, y)| film.sample_count(x, y)).collect();
+
+        assert!(counts.iter().all(|count| (8..=256).contains(count)));
+        // Гладкое небо в углу сходится сразу, а освещенная сфера в центре требует больше сэмплов.
+        assert_eq!(8, film.sample_count(0, 0));
+        assert!(film.sample_count(12, 12) > 8);
+        assert!(counts.iter().sum::<u32>() < 256 * 24 * 24 / 2);
+
+        let map = film.sample_map();
+        assert_eq!(1.0, map.pixels.iter().map(|pixel| pixel[0]).fold(0.0, f32::max));
+        assert_eq!(8.0 / *counts.iter().max().unwrap() as f32, map.pixels[0][0]);
+
+        let fixed = render_sphere(1, 5, 42, 4, None);
+        assert!((0..24).all(|x| fixed.sample_count(x, 0) == 4 && fixed.sample_count(x, 12) == 4));
+    }
+}
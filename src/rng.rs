@@ -0,0 +1,84 @@
+/// Генератор псевдослучайных чисел PCG32 (XSH RR) Мелиссы О'Нил.
+///
+/// Состояние занимает 16 байт и копируется явно, поэтому последовательность чисел зависит только
+/// от начального значения и номера потока, а не от порядка работы потоков отрисовки.
+#[derive(Clone, Debug)]
+pub struct Pcg32 {
+    state: u64,
+    inc: u64,
+}
+
+const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+
+/// Перемешивание SplitMix64: близкие ключи дают несвязанные значения.
+pub fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+impl Pcg32 {
+    /// Создает генератор с начальным значением `seed` и номером последовательности `stream`.
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Pcg32 { state: 0, inc: (stream << 1) | 1 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    /// Генератор для сэмпла `sample` пикселя `(x, y)`.
+    ///
+    /// Каждый сэмпл получает свою последовательность, поэтому изображение не зависит от того,
+    /// какой поток и в каком порядке его считает.
+    pub fn for_sample(seed: u64, x: u32, y: u32, sample: u32) -> Self {
+        let pixel = ((y as u64) << 32) | x as u64;
+        Pcg32::new(mix(seed ^ mix(pixel)), mix(seed.wrapping_add(sample as u64)))
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    /// Равномерно распределенное число из `[0; 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+
+    /// Равномерно распределенное число из `[low; high)`.
+    pub fn range(&mut self, low: f32, high: f32) -> f32 {
+        low + (high - low) * self.next_f32()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Pcg32;
+
+    #[test]
+    fn check_reference_sequence() {
+        // Первые значения демонстрационной программы pcg32-global-demo для pcg32_srandom(42, 54).
+        let mut rng = Pcg32::new(42, 54);
+        let values: Vec<u32> = (0..6).map(|_| rng.next_u32()).collect();
+        assert_eq!(vec![0xA15C_02B7, 0x7B47_F409, 0xBA1D_3330, 0x83D2_F293, 0xBFA4_784B, 0xCBED_606E], values);
+    }
+
+    #[test]
+    fn check_sample_streams() {
+        let first = |mut rng: Pcg32| (0..4).map(|_| rng.next_u32()).collect::<Vec<_>>();
+
+        assert_eq!(first(Pcg32::for_sample(1, 2, 3, 4)), first(Pcg32::for_sample(1, 2, 3, 4)));
+        assert_ne!(first(Pcg32::for_sample(1, 2, 3, 4)), first(Pcg32::for_sample(1, 2, 3, 5)));
+        assert_ne!(first(Pcg32::for_sample(1, 2, 3, 4)), first(Pcg32::for_sample(1, 3, 2, 4)));
+        assert_ne!(first(Pcg32::for_sample(1, 2, 3, 4)), first(Pcg32::for_sample(2, 2, 3, 4)));
+
+        let mut rng = Pcg32::new(7, 0);
+        let mean = (0..10000).map(|_| rng.range(-1.0, 3.0)).sum::<f32>() / 10000.0;
+        assert!((mean - 1.0).abs() < 0.05);
+    }
+}
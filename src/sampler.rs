@@ -0,0 +1,427 @@
+use crate::rng::{mix, Pcg32};
+use std::str::FromStr;
+
+/// Наибольшее число типа `f32`, меньшее единицы.
+const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
+
+/// Источник случайных чисел для одного сэмпла пикселя.
+///
+/// Каждое обращение берет следующее измерение сэмпла: сначала положение в пикселе, затем точку на линзе,
+/// затем измерения для рассеяния на каждом отскоке. Генераторы с низким расхождением распределяют
+/// сэмплы пикселя в каждом измерении равномернее независимых случайных чисел.
+pub trait Sampler {
+    /// Переходит к сэмплу `index` пикселя `(x, y)`; измерения снова отсчитываются с нуля.
+    fn start_sample(&mut self, x: u32, y: u32, index: u32);
+
+    /// Число из `[0; 1)` для следующего измерения.
+    fn next_1d(&mut self) -> f32;
+
+    /// Точка квадрата `[0; 1)²` для двух следующих измерений.
+    fn next_2d(&mut self) -> (f32, f32);
+}
+
+/// Алгоритм выбора сэмплов.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SamplerKind {
+    /// Независимые случайные числа.
+    Independent,
+    /// Случайное смещение внутри слоев, на которые разбито каждое измерение.
+    Stratified,
+    /// Последовательность Холтона со скремблированием цифр.
+    Halton,
+    /// Последовательность Соболя со скремблированием Оуэна.
+    Sobol,
+    /// Аддитивная последовательность R2 Мартина Робертса.
+    R2,
+}
+
+impl SamplerKind {
+    /// Создает генератор для `samples_per_pixel` сэмплов на пиксель с начальным значением `seed`.
+    pub fn create(self, seed: u64, samples_per_pixel: u32) -> Box<dyn Sampler> {
+        let state = SampleState::new(seed);
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerKind::Stratified => Box::new(StratifiedSampler { state, samples: samples_per_pixel.max(1) }),
+            SamplerKind::Halton => Box::new(HaltonSampler { state }),
+            SamplerKind::Sobol => Box::new(SobolSampler { state }),
+            SamplerKind::R2 => Box::new(R2Sampler { state }),
+        }
+    }
+}
+
+impl FromStr for SamplerKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "independent" => Ok(SamplerKind::Independent),
+            "stratified" => Ok(SamplerKind::Stratified),
+            "halton" => Ok(SamplerKind::Halton),
+            "sobol" => Ok(SamplerKind::Sobol),
+            "r2" => Ok(SamplerKind::R2),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Независимые случайные числа. Генератор перезапускается для каждого сэмпла.
+pub struct IndependentSampler {
+    seed: u64,
+    rng: Pcg32,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        IndependentSampler { seed, rng: Pcg32::new(seed, 0) }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.rng = Pcg32::for_sample(self.seed, x, y, index);
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        self.rng.next_f32()
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        (self.rng.next_f32(), self.rng.next_f32())
+    }
+}
+
+/// Общее состояние генераторов с низким расхождением: номер сэмпла, текущее измерение
+/// и ключ пикселя, от которого зависит скремблирование.
+struct SampleState {
+    seed: u64,
+    pixel: u64,
+    index: u32,
+    dimension: u32,
+    /// Случайные числа для сдвигов внутри слоев и для измерений сверх поддерживаемых.
+    rng: Pcg32,
+}
+
+impl SampleState {
+    fn new(seed: u64) -> Self {
+        SampleState { seed, pixel: mix(seed), index: 0, dimension: 0, rng: Pcg32::new(seed, 0) }
+    }
+
+    fn start(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel = mix(self.seed ^ mix(((y as u64) << 32) | x as u64));
+        self.index = index;
+        self.dimension = 0;
+        self.rng = Pcg32::for_sample(self.seed, x, y, index);
+    }
+
+    /// Ключ скремблирования следующего измерения; `count` измерений считаются занятыми.
+    fn next_key(&mut self, count: u32) -> u64 {
+        let key = mix(self.pixel ^ self.dimension as u64);
+        self.dimension += count;
+        key
+    }
+}
+
+/// Перестановка чисел `[0; len)`, заданная ключом `key` (Kensler, "Correlated Multi-Jittered Sampling").
+fn permute(mut i: u32, len: u32, key: u32) -> u32 {
+    let mut w = len - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= key;
+        i = i.wrapping_mul(0xE170_893D);
+        i ^= key >> 16;
+        i ^= (i & w) >> 4;
+        i ^= key >> 8;
+        i = i.wrapping_mul(0x0929_EB3F);
+        i ^= key >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | key >> 27);
+        i = i.wrapping_mul(0x6935_FA69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74DC_B303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9E50_1CC3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xC860_A3DF);
+        i &= w;
+        i ^= i >> 5;
+        if i < len {
+            return (i + key) % len;
+        }
+    }
+}
+
+/// Разбивает каждое измерение на столько слоев, сколько сэмплов в пикселе, и берет по точке из каждого.
+///
+/// Порядок слоев перемешивается независимо для каждого измерения, поэтому измерения не коррелируют.
+struct StratifiedSampler {
+    state: SampleState,
+    samples: u32,
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.state.start(x, y, index);
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let key = self.state.next_key(1) as u32;
+        let stratum = permute(self.state.index % self.samples, self.samples, key);
+        ((stratum as f32 + self.state.rng.next_f32()) / self.samples as f32).min(ONE_MINUS_EPSILON)
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        // Сетка nx на ny, близкая к квадратной; при неквадратном числе сэмплов часть ячеек пропускается.
+        let nx = (self.samples as f32).sqrt().ceil() as u32;
+        let ny = self.samples.div_ceil(nx);
+        let key = self.state.next_key(2) as u32;
+        let cell = permute(self.state.index % self.samples, nx * ny, key);
+        let x = ((cell % nx) as f32 + self.state.rng.next_f32()) / nx as f32;
+        let y = ((cell / nx) as f32 + self.state.rng.next_f32()) / ny as f32;
+        (x.min(ONE_MINUS_EPSILON), y.min(ONE_MINUS_EPSILON))
+    }
+}
+
+/// Простые числа - основания последовательности Холтона для первых измерений.
+const PRIMES: [u32; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109,
+    113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193, 197, 199, 211, 223, 227, 229, 233, 239,
+    241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307, 311,
+];
+
+/// Обращение цифр номера `index` в системе счисления `base`, каждая цифра которого сдвинута
+/// по модулю основания на случайную величину, зависящую от ключа и разряда.
+fn scrambled_radical_inverse(base: u32, mut index: u64, key: u64) -> f32 {
+    let inv_base = 1.0 / base as f64;
+    let mut weight = inv_base;
+    let mut result = 0.0;
+    let mut digit_index = 0;
+    // Сдвигаются и нулевые старшие цифры, иначе малые номера дали бы точки у нуля.
+    while weight > 1e-9 {
+        let digit = index % base as u64;
+        index /= base as u64;
+        let shift = mix(key ^ digit_index) % base as u64;
+        result += ((digit + shift) % base as u64) as f64 * weight;
+        weight *= inv_base;
+        digit_index += 1;
+    }
+    (result as f32).min(ONE_MINUS_EPSILON)
+}
+
+/// Последовательность Холтона: измерение `d` - обращение номера сэмпла по основанию `d`-го простого числа.
+///
+/// Измерения дальше таблицы простых чисел заполняются независимыми случайными числами.
+struct HaltonSampler {
+    state: SampleState,
+}
+
+impl Sampler for HaltonSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.state.start(x, y, index);
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let dimension = self.state.dimension as usize;
+        let key = self.state.next_key(1);
+        match PRIMES.get(dimension) {
+            Some(&base) => scrambled_radical_inverse(base, self.state.index as u64, key),
+            None => self.state.rng.next_f32(),
+        }
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        (self.next_1d(), self.next_1d())
+    }
+}
+
+/// Перестановка Лейна-Карраса: младшие биты влияют только на старшие.
+fn laine_karras(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6C50_B47C);
+    x ^= x.wrapping_mul(0xB82F_1E52);
+    x ^= x.wrapping_mul(0xC7AF_E638);
+    x ^= x.wrapping_mul(0x8D22_F6E6);
+    x
+}
+
+/// Скремблирование Оуэна двоичной дроби через хэш (Burley, "Practical Hash-based Owen Scrambling").
+fn owen_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras(x.reverse_bits(), seed).reverse_bits()
+}
+
+/// Второе измерение последовательности Соболя; первое - обращение битов номера.
+fn sobol_second(mut index: u32) -> u32 {
+    let mut v = 1 << 31;
+    let mut result = 0;
+    while index != 0 {
+        if index & 1 == 1 {
+            result ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    result
+}
+
+fn fraction(bits: u32) -> f32 {
+    (bits >> 8) as f32 / (1 << 24) as f32
+}
+
+/// Двумерная последовательность Соболя, дополненная до любого числа измерений.
+///
+/// Каждая пара измерений получает свою перестановку номеров сэмплов и свое скремблирование Оуэна,
+/// поэтому пары не коррелируют, а степень двойки сэмплов в пикселе остается (0, m, 2)-сетью.
+struct SobolSampler {
+    state: SampleState,
+}
+
+impl SobolSampler {
+    fn scrambled_index(&mut self, count: u32) -> (u32, u64) {
+        let key = self.state.next_key(count);
+        (owen_scramble(self.state.index, key as u32), key)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.state.start(x, y, index);
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let (index, key) = self.scrambled_index(1);
+        fraction(owen_scramble(index.reverse_bits(), (key >> 32) as u32))
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        let (index, key) = self.scrambled_index(2);
+        let x = owen_scramble(index.reverse_bits(), (key >> 32) as u32);
+        let y = owen_scramble(sobol_second(index), mix(key) as u32);
+        (fraction(x), fraction(y))
+    }
+}
+
+/// Последовательности Робертса: точка `i` равна `{0.5 + i·α}` для иррациональных `α`,
+/// в двумерном случае связанных с пластическим числом.
+///
+/// Для каждой пары измерений последовательность начинается с другого номера и сдвигается на случайный вектор.
+struct R2Sampler {
+    state: SampleState,
+}
+
+impl R2Sampler {
+    /// Номер точки и случайный сдвиг для следующих `count` измерений.
+    fn offset(&mut self, count: u32) -> (f64, u64) {
+        let key = self.state.next_key(count);
+        (self.state.index as f64 + (key >> 44) as f64, mix(key))
+    }
+}
+
+impl Sampler for R2Sampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.state.start(x, y, index);
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        // Для одного измерения α - обратное золотое сечение.
+        const ALPHA: f64 = 0.618_033_988_749_894_8;
+        let (index, shift) = self.offset(1);
+        let shift = fraction(shift as u32) as f64;
+        ((0.5 + index * ALPHA + shift).fract() as f32).min(ONE_MINUS_EPSILON)
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        const PLASTIC: f64 = 1.324_717_957_244_746;
+        let (index, shift) = self.offset(2);
+        let x = 0.5 + index / PLASTIC + fraction(shift as u32) as f64;
+        let y = 0.5 + index / (PLASTIC * PLASTIC) + fraction((shift >> 32) as u32) as f64;
+        ((x.fract() as f32).min(ONE_MINUS_EPSILON), (y.fract() as f32).min(ONE_MINUS_EPSILON))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{permute, sobol_second, SamplerKind};
+
+    const KINDS: [SamplerKind; 5] =
+        [SamplerKind::Independent, SamplerKind::Stratified, SamplerKind::Halton, SamplerKind::Sobol, SamplerKind::R2];
+
+    #[test]
+    fn check_permutation() {
+        for len in [1, 5, 16, 100] {
+            let mut values: Vec<u32> = (0..len).map(|i| permute(i, len, 0x1234_5678)).collect();
+            values.sort_unstable();
+            assert_eq!((0..len).collect::<Vec<_>>(), values);
+        }
+        assert_eq!(vec![0, 0x8000_0000, 0xC000_0000, 0x4000_0000], (0..4).map(sobol_second).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn check_samples_are_in_unit_square() {
+        for kind in KINDS {
+            let mut sampler = kind.create(3, 16);
+            for index in 0..16 {
+                sampler.start_sample(7, 11, index);
+                for _ in 0..100 {
+                    let x = sampler.next_1d();
+                    let (u, v) = sampler.next_2d();
+                    assert!([x, u, v].iter().all(|t| (0.0..1.0).contains(t)), "{:?}: {} {} {}", kind, x, u, v);
+                }
+            }
+        }
+    }
+
+    /// Среднее по пикселю функции `u·v` на первой паре измерений и на паре после многих измерений.
+    fn mean_error(kind: SamplerKind, samples: u32, skip: usize) -> f32 {
+        let mut error = 0.0;
+        for pixel in 0..64 {
+            let mut sampler = kind.create(5, samples);
+            let mut sum = 0.0;
+            for index in 0..samples {
+                sampler.start_sample(pixel, 0, index);
+                for _ in 0..skip {
+                    sampler.next_2d();
+                }
+                let (u, v) = sampler.next_2d();
+                sum += u * v;
+            }
+            error += (sum / samples as f32 - 0.25).abs();
+        }
+        error / 64.0
+    }
+
+    #[test]
+    fn check_low_discrepancy_converges_faster() {
+        let independent = mean_error(SamplerKind::Independent, 64, 0);
+        assert!(independent < 0.05);
+
+        // При 64 сэмплах соседние измерения Холтона с большими основаниями коррелируют,
+        // поэтому для него проверяются только первые измерения.
+        let checks = [
+            (SamplerKind::Stratified, [0, 20]),
+            (SamplerKind::Halton, [0, 2]),
+            (SamplerKind::Sobol, [0, 20]),
+            (SamplerKind::R2, [0, 20]),
+        ];
+        for (kind, skips) in checks {
+            for skip in skips {
+                let error = mean_error(kind, 64, skip);
+                assert!(error < 0.5 * independent, "{:?} at dimension {}: {} vs {}", kind, 2 * skip, error, independent);
+            }
+        }
+    }
+
+    #[test]
+    fn check_samples_are_deterministic() {
+        for kind in KINDS {
+            let values = || {
+                let mut sampler = kind.create(9, 4);
+                sampler.start_sample(1, 2, 3);
+                (0..10).map(|_| sampler.next_1d()).collect::<Vec<_>>()
+            };
+            assert_eq!(values(), values());
+        }
+    }
+}
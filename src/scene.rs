@@ -0,0 +1,882 @@
+use crate::bodies::{BoxShape, Hittable, MovingSphere, Sphere, XYRect, XZRect, YZRect};
+use crate::camera::Camera;
+use crate::color;
+use crate::geom::{Mat4, Vec3};
+use crate::materials::{Dielectric, DiffuseLight, Isotropic, Lambert, Material, Metal};
+use crate::scenes::{load_model, model};
+use crate::texture::{CheckerTexture, ImageTexture, NoisePattern, NoiseTexture, SolidColor, Texture};
+use crate::toml::{self, Table, TomlError, Value};
+use crate::transform::{RotateY, Transform, Translate};
+use crate::volume::{ConstantMedium, HeterogeneousMedium, NoiseDensity, VoxelGrid};
+use crate::{Background, World};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+use std::{fmt, fs, io};
+
+/// Параметры камеры в том виде, в котором их принимает `Camera::new`.
+///
+/// Соотношение сторон кадра хранится в настройках изображения сцены.
+#[derive(Copy, Clone, Debug)]
+pub struct CameraParams {
+    pub lookfrom: Vec3,
+    pub lookat: Vec3,
+    pub vup: Vec3,
+    pub vfov: f32,
+    pub aperture: f32,
+    pub focus_dist: f32,
+    pub time0: f32,
+    pub time1: f32,
+}
+
+impl Default for CameraParams {
+    fn default() -> Self {
+        CameraParams {
+            lookfrom: [13.0, 2.0, 3.0].into(),
+            lookat: [0.0, 0.0, 0.0].into(),
+            vup: [0.0, 1.0, 0.0].into(),
+            vfov: 20.0,
+            aperture: 0.0,
+            focus_dist: 10.0,
+            time0: 0.0,
+            time1: 1.0,
+        }
+    }
+}
+
+impl CameraParams {
+    pub fn build(&self, aspect_ratio: f32) -> Camera {
+        Camera::new(
+            self.lookfrom,
+            self.lookat,
+            self.vup,
+            self.vfov,
+            aspect_ratio,
+            self.aperture,
+            self.focus_dist,
+            self.time0,
+            self.time1,
+        )
+    }
+}
+
+/// Объекты сцены вместе с камерой, фоном и настройками изображения.
+pub struct Scene {
+    pub world: World,
+    pub camera: CameraParams,
+    pub background: Background,
+    pub image_width: i32,
+    pub aspect_ratio: f32,
+    pub samples_per_pixel: i32,
+    pub depth: i32,
+    /// Число отражений, после которого путь луча может оборваться русской рулеткой.
+    pub roulette_depth: i32,
+    /// Копии источников света, на которые выбираются направления рассеяния.
+    ///
+    /// Сами источники остаются в `world`; пустой список означает выборку только по материалам.
+    pub lights: World,
+}
+
+impl Scene {
+    /// Сцена с настройками по умолчанию: кадр 1200 точек шириной 3:2, 500 лучей на точку и небо в качестве фона.
+    pub fn new(world: World) -> Self {
+        Scene {
+            world,
+            camera: CameraParams::default(),
+            background: Background::Gradient { bottom: color::WHITE, top: color::LIGHT_BLUE },
+            image_width: 1200,
+            aspect_ratio: 3.0 / 2.0,
+            samples_per_pixel: 500,
+            depth: 50,
+            roulette_depth: 5,
+            lights: World(vec![]),
+        }
+    }
+
+    /// Высота кадра, округленная до ближайшего целого.
+    pub fn image_height(&self) -> i32 {
+        (self.image_width as f32 / self.aspect_ratio).round() as i32
+    }
+}
+
+/// Ошибка загрузки файла сцены.
+#[derive(Debug)]
+pub enum SceneError {
+    /// Ошибка ввода-вывода при чтении файла.
+    Io(io::Error),
+    /// Файл не является корректным TOML.
+    Syntax(TomlError),
+    /// Некорректная запись `entry` в строке `line`: неизвестный ключ, неверное значение или ссылка.
+    Invalid { line: usize, entry: String, message: String },
+    /// В сцене нет ни одного объекта.
+    Empty,
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "I/O error: {}", err),
+            SceneError::Syntax(err) => write!(f, "{}", err),
+            SceneError::Invalid { line, entry, message } => write!(f, "line {}, {}: {}", line, entry, message),
+            SceneError::Empty => write!(f, "scene has no objects"),
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io(err) => Some(err),
+            SceneError::Syntax(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+impl From<TomlError> for SceneError {
+    fn from(err: TomlError) -> Self {
+        SceneError::Syntax(err)
+    }
+}
+
+/// Запись файла сцены: таблица и ее описание для сообщений об ошибках.
+struct Entry<'a> {
+    table: &'a Table,
+    name: String,
+}
+
+impl<'a> Entry<'a> {
+    /// Описание записи: `[camera]`, `material 'red'` или `object #3`, если имени нет.
+    fn new(table: &'a Table, kind: &str, index: Option<usize>) -> Self {
+        let name = match (index, table.get("name")) {
+            (None, _) => format!("[{}]", kind),
+            (Some(_), Some(Value::String(name))) => format!("{} '{}'", kind, name),
+            (Some(index), _) => format!("{} #{}", kind, index + 1),
+        };
+
+        Entry { table, name }
+    }
+
+    fn error<T>(&self, key: &str, message: String) -> Result<T, SceneError> {
+        Err(SceneError::Invalid { line: self.table.line_of(key), entry: self.name.clone(), message })
+    }
+
+    /// Проверяет, что в записи нет ключей кроме `allowed`, чтобы опечатки не проходили незамеченными.
+    fn check_keys(&self, allowed: &[&str]) -> Result<(), SceneError> {
+        match self.table.keys().find(|(key, _)| !allowed.contains(key)) {
+            Some((key, _)) => self.error(key, format!("unknown key '{}'", key)),
+            None => Ok(()),
+        }
+    }
+
+    fn has(&self, key: &str) -> bool {
+        self.table.get(key).is_some()
+    }
+
+    fn get<T>(&self, key: &str, expected: &str, convert: impl Fn(&Value) -> Option<T>) -> Result<Option<T>, SceneError> {
+        match self.table.get(key) {
+            None => Ok(None),
+            Some(value) => match convert(value) {
+                Some(result) => Ok(Some(result)),
+                None => self.error(key, format!("'{}' must be {}, found {}", key, expected, value.type_name())),
+            },
+        }
+    }
+
+    fn require<T>(&self, key: &str, value: Option<T>) -> Result<T, SceneError> {
+        match value {
+            Some(value) => Ok(value),
+            None => self.error(key, format!("missing key '{}'", key)),
+        }
+    }
+
+    fn float(&self, key: &str) -> Result<Option<f32>, SceneError> {
+        self.get(key, "a number", |value| value.as_float().map(|f| f as f32))
+    }
+
+    fn float_or(&self, key: &str, default: f32) -> Result<f32, SceneError> {
+        Ok(self.float(key)?.unwrap_or(default))
+    }
+
+    fn required_float(&self, key: &str) -> Result<f32, SceneError> {
+        let value = self.float(key)?;
+        self.require(key, value)
+    }
+
+    /// Число, строго большее нуля.
+    fn positive(&self, key: &str, value: f32) -> Result<f32, SceneError> {
+        if value > 0.0 {
+            Ok(value)
+        } else {
+            self.error(key, format!("'{}' must be positive", key))
+        }
+    }
+
+    fn integer(&self, key: &str) -> Result<Option<i64>, SceneError> {
+        self.get(key, "an integer", |value| match *value {
+            Value::Integer(i) => Some(i),
+            _ => None,
+        })
+    }
+
+    fn boolean(&self, key: &str) -> Result<Option<bool>, SceneError> {
+        self.get(key, "a boolean", |value| match *value {
+            Value::Boolean(b) => Some(b),
+            _ => None,
+        })
+    }
+
+    fn string(&self, key: &str) -> Result<Option<&'a str>, SceneError> {
+        let table = self.table;
+        match table.get(key) {
+            Some(Value::String(s)) => Ok(Some(s.as_str())),
+            Some(value) => self.error(key, format!("'{}' must be a string, found {}", key, value.type_name())),
+            None => Ok(None),
+        }
+    }
+
+    fn required_string(&self, key: &str) -> Result<&'a str, SceneError> {
+        let value = self.string(key)?;
+        self.require(key, value)
+    }
+
+    fn floats(&self, key: &str, count: usize) -> Result<Option<Vec<f32>>, SceneError> {
+        let expected = format!("an array of {} numbers", count);
+        self.get(key, &expected, |value| match value {
+            Value::Array(items) if items.len() == count => items.iter().map(|v| v.as_float().map(|f| f as f32)).collect(),
+            _ => None,
+        })
+    }
+
+    fn vec3(&self, key: &str) -> Result<Option<Vec3>, SceneError> {
+        Ok(self.floats(key, 3)?.map(|v| Vec3::new(v[0], v[1], v[2])))
+    }
+
+    fn required_vec3(&self, key: &str) -> Result<Vec3, SceneError> {
+        let value = self.vec3(key)?;
+        self.require(key, value)
+    }
+
+    /// Цвет с неотрицательными компонентами.
+    fn color(&self, key: &str) -> Result<Option<Vec3>, SceneError> {
+        match self.vec3(key)? {
+            Some(c) if c.0.iter().any(|&x| x < 0.0) => self.error(key, format!("'{}' must not be negative", key)),
+            color => Ok(color),
+        }
+    }
+
+    fn required_color(&self, key: &str) -> Result<Vec3, SceneError> {
+        let value = self.color(key)?;
+        self.require(key, value)
+    }
+
+    /// Путь к файлу относительно каталога сцены.
+    fn path(&self, key: &str, base_dir: &Path) -> Result<String, SceneError> {
+        Ok(base_dir.join(self.required_string(key)?).to_string_lossy().into_owned())
+    }
+
+    /// Текстура из ключа `texture` со ссылкой на именованную текстуру или однотонная из ключа `color`.
+    fn albedo(
+        &self,
+        textures: &HashMap<String, Arc<dyn Texture>>,
+        default: Option<Vec3>,
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        if self.has("texture") && self.has("color") {
+            return self.error("texture", String::from("'color' and 'texture' are mutually exclusive"));
+        }
+
+        if let Some(name) = self.string("texture")? {
+            return match textures.get(name) {
+                Some(texture) => Ok(texture.clone()),
+                None => self.error("texture", format!("unknown texture '{}'", name)),
+            };
+        }
+
+        match self.color("color")?.or(default) {
+            Some(color) => Ok(Arc::new(SolidColor::new(color))),
+            None => self.error("color", String::from("missing key 'color' or 'texture'")),
+        }
+    }
+}
+
+/// Загружает сцену из файла. Пути к моделям и изображениям в нем задаются относительно каталога файла.
+pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+
+    parse_scene(&source, path.parent().unwrap_or_else(|| Path::new("")))
+}
+
+/// Разбирает файл сцены в формате TOML.
+///
+/// Файл состоит из необязательных таблиц `[render]`, `[camera]` и `[background]`
+/// и массивов таблиц `[[texture]]`, `[[material]]` и `[[object]]`. Текстуры и материалы
+/// имеют уникальные имена, по которым на них ссылаются материалы и объекты; порядок записей не важен.
+/// Объекты с ключом `light = true` также попадают в список источников, на которые выбираются направления рассеяния.
+/// Пример - `scenes/cornell.toml`.
+pub fn parse_scene(source: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+    let document = toml::parse(source)?;
+    if let Some((key, line)) = document.root.keys().next() {
+        return Err(SceneError::Invalid {
+            line,
+            entry: String::from("scene"),
+            message: format!("key '{}' outside of a table", key),
+        });
+    }
+
+    let mut scene = Scene::new(World(vec![]));
+    let mut sections: HashMap<&str, Vec<&Table>> = HashMap::new();
+    for section in &document.sections {
+        let array = match section.name.as_str() {
+            "render" | "camera" | "background" => false,
+            "texture" | "material" | "object" => true,
+            name => {
+                return Err(SceneError::Invalid {
+                    line: section.table.line,
+                    entry: format!("[{}]", name),
+                    message: String::from("unknown table"),
+                })
+            }
+        };
+        if section.array != array {
+            let (entry, expected) = if array {
+                (format!("[{}]", section.name), format!("[[{}]]", section.name))
+            } else {
+                (format!("[[{}]]", section.name), format!("[{}]", section.name))
+            };
+            return Err(SceneError::Invalid {
+                line: section.table.line,
+                entry,
+                message: format!("expected {}", expected),
+            });
+        }
+        sections.entry(section.name.as_str()).or_default().push(&section.table);
+    }
+    let tables = |name: &str| sections.get(name).cloned().unwrap_or_default();
+
+    if let Some(table) = tables("render").first() {
+        parse_render(&Entry::new(table, "render", None), &mut scene)?;
+    }
+    if let Some(table) = tables("camera").first() {
+        scene.camera = parse_camera(&Entry::new(table, "camera", None))?;
+    }
+    if let Some(table) = tables("background").first() {
+        scene.background = parse_background(&Entry::new(table, "background", None))?;
+    }
+
+    let mut textures: HashMap<String, Arc<dyn Texture>> = HashMap::new();
+    for (index, table) in tables("texture").into_iter().enumerate() {
+        let entry = Entry::new(table, "texture", Some(index));
+        let name = unique_name(&entry, &textures)?;
+        textures.insert(name, parse_texture(&entry, base_dir)?);
+    }
+
+    let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+    for (index, table) in tables("material").into_iter().enumerate() {
+        let entry = Entry::new(table, "material", Some(index));
+        let name = unique_name(&entry, &materials)?;
+        materials.insert(name, parse_material(&entry, &textures)?);
+    }
+
+    for (index, table) in tables("object").into_iter().enumerate() {
+        let entry = Entry::new(table, "object", Some(index));
+        let object = parse_object(&entry, &textures, &materials, base_dir)?;
+        if entry.boolean("light")?.unwrap_or(false) {
+            // Источник света строится один раз и попадает в оба списка.
+            let light: Arc<dyn Hittable> = Arc::from(object);
+            scene.lights.0.push(Box::new(light.clone()));
+            scene.world.0.push(Box::new(light));
+        } else {
+            scene.world.0.push(object);
+        }
+    }
+
+    if scene.world.0.is_empty() {
+        return Err(SceneError::Empty);
+    }
+    Ok(scene)
+}
+
+fn unique_name<T>(entry: &Entry, defined: &HashMap<String, T>) -> Result<String, SceneError> {
+    let name = entry.required_string("name")?;
+    if defined.contains_key(name) {
+        return entry.error("name", format!("duplicate name '{}'", name));
+    }
+    Ok(name.to_string())
+}
+
+fn parse_render(entry: &Entry, scene: &mut Scene) -> Result<(), SceneError> {
+    entry.check_keys(&["width", "aspect_ratio", "samples_per_pixel", "depth", "roulette_depth"])?;
+
+    let positive_integer = |key: &str, default: i32| -> Result<i32, SceneError> {
+        match entry.integer(key)? {
+            None => Ok(default),
+            Some(value) if value > 0 && value <= i32::MAX as i64 => Ok(value as i32),
+            Some(_) => entry.error(key, format!("'{}' must be a positive integer", key)),
+        }
+    };
+
+    scene.image_width = positive_integer("width", scene.image_width)?;
+    scene.samples_per_pixel = positive_integer("samples_per_pixel", scene.samples_per_pixel)?;
+    scene.depth = positive_integer("depth", scene.depth)?;
+    scene.roulette_depth = positive_integer("roulette_depth", scene.roulette_depth)?;
+    scene.aspect_ratio = entry.positive("aspect_ratio", entry.float_or("aspect_ratio", scene.aspect_ratio)?)?;
+
+    if scene.image_height() < 1 {
+        return entry.error("aspect_ratio", String::from("image height is less than one pixel"));
+    }
+    Ok(())
+}
+
+fn parse_camera(entry: &Entry) -> Result<CameraParams, SceneError> {
+    entry.check_keys(&["lookfrom", "lookat", "vup", "vfov", "aperture", "focus_distance", "time0", "time1"])?;
+
+    let default = CameraParams::default();
+    let camera = CameraParams {
+        lookfrom: entry.vec3("lookfrom")?.unwrap_or(default.lookfrom),
+        lookat: entry.vec3("lookat")?.unwrap_or(default.lookat),
+        vup: entry.vec3("vup")?.unwrap_or(default.vup),
+        vfov: entry.float_or("vfov", default.vfov)?,
+        aperture: entry.float_or("aperture", default.aperture)?,
+        focus_dist: entry.positive("focus_distance", entry.float_or("focus_distance", default.focus_dist)?)?,
+        time0: entry.float_or("time0", default.time0)?,
+        time1: entry.float_or("time1", default.time1)?,
+    };
+
+    if !(camera.vfov > 0.0 && camera.vfov < 180.0) {
+        return entry.error("vfov", String::from("'vfov' must be between 0 and 180 degrees"));
+    }
+    if camera.aperture < 0.0 {
+        return entry.error("aperture", String::from("'aperture' must not be negative"));
+    }
+    if camera.time1 < camera.time0 {
+        return entry.error("time1", String::from("'time1' must not be less than 'time0'"));
+    }
+    if (camera.lookfrom - camera.lookat).length_squared() == 0.0 {
+        return entry.error("lookat", String::from("'lookfrom' and 'lookat' must differ"));
+    }
+    Ok(camera)
+}
+
+fn parse_background(entry: &Entry) -> Result<Background, SceneError> {
+    entry.check_keys(&["color", "bottom", "top"])?;
+
+    match (entry.color("color")?, entry.color("bottom")?, entry.color("top")?) {
+        (Some(color), None, None) => Ok(Background::Solid(color)),
+        (None, Some(bottom), Some(top)) => Ok(Background::Gradient { bottom, top }),
+        _ => entry.error("color", String::from("expected either 'color' or both 'bottom' and 'top'")),
+    }
+}
+
+fn parse_texture(entry: &Entry, base_dir: &Path) -> Result<Arc<dyn Texture>, SceneError> {
+    let kind = entry.required_string("type")?;
+    let texture: Arc<dyn Texture> = match kind {
+        "solid" => {
+            entry.check_keys(&["name", "type", "color"])?;
+            Arc::new(SolidColor::new(entry.required_color("color")?))
+        }
+        "checker" => {
+            entry.check_keys(&["name", "type", "even", "odd"])?;
+            Arc::new(CheckerTexture::with_colors(entry.required_color("even")?, entry.required_color("odd")?))
+        }
+        "noise" => {
+            entry.check_keys(&["name", "type", "seed", "scale", "pattern", "octaves"])?;
+            let pattern = match entry.string("pattern")?.unwrap_or("marble") {
+                "smooth" => NoisePattern::Smooth,
+                "turbulence" => NoisePattern::Turbulence,
+                "marble" => NoisePattern::Marble,
+                other => return entry.error("pattern", format!("unknown noise pattern '{}'", other)),
+            };
+            let (seed, octaves) = noise_parameters(entry, "seed", "octaves")?;
+            Arc::new(NoiseTexture::with_octaves(seed, entry.float_or("scale", 1.0)?, pattern, octaves))
+        }
+        "image" => {
+            entry.check_keys(&["name", "type", "path"])?;
+            let path = entry.path("path", base_dir)?;
+            match ImageTexture::open(&path) {
+                Ok(texture) => Arc::new(texture),
+                Err(err) => return entry.error("path", format!("can not load '{}': {}", path, err)),
+            }
+        }
+        other => return entry.error("type", format!("unknown texture type '{}'", other)),
+    };
+
+    Ok(texture)
+}
+
+/// Зерно и число октав шума Перлина; по умолчанию 0 и 7.
+fn noise_parameters(entry: &Entry, seed_key: &str, octaves_key: &str) -> Result<(u64, usize), SceneError> {
+    let seed = match entry.integer(seed_key)? {
+        Some(seed) if seed < 0 => return entry.error(seed_key, format!("'{}' must not be negative", seed_key)),
+        seed => seed.unwrap_or(0) as u64,
+    };
+    let octaves = match entry.integer(octaves_key)? {
+        Some(octaves) if !(1..=16).contains(&octaves) => {
+            return entry.error(octaves_key, format!("'{}' must be between 1 and 16", octaves_key))
+        }
+        octaves => octaves.unwrap_or(7) as usize,
+    };
+
+    Ok((seed, octaves))
+}
+
+fn parse_material(entry: &Entry, textures: &HashMap<String, Arc<dyn Texture>>) -> Result<Arc<dyn Material>, SceneError> {
+    let kind = entry.required_string("type")?;
+    let material: Arc<dyn Material> = match kind {
+        "lambert" => {
+            entry.check_keys(&["name", "type", "color", "texture"])?;
+            Arc::new(Lambert::new(entry.albedo(textures, None)?))
+        }
+        "metal" => {
+            entry.check_keys(&["name", "type", "color", "fuzz"])?;
+            let fuzz = entry.float_or("fuzz", 0.0)?;
+            if !(0.0..=1.0).contains(&fuzz) {
+                return entry.error("fuzz", String::from("'fuzz' must be between 0 and 1"));
+            }
+            Arc::new(Metal::with_albedo_fuzz(entry.required_color("color")?, fuzz))
+        }
+        "dielectric" => {
+            entry.check_keys(&["name", "type", "ior"])?;
+            Arc::new(Dielectric { ir: entry.positive("ior", entry.required_float("ior")?)? })
+        }
+        "diffuse_light" => {
+            entry.check_keys(&["name", "type", "color", "texture"])?;
+            Arc::new(DiffuseLight { emit: entry.albedo(textures, None)? })
+        }
+        "isotropic" => {
+            entry.check_keys(&["name", "type", "color", "texture"])?;
+            Arc::new(Isotropic::new(entry.albedo(textures, None)?))
+        }
+        other => return entry.error("type", format!("unknown material type '{}'", other)),
+    };
+
+    Ok(material)
+}
+
+/// Ключи преобразований, допустимые в любом объекте.
+const TRANSFORM_KEYS: [&str; 4] = ["scale", "rotate", "rotate_y", "translate"];
+
+/// Ключи сред по их типу в ключе `medium`.
+fn medium_keys(medium: &str) -> &'static [&'static str] {
+    match medium {
+        "constant" => &["density", "color", "texture"],
+        "noise" => &["density", "color", "texture", "noise_seed", "noise_scale", "noise_octaves"],
+        "voxels" => &["color", "texture", "voxel_path", "voxel_size"],
+        _ => &[],
+    }
+}
+
+fn parse_object(
+    entry: &Entry,
+    textures: &HashMap<String, Arc<dyn Texture>>,
+    materials: &HashMap<String, Arc<dyn Material>>,
+    base_dir: &Path,
+) -> Result<Box<dyn Hittable>, SceneError> {
+    let kind = entry.required_string("type")?;
+    let medium = entry.string("medium")?;
+
+    let shape_keys: &[&str] = match kind {
+        "sphere" => &["center", "radius"],
+        "moving_sphere" => &["center0", "center1", "time0", "time1", "radius"],
+        "xy_rect" => &["x0", "x1", "y0", "y1", "k"],
+        "xz_rect" => &["x0", "x1", "z0", "z1", "k"],
+        "yz_rect" => &["y0", "y1", "z0", "z1", "k"],
+        "box" => &["min", "max"],
+        "mesh" => &["path"],
+        other => return entry.error("type", format!("unknown object type '{}'", other)),
+    };
+
+    // Граница среды служит только для поиска пересечений, поэтому материал ей не нужен.
+    let mut allowed = vec!["name", "type", "light"];
+    allowed.extend_from_slice(shape_keys);
+    allowed.extend_from_slice(&TRANSFORM_KEYS);
+    match medium {
+        Some(medium) => {
+            if kind == "mesh" {
+                return entry.error("medium", String::from("a mesh can not bound a medium"));
+            }
+            allowed.push("medium");
+            allowed.extend_from_slice(medium_keys(medium));
+        }
+        None => allowed.push("material"),
+    }
+    entry.check_keys(&allowed)?;
+
+    // Направления на источник света умеют выбирать только сферы и прямоугольники, а из преобразований
+    // телесный угол сохраняют только перенос и поворот вокруг `Y`.
+    if entry.boolean("light")?.unwrap_or(false) {
+        if medium.is_some() || !["sphere", "xy_rect", "xz_rect", "yz_rect"].contains(&kind) {
+            return entry.error("light", String::from("only spheres and rectangles can be sampled as lights"));
+        }
+        if let Some(key) = ["scale", "rotate"].iter().find(|key| entry.has(key)) {
+            return entry.error(key, format!("a light can not use '{}', only 'rotate_y' and 'translate'", key));
+        }
+    }
+
+    let material = match (medium, entry.string("material")?) {
+        (Some(_), _) => Arc::new(Lambert::with_color(color::WHITE)) as Arc<dyn Material>,
+        (None, Some(name)) => match materials.get(name) {
+            Some(material) => material.clone(),
+            None => return entry.error("material", format!("unknown material '{}'", name)),
+        },
+        (None, None) if kind == "mesh" => {
+            let path = entry.path("path", base_dir)?;
+            return transformed(entry, Box::new(load_mesh(entry, &path, model(&path))?));
+        }
+        (None, None) => return entry.error("material", String::from("missing key 'material'")),
+    };
+
+    let ordered = |a: &str, b: &str| -> Result<(f32, f32), SceneError> {
+        let (v0, v1) = (entry.required_float(a)?, entry.required_float(b)?);
+        if v0 < v1 {
+            Ok((v0, v1))
+        } else {
+            entry.error(b, format!("'{}' must be greater than '{}'", b, a))
+        }
+    };
+    let radius = || match entry.required_float("radius")? {
+        0.0 => entry.error("radius", String::from("'radius' must not be zero")),
+        r => Ok(r),
+    };
+
+    let shape: Box<dyn Hittable> = match kind {
+        "sphere" => Box::new(Sphere { center: entry.required_vec3("center")?, radius: radius()?, material }),
+        "moving_sphere" => {
+            let (time0, time1) = ordered("time0", "time1")?;
+            Box::new(MovingSphere {
+                center0: entry.required_vec3("center0")?,
+                center1: entry.required_vec3("center1")?,
+                time0,
+                time1,
+                radius: radius()?,
+                material,
+            })
+        }
+        "xy_rect" => {
+            let ((x0, x1), (y0, y1)) = (ordered("x0", "x1")?, ordered("y0", "y1")?);
+            Box::new(XYRect { x0, x1, y0, y1, k: entry.required_float("k")?, material })
+        }
+        "xz_rect" => {
+            let ((x0, x1), (z0, z1)) = (ordered("x0", "x1")?, ordered("z0", "z1")?);
+            Box::new(XZRect { x0, x1, z0, z1, k: entry.required_float("k")?, material })
+        }
+        "yz_rect" => {
+            let ((y0, y1), (z0, z1)) = (ordered("y0", "y1")?, ordered("z0", "z1")?);
+            Box::new(YZRect { y0, y1, z0, z1, k: entry.required_float("k")?, material })
+        }
+        "box" => {
+            let (min, max) = (entry.required_vec3("min")?, entry.required_vec3("max")?);
+            if (0..3).any(|i| min[i] >= max[i]) {
+                return entry.error("max", String::from("'max' must be greater than 'min' on every axis"));
+            }
+            Box::new(BoxShape::new(min, max, material))
+        }
+        _ => {
+            let path = entry.path("path", base_dir)?;
+            Box::new(load_mesh(entry, &path, load_model(&path, material))?)
+        }
+    };
+
+    let shape = transformed(entry, shape)?;
+    match medium {
+        Some(medium) => parse_medium(entry, medium, shape, textures, base_dir),
+        None => Ok(shape),
+    }
+}
+
+fn load_mesh(entry: &Entry, path: &str, world: Result<World, Box<dyn Error>>) -> Result<World, SceneError> {
+    world.or_else(|err| entry.error("path", format!("can not load '{}': {}", path, err)))
+}
+
+/// Применяет к объекту масштаб, поворот вокруг произвольной оси, поворот вокруг оси `Y` и перенос - в этом порядке.
+///
+/// Поворот вокруг `Y` и перенос без остальных преобразований обходятся без матриц.
+fn transformed(entry: &Entry, object: Box<dyn Hittable>) -> Result<Box<dyn Hittable>, SceneError> {
+    let scale = entry.vec3("scale")?;
+    let rotate = entry.floats("rotate", 4)?;
+    let rotate_y = entry.float("rotate_y")?;
+    let translate = entry.vec3("translate")?;
+
+    if let Some(scale) = scale {
+        if scale.0.contains(&0.0) {
+            return entry.error("scale", String::from("'scale' must not be zero on any axis"));
+        }
+    }
+    if let Some(rotate) = &rotate {
+        if rotate[..3].iter().all(|&x| x == 0.0) {
+            return entry.error("rotate", String::from("'rotate' must be [x, y, z, degrees] with a non-zero axis"));
+        }
+    }
+
+    if scale.is_some() || rotate.is_some() {
+        let mut matrix = Mat4::scaling(scale.unwrap_or_else(|| Vec3::new(1.0, 1.0, 1.0)));
+        if let Some(rotate) = rotate {
+            matrix = Mat4::rotation(Vec3::new(rotate[0], rotate[1], rotate[2]), rotate[3]) * matrix;
+        }
+        if let Some(degrees) = rotate_y {
+            matrix = Mat4::rotation(Vec3::new(0.0, 1.0, 0.0), degrees) * matrix;
+        }
+        if let Some(offset) = translate {
+            matrix = Mat4::translation(offset) * matrix;
+        }
+        return match Transform::new(object, matrix) {
+            Some(transform) => Ok(Box::new(transform)),
+            None => entry.error("scale", String::from("'scale' and 'rotate' give a degenerate transformation")),
+        };
+    }
+
+    let mut object = object;
+    if let Some(degrees) = rotate_y {
+        object = Box::new(RotateY::new(object, degrees));
+    }
+    if let Some(offset) = translate {
+        object = Box::new(Translate::new(object, offset));
+    }
+    Ok(object)
+}
+
+fn parse_medium(
+    entry: &Entry,
+    medium: &str,
+    boundary: Box<dyn Hittable>,
+    textures: &HashMap<String, Arc<dyn Texture>>,
+    base_dir: &Path,
+) -> Result<Box<dyn Hittable>, SceneError> {
+    let albedo = entry.albedo(textures, Some(color::WHITE))?;
+    let density = || entry.positive("density", entry.required_float("density")?);
+
+    let object: Box<dyn Hittable> = match medium {
+        "constant" => Box::new(ConstantMedium::new(boundary, density()?, albedo)),
+        "noise" => {
+            let (seed, octaves) = noise_parameters(entry, "noise_seed", "noise_octaves")?;
+            let scale = entry.float_or("noise_scale", 1.0)?;
+            let field = Arc::new(NoiseDensity::new(seed, scale, density()?, octaves));
+            Box::new(HeterogeneousMedium::new(boundary, field, albedo))
+        }
+        "voxels" => {
+            let size = match entry.floats("voxel_size", 3)? {
+                Some(size) if size.iter().all(|&n| n >= 1.0 && n.fract() == 0.0) => {
+                    [size[0] as usize, size[1] as usize, size[2] as usize]
+                }
+                Some(_) => return entry.error("voxel_size", String::from("'voxel_size' must be three positive integers")),
+                None => return entry.error("voxel_size", String::from("missing key 'voxel_size'")),
+            };
+            let bounds = match boundary.bounding_box(0.0, 1.0) {
+                Some(bounds) => bounds,
+                None => return entry.error("type", String::from("medium boundary has no bounding box")),
+            };
+            let path = entry.path("voxel_path", base_dir)?;
+            let grid = VoxelGrid::load(&path, size, bounds)
+                .or_else(|err| entry.error("voxel_path", format!("can not load '{}': {}", path, err)))?;
+            Box::new(HeterogeneousMedium::new(boundary, Arc::new(grid), albedo))
+        }
+        other => return entry.error("medium", format!("unknown medium '{}'", other)),
+    };
+
+    Ok(object)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_scene, SceneError};
+    use crate::Background;
+    use std::path::Path;
+
+    fn error(source: &str) -> String {
+        match parse_scene(source, Path::new("")) {
+            Err(err) => err.to_string(),
+            Ok(_) => panic!("scene should not load"),
+        }
+    }
+
+    #[test]
+    fn check_scene_file() {
+        let source = r#"
+            [render]
+            width = 400
+            aspect_ratio = 2.0
+            samples_per_pixel = 16
+            roulette_depth = 3
+
+            [camera]
+            lookfrom = [0, 1, 5]
+            vfov = 40
+
+            [background]
+            color = [0, 0, 0]
+
+            [[object]]
+            type = "sphere"
+            center = [0, 0, 0]
+            radius = 1
+            material = "glass"
+            translate = [0, 1, 0]
+            light = true
+
+            [[material]]
+            name = "glass"
+            type = "dielectric"
+            ior = 1.5
+
+            [[object]]
+            type = "box"
+            min = [0, 0, 0]
+            max = [1, 1, 1]
+            medium = "constant"
+            density = 0.5
+            rotate = [1, 0, 0, 30]
+        "#;
+        let scene = parse_scene(source, Path::new("")).unwrap();
+
+        assert_eq!((400, 200, 16, 50), (scene.image_width, scene.image_height(), scene.samples_per_pixel, scene.depth));
+        assert_eq!(3, scene.roulette_depth);
+        assert_eq!(40.0, scene.camera.vfov);
+        assert_eq!([0.0, 0.0, 0.0], scene.camera.lookat.0);
+        assert!(matches!(scene.background, Background::Solid(_)));
+        assert_eq!(2, scene.world.0.len());
+        assert_eq!(1, scene.lights.0.len());
+    }
+
+    #[test]
+    fn check_validation_errors() {
+        assert_eq!(
+            "line 3, object #1: unknown material 'gold'",
+            error("[[object]]\ntype = \"sphere\"\nmaterial = \"gold\"\ncenter = [0, 0, 0]\nradius = 1")
+        );
+        assert_eq!(
+            "line 4, material 'm': unknown key 'fuz'",
+            error("[[material]]\nname = \"m\"\ntype = \"metal\"\nfuz = 0.5\ncolor = [1, 1, 1]")
+        );
+        assert_eq!(
+            "line 2, [camera]: 'vfov' must be a number, found string",
+            error("[camera]\nvfov = \"wide\"")
+        );
+        assert_eq!(
+            "line 1, object #1: missing key 'radius'",
+            error("[[object]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nmaterial = \"m\"\n[[material]]\nname = \"m\"\ntype = \"lambert\"\ncolor = [1, 1, 1]")
+        );
+        assert_eq!(
+            "line 4, object #1: 'x1' must be greater than 'x0'",
+            error("[[object]]\ntype = \"xy_rect\"\nx0 = 1\nx1 = 0\ny0 = 0\ny1 = 1\nk = 0\nmedium = \"constant\"\ndensity = 1")
+        );
+        assert_eq!(
+            "line 4, object #1: only spheres and rectangles can be sampled as lights",
+            error("[[object]]\ntype = \"box\"\nmin = [0, 0, 0]\nlight = true\nmax = [1, 1, 1]\nmedium = \"constant\"\ndensity = 1")
+        );
+        assert_eq!(
+            "line 5, object #1: 'scale' and 'rotate' give a degenerate transformation",
+            error("[[object]]\ntype = \"box\"\nmin = [0, 0, 0]\nmax = [1, 1, 1]\nscale = [1e-20, 1, 1]\nmaterial = \"m\"\n[[material]]\nname = \"m\"\ntype = \"lambert\"\ncolor = [1, 1, 1]")
+        );
+        assert_eq!("line 1, [[camera]]: expected [camera]", error("[[camera]]"));
+        assert_eq!("line 3: duplicate key 'a'", error("[render]\na = 1\na = 2"));
+        assert_eq!(SceneError::Empty.to_string(), error("[render]\nwidth = 10"));
+    }
+}
@@ -0,0 +1,414 @@
+use crate::bvh::SahBvh;
+use crate::bodies::{BoxShape, Hittable, MovingSphere, Sphere, XYRect, XZRect, YZRect};
+use crate::geom::{unit_vector, Vec3};
+use crate::materials::{Dielectric, DiffuseLight, Lambert, Material, Metal};
+use crate::mesh::{MeshData, TriangleMesh};
+use crate::obj::load_obj;
+use crate::perlin::Perlin;
+use crate::ply::load_ply;
+use crate::ppm::PpmError;
+use crate::scene::Scene;
+use crate::stl::load_stl;
+use crate::transform::{RotateY, Translate};
+use crate::texture::{CheckerTexture, SolidColor, ImageTexture, NoisePattern, NoiseTexture};
+use crate::rng::Pcg32;
+use crate::utils::deg_to_rad;
+use crate::volume::{ConstantMedium, HeterogeneousMedium, NoiseDensity};
+use crate::{color, Background, World};
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Случайная сцена с обложки первой книги.
+pub fn random_scene(rng: &mut Pcg32) -> World {
+    let mut scene: Vec<Box<dyn Hittable>> = vec![];
+
+    // Шар - земля.
+    scene.push(Box::new(Sphere{center: [0.0, -1000.0, 0.0].into(), radius: 1000.0,
+        material: Arc::new(Lambert::new(Arc::new(CheckerTexture::with_colors([0.2, 0.3, 0.1].into(), [0.9, 0.9, 0.9].into()))))
+    }));
+
+    // Случайно рассыпанные шарики.
+    for a in -11..11 {
+        for b in -11..11 {
+            let material_rate = rng.next_f32();
+            let x = rng.next_f32();
+            let z = rng.next_f32();
+            let center: Vec3 = [a as f32 + 0.9 * x, 0.2, b as f32 + 0.9 * z].into();
+
+            if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                let x = rng.next_f32();
+                let y = rng.next_f32();
+                let z = rng.next_f32();
+                let fuzz = rng.next_f32();
+
+                if material_rate < 0.8 {
+                    // Рассеивающие шарики подпрыгивают, пока открыт затвор камеры.
+                    let material = Arc::new(Lambert::with_color([x * x, y * y, z * z].into()));
+                    let center1 = center + Vec3::new(0.0, rng.range(0.0, 0.5), 0.0);
+                    scene.push(Box::new(MovingSphere{center0: center, center1, time0: 0.0, time1: 1.0, radius: 0.2, material}));
+                    continue;
+                }
+
+                let material: Arc<dyn Material> = if material_rate < 0.95 {
+                    Arc::new(Metal::with_albedo_fuzz([0.5 * (1.0 + x), 0.5 * (1.0 + y), 0.5 * (1.0 + z)].into(), fuzz))
+                } else {
+                    Arc::new(Dielectric{ir: 1.5})
+                };
+
+                scene.push(Box::new(Sphere{center, radius: 0.2, material}));
+            }
+        }
+    }
+
+    // Три больших шарика в центре.
+    scene.push(Box::new(Sphere{center: [0.0, 1.0, 0.0].into(), radius: 1.0,
+        material: Arc::new(Dielectric{ir: 1.5})
+    }));
+    scene.push(Box::new(Sphere{center: [-4.0, 1.0, 0.0].into(), radius: 1.0,
+        material: Arc::new(Lambert::with_color([0.4, 0.2, 0.1].into()))
+    }));
+    scene.push(Box::new(Sphere{center: [4.0, 1.0, 0.0].into(), radius: 1.0,
+        material: Arc::new(Metal::with_albedo_fuzz([0.7, 0.6, 0.5].into(), 0.0))
+    }));
+
+    World(scene)
+}
+
+/// Две большие сферы с шахматной текстурой.
+pub fn two_spheres() -> World {
+    let checker = Arc::new(CheckerTexture::with_colors([0.2, 0.3, 0.1].into(), [0.9, 0.9, 0.9].into()));
+    let material = Arc::new(Lambert::new(checker));
+
+    World(vec![
+        Box::new(Sphere{center: [0.0, -10.0, 0.0].into(), radius: 10.0, material: material.clone()}),
+        Box::new(Sphere{center: [0.0, 10.0, 0.0].into(), radius: 10.0, material}),
+    ])
+}
+
+/// Две сферы с мраморной текстурой на основе шума Перлина.
+pub fn two_perlin_spheres() -> World {
+    let pertext = Arc::new(NoiseTexture::new(0, 4.0, NoisePattern::Marble));
+    let material = Arc::new(Lambert::new(pertext));
+
+    World(vec![
+        Box::new(Sphere{center: [0.0, -1000.0, 0.0].into(), radius: 1000.0, material: material.clone()}),
+        Box::new(Sphere{center: [0.0, 2.0, 0.0].into(), radius: 2.0, material}),
+    ])
+}
+
+/// Земной шар с текстурой из файла `earthmap.ppm` в текущем каталоге.
+pub fn earth() -> Result<World, PpmError> {
+    let earth_texture = Arc::new(ImageTexture::open("earthmap.ppm")?);
+    let earth_surface = Arc::new(Lambert::new(earth_texture));
+
+    Ok(World(vec![
+        Box::new(Sphere{center: [0.0, 0.0, 0.0].into(), radius: 2.0, material: earth_surface}),
+    ]))
+}
+
+/// Мраморные сферы, освещенные светящейся сферой. Рассчитана на черный фон.
+pub fn simple_light() -> World {
+    let pertext = Arc::new(NoiseTexture::new(0, 4.0, NoisePattern::Marble));
+    let material = Arc::new(Lambert::new(pertext));
+    let light = Arc::new(DiffuseLight::with_color([4.0, 4.0, 4.0].into()));
+
+    World(vec![
+        Box::new(Sphere{center: [0.0, -1000.0, 0.0].into(), radius: 1000.0, material: material.clone()}),
+        Box::new(Sphere{center: [0.0, 2.0, 0.0].into(), radius: 2.0, material}),
+        Box::new(Sphere{center: [0.0, 7.0, 0.0].into(), radius: 2.0, material: light.clone()}),
+        Box::new(XYRect{x0: 3.0, x1: 5.0, y0: 1.0, y1: 3.0, k: -2.0, material: light}),
+    ])
+}
+
+/// Границы `(x0, x1, z0, z1)` светильников под потолком коробки Корнелла.
+const CORNELL_LIGHT: (f32, f32, f32, f32) = (213.0, 343.0, 227.0, 332.0);
+const WIDE_LIGHT: (f32, f32, f32, f32) = (113.0, 443.0, 127.0, 432.0);
+const FINAL_LIGHT: (f32, f32, f32, f32) = (123.0, 423.0, 147.0, 412.0);
+
+/// Прямоугольный светильник под потолком коробки Корнелла.
+fn ceiling_light((x0, x1, z0, z1): (f32, f32, f32, f32), material: Arc<dyn Material>) -> Box<dyn Hittable> {
+    Box::new(XZRect{x0, x1, z0, z1, k: 554.0, material})
+}
+
+/// Корнеллская комната с двумя параллелепипедами и квадратным светильником на потолке.
+pub fn cornell_box() -> World {
+    let red: Arc<dyn Material> = Arc::new(Lambert::with_color([0.65, 0.05, 0.05].into()));
+    let white: Arc<dyn Material> = Arc::new(Lambert::with_color([0.73, 0.73, 0.73].into()));
+    let green: Arc<dyn Material> = Arc::new(Lambert::with_color([0.12, 0.45, 0.15].into()));
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::with_color([15.0, 15.0, 15.0].into()));
+
+    World(vec![
+        Box::new(YZRect{y0: 0.0, y1: 555.0, z0: 0.0, z1: 555.0, k: 555.0, material: green}),
+        Box::new(YZRect{y0: 0.0, y1: 555.0, z0: 0.0, z1: 555.0, k: 0.0, material: red}),
+        ceiling_light(CORNELL_LIGHT, light),
+        Box::new(XZRect{x0: 0.0, x1: 555.0, z0: 0.0, z1: 555.0, k: 0.0, material: white.clone()}),
+        Box::new(XZRect{x0: 0.0, x1: 555.0, z0: 0.0, z1: 555.0, k: 555.0, material: white.clone()}),
+        Box::new(XYRect{x0: 0.0, x1: 555.0, y0: 0.0, y1: 555.0, k: 555.0, material: white.clone()}),
+        Box::new(Translate::new(
+            Box::new(RotateY::new(Box::new(BoxShape::new([0.0, 0.0, 0.0].into(), [165.0, 330.0, 165.0].into(), white.clone())), 15.0)),
+            [265.0, 0.0, 295.0].into(),
+        )),
+        Box::new(Translate::new(
+            Box::new(RotateY::new(Box::new(BoxShape::new([0.0, 0.0, 0.0].into(), [165.0, 165.0, 165.0].into(), white)), -18.0)),
+            [130.0, 0.0, 65.0].into(),
+        )),
+    ])
+}
+
+/// Корнеллская комната, в которой параллелепипеды заменены облаками дыма и тумана.
+pub fn cornell_smoke() -> World {
+    let red: Arc<dyn Material> = Arc::new(Lambert::with_color([0.65, 0.05, 0.05].into()));
+    let white: Arc<dyn Material> = Arc::new(Lambert::with_color([0.73, 0.73, 0.73].into()));
+    let green: Arc<dyn Material> = Arc::new(Lambert::with_color([0.12, 0.45, 0.15].into()));
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::with_color([7.0, 7.0, 7.0].into()));
+
+    let box1 = Translate::new(
+        Box::new(RotateY::new(Box::new(BoxShape::new([0.0, 0.0, 0.0].into(), [165.0, 330.0, 165.0].into(), white.clone())), 15.0)),
+        [265.0, 0.0, 295.0].into(),
+    );
+    let box2 = Translate::new(
+        Box::new(RotateY::new(Box::new(BoxShape::new([0.0, 0.0, 0.0].into(), [165.0, 165.0, 165.0].into(), white.clone())), -18.0)),
+        [130.0, 0.0, 65.0].into(),
+    );
+
+    World(vec![
+        Box::new(YZRect{y0: 0.0, y1: 555.0, z0: 0.0, z1: 555.0, k: 555.0, material: green}),
+        Box::new(YZRect{y0: 0.0, y1: 555.0, z0: 0.0, z1: 555.0, k: 0.0, material: red}),
+        ceiling_light(WIDE_LIGHT, light),
+        Box::new(XZRect{x0: 0.0, x1: 555.0, z0: 0.0, z1: 555.0, k: 555.0, material: white.clone()}),
+        Box::new(XZRect{x0: 0.0, x1: 555.0, z0: 0.0, z1: 555.0, k: 0.0, material: white.clone()}),
+        Box::new(XYRect{x0: 0.0, x1: 555.0, y0: 0.0, y1: 555.0, k: 555.0, material: white}),
+        Box::new(ConstantMedium::with_color(Box::new(box1), 0.01, [0.0, 0.0, 0.0].into())),
+        Box::new(ConstantMedium::with_color(Box::new(box2), 0.01, [1.0, 1.0, 1.0].into())),
+    ])
+}
+
+/// Итоговая сцена второй книги: все виды объектов, материалов и текстур вместе.
+///
+/// Текстура Земли загружается из файла `earthmap.ppm` в текущем каталоге.
+pub fn final_scene(rng: &mut Pcg32) -> Result<World, PpmError> {
+    let mut objects: Vec<Box<dyn Hittable>> = vec![];
+
+    // Пол из параллелепипедов случайной высоты.
+    let ground: Arc<dyn Material> = Arc::new(Lambert::with_color([0.48, 0.83, 0.53].into()));
+    let boxes_per_side = 20;
+    let mut boxes1: Vec<Box<dyn Hittable>> = vec![];
+    for i in 0..boxes_per_side {
+        for j in 0..boxes_per_side {
+            let w = 100.0;
+            let x0 = -1000.0 + i as f32 * w;
+            let z0 = -1000.0 + j as f32 * w;
+            let y1 = rng.range(1.0, 101.0);
+            boxes1.push(Box::new(BoxShape::new([x0, 0.0, z0].into(), [x0 + w, y1, z0 + w].into(), ground.clone())));
+        }
+    }
+    objects.push(Box::new(SahBvh::new(World(boxes1), 0.0, 1.0)));
+
+    let light = Arc::new(DiffuseLight::with_color([7.0, 7.0, 7.0].into()));
+    objects.push(ceiling_light(FINAL_LIGHT, light));
+
+    let center0: Vec3 = [400.0, 400.0, 200.0].into();
+    let center1 = center0 + Vec3::new(30.0, 0.0, 0.0);
+    let moving_sphere_material = Arc::new(Lambert::with_color([0.7, 0.3, 0.1].into()));
+    objects.push(Box::new(MovingSphere{center0, center1, time0: 0.0, time1: 1.0, radius: 50.0, material: moving_sphere_material}));
+
+    objects.push(Box::new(Sphere{center: [260.0, 150.0, 45.0].into(), radius: 50.0, material: Arc::new(Dielectric{ir: 1.5})}));
+    objects.push(Box::new(Sphere{center: [0.0, 150.0, 145.0].into(), radius: 50.0,
+        material: Arc::new(Metal::with_albedo_fuzz([0.8, 0.8, 0.9].into(), 1.0))
+    }));
+
+    // Стеклянный шар, заполненный синим туманом, и легкая дымка на всю сцену.
+    let glass = || Arc::new(Dielectric{ir: 1.5});
+    objects.push(Box::new(Sphere{center: [360.0, 150.0, 145.0].into(), radius: 70.0, material: glass()}));
+    let boundary = Sphere{center: [360.0, 150.0, 145.0].into(), radius: 70.0, material: glass()};
+    objects.push(Box::new(ConstantMedium::with_color(Box::new(boundary), 0.2, [0.2, 0.4, 0.9].into())));
+    let boundary = Sphere{center: [0.0, 0.0, 0.0].into(), radius: 5000.0, material: glass()};
+    objects.push(Box::new(ConstantMedium::with_color(Box::new(boundary), 0.0001, [1.0, 1.0, 1.0].into())));
+
+    let emat = Arc::new(Lambert::new(Arc::new(ImageTexture::open("earthmap.ppm")?)));
+    objects.push(Box::new(Sphere{center: [400.0, 200.0, 400.0].into(), radius: 100.0, material: emat}));
+    let pertext = Arc::new(NoiseTexture::new(0, 0.1, NoisePattern::Marble));
+    objects.push(Box::new(Sphere{center: [220.0, 280.0, 300.0].into(), radius: 80.0, material: Arc::new(Lambert::new(pertext))}));
+
+    // Куб из маленьких белых шариков.
+    let white: Arc<dyn Material> = Arc::new(Lambert::with_color([0.73, 0.73, 0.73].into()));
+    let mut boxes2: Vec<Box<dyn Hittable>> = vec![];
+    for _ in 0..1000 {
+        let center = Vec3::new(rng.range(0.0, 165.0), rng.range(0.0, 165.0), rng.range(0.0, 165.0));
+        boxes2.push(Box::new(Sphere{center, radius: 10.0, material: white.clone()}));
+    }
+    objects.push(Box::new(Translate::new(
+        Box::new(RotateY::new(Box::new(SahBvh::new(World(boxes2), 0.0, 1.0)), 15.0)),
+        [-100.0, 270.0, 395.0].into(),
+    )));
+
+    Ok(World(objects))
+}
+
+/// Корнеллская комната с облаком, плотность которого задана шумом Перлина.
+pub fn cornell_cloud() -> World {
+    let red: Arc<dyn Material> = Arc::new(Lambert::with_color([0.65, 0.05, 0.05].into()));
+    let white: Arc<dyn Material> = Arc::new(Lambert::with_color([0.73, 0.73, 0.73].into()));
+    let green: Arc<dyn Material> = Arc::new(Lambert::with_color([0.12, 0.45, 0.15].into()));
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::with_color([7.0, 7.0, 7.0].into()));
+
+    let boundary = Sphere{center: [278.0, 250.0, 278.0].into(), radius: 180.0, material: white.clone()};
+    let field = Arc::new(NoiseDensity::new(0, 0.01, 0.05, 5));
+    let albedo = Arc::new(SolidColor::new([0.9, 0.9, 0.9].into()));
+
+    World(vec![
+        Box::new(YZRect{y0: 0.0, y1: 555.0, z0: 0.0, z1: 555.0, k: 555.0, material: green}),
+        Box::new(YZRect{y0: 0.0, y1: 555.0, z0: 0.0, z1: 555.0, k: 0.0, material: red}),
+        ceiling_light(WIDE_LIGHT, light),
+        Box::new(XZRect{x0: 0.0, x1: 555.0, z0: 0.0, z1: 555.0, k: 555.0, material: white.clone()}),
+        Box::new(XZRect{x0: 0.0, x1: 555.0, z0: 0.0, z1: 555.0, k: 0.0, material: white.clone()}),
+        Box::new(XYRect{x0: 0.0, x1: 555.0, y0: 0.0, y1: 555.0, k: 555.0, material: white}),
+        Box::new(HeterogeneousMedium::new(Box::new(boundary), field, albedo)),
+    ])
+}
+
+/// Холмистая местность из треугольной сетки, высоты которой заданы шумом Перлина.
+pub fn terrain() -> World {
+    let noise = Perlin::new(0);
+    let cells = 128;
+    let size = 20.0;
+
+    let mut positions = vec![];
+    for j in 0..=cells {
+        for i in 0..=cells {
+            let x = size * (i as f32 / cells as f32 - 0.5);
+            let z = size * (j as f32 / cells as f32 - 0.5);
+            let y = 3.0 * noise.turb(Vec3::new(0.15 * x, 0.0, 0.15 * z), 5);
+            positions.push(Vec3::new(x, y, z));
+        }
+    }
+
+    let mut indices = vec![];
+    let row = cells + 1;
+    for j in 0..cells {
+        for i in 0..cells {
+            let v = j * row + i;
+            indices.push([v, v + row, v + 1]);
+            indices.push([v + 1, v + row, v + row + 1]);
+        }
+    }
+
+    let ground = Arc::new(Lambert::with_color([0.45, 0.55, 0.3].into()));
+    let mut mesh = MeshData::new(positions, indices, ground);
+    mesh.compute_normals();
+
+    World(vec![
+        Box::new(TriangleMesh::new(mesh)),
+        Box::new(Sphere{center: [0.0, 4.0, 0.0].into(), radius: 1.5, material: Arc::new(Metal::with_albedo_fuzz([0.8, 0.8, 0.8].into(), 0.05))}),
+    ])
+}
+
+/// Модель из файла OBJ, PLY или STL со светло-серым материалом по умолчанию.
+pub fn model(path: &str) -> Result<World, Box<dyn Error>> {
+    load_model(path, Arc::new(Lambert::with_color([0.73, 0.73, 0.73].into())))
+}
+
+/// Загружает модель из файла OBJ, PLY или STL. Материал `default_material` получают грани, для которых
+/// файл не задает своего материала; цвета вершин PLY умножаются на него.
+///
+/// Формат определяется по расширению, каждый объект OBJ становится отдельной сеткой.
+pub fn load_model(path: &str, default_material: Arc<dyn Material>) -> Result<World, Box<dyn Error>> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+
+    let objects = match extension.as_deref() {
+        Some("obj") => load_obj(path, default_material)?,
+        Some("ply") => vec![load_ply(path, default_material)?],
+        Some("stl") => vec![load_stl(path, default_material)?],
+        _ => return Err(format!("unsupported model format '{}'", path).into()),
+    };
+
+    // Пустая сцена не имеет ограничивающего параллелепипеда, и иерархию над ней не построить.
+    if objects.is_empty() {
+        return Err(format!("'{}' has no faces", path).into());
+    }
+
+    let meshes: Vec<Box<dyn Hittable>> = objects
+        .into_iter()
+        .map(|mesh| Box::new(TriangleMesh::new(mesh)) as Box<dyn Hittable>)
+        .collect();
+
+    Ok(World(meshes))
+}
+
+/// Число встроенных сцен; их номера начинаются с единицы.
+pub const BUILTIN_SCENE_COUNT: i32 = 11;
+
+/// Встроенная сцена с номером `number` вместе с камерой и настройками изображения.
+///
+/// Неизвестный номер означает случайную сцену с обложки первой книги.
+/// Случайные сцены строятся генератором `rng`, так что одно и то же зерно дает одну и ту же сцену.
+pub fn builtin_scene(number: i32, rng: &mut Pcg32) -> Result<Scene, Box<dyn Error>> {
+    // Сцены в коробке Корнелла освещены только лампой под потолком и смотрят на нее спереди.
+    // Копия лампы нужна только для выборки направлений на свет, поэтому ее материал не важен.
+    let cornell = |world: World, light| {
+        let mut scene = Scene::new(world);
+        scene.lights = World(vec![ceiling_light(light, Arc::new(DiffuseLight::with_color(color::BLACK)))]);
+        scene.aspect_ratio = 1.0;
+        scene.image_width = 600;
+        scene.samples_per_pixel = 200;
+        scene.background = Background::Solid(color::BLACK);
+        scene.camera.lookfrom = [278.0, 278.0, -800.0].into();
+        scene.camera.lookat = [278.0, 278.0, 0.0].into();
+        scene.camera.vfov = 40.0;
+        scene
+    };
+
+    let scene = match number {
+        2 => Scene::new(two_spheres()),
+        3 => Scene::new(two_perlin_spheres()),
+        4 => Scene::new(earth()?),
+        5 => {
+            let mut scene = Scene::new(simple_light());
+            scene.background = Background::Solid(color::BLACK);
+            scene.camera.lookfrom = [26.0, 3.0, 6.0].into();
+            scene.camera.lookat = [0.0, 2.0, 0.0].into();
+            scene
+        }
+        6 => cornell(cornell_box(), CORNELL_LIGHT),
+        7 => cornell(cornell_smoke(), WIDE_LIGHT),
+        8 => {
+            let mut scene = cornell(final_scene(rng)?, FINAL_LIGHT);
+            scene.image_width = 800;
+            scene.samples_per_pixel = 10000;
+            scene.camera.lookfrom = [478.0, 278.0, -600.0].into();
+            scene
+        }
+        9 => cornell(cornell_cloud(), WIDE_LIGHT),
+        10 => {
+            let mut scene = Scene::new(terrain());
+            scene.camera.lookfrom = [0.0, 9.0, 16.0].into();
+            scene.camera.lookat = [0.0, 1.5, 0.0].into();
+            scene.camera.vfov = 45.0;
+            scene
+        }
+        11 => {
+            let mut scene = Scene::new(model("model.obj")?);
+
+            // Камера смотрит на центр модели с расстояния, при котором модель целиком попадает в кадр.
+            if let Some(bbox) = scene.world.bounding_box(0.0, 1.0) {
+                let radius = 0.5 * (bbox.max - bbox.min).length();
+                let camera = &mut scene.camera;
+                camera.lookat = bbox.centroid();
+                camera.lookfrom =
+                    camera.lookat + 2.0 * radius * unit_vector(Vec3::new(0.6, 0.5, 1.0)) / deg_to_rad(0.5 * camera.vfov).tan();
+            }
+            scene
+        }
+        _ => {
+            let mut scene = Scene::new(random_scene(rng));
+            scene.camera.aperture = 0.1;
+            scene
+        }
+    };
+
+    Ok(scene)
+}
@@ -0,0 +1,238 @@
+use crate::geom::Vec3;
+use crate::materials::Material;
+use crate::mesh::MeshData;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+use std::{fmt, fs, io};
+
+/// Размер заголовка двоичного STL: 80 байт комментария и число треугольников.
+const BINARY_HEADER: usize = 84;
+/// Размер записи треугольника в двоичном STL: нормаль, три вершины и атрибут.
+const BINARY_TRIANGLE: usize = 50;
+
+/// Ошибка загрузки файла STL.
+#[derive(Debug)]
+pub enum StlError {
+    /// Ошибка ввода-вывода при чтении файла.
+    Io(io::Error),
+    /// Ошибка в строке `line` текстового файла.
+    Parse { line: usize, message: String },
+    /// Размер двоичного файла не соответствует числу треугольников.
+    Truncated,
+    /// В файле нет ни одного треугольника.
+    Empty,
+}
+
+impl Display for StlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Io(err) => write!(f, "I/O error: {}", err),
+            StlError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            StlError::Truncated => write!(f, "binary STL is truncated"),
+            StlError::Empty => write!(f, "STL file has no triangles"),
+        }
+    }
+}
+
+impl Error for StlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StlError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StlError {
+    fn from(err: io::Error) -> Self {
+        StlError::Io(err)
+    }
+}
+
+/// Собирает сетку из треугольников, объединяя вершины с одинаковыми координатами.
+///
+/// Нормали файла не используются: STL описывает граненые модели, поэтому сетка остается с плоским затенением.
+#[derive(Default)]
+struct MeshBuilder {
+    positions: Vec<Vec3>,
+    indices: Vec<[usize; 3]>,
+    lookup: HashMap<[u32; 3], usize>,
+}
+
+impl MeshBuilder {
+    fn vertex(&mut self, p: Vec3) -> usize {
+        let key = [p.0[0].to_bits(), p.0[1].to_bits(), p.0[2].to_bits()];
+        let positions = &mut self.positions;
+        *self.lookup.entry(key).or_insert_with(|| {
+            positions.push(p);
+            positions.len() - 1
+        })
+    }
+
+    fn triangle(&mut self, vertices: [Vec3; 3]) {
+        let face = [self.vertex(vertices[0]), self.vertex(vertices[1]), self.vertex(vertices[2])];
+        self.indices.push(face);
+    }
+
+    fn build(self, material: Arc<dyn Material>) -> MeshData {
+        MeshData::new(self.positions, self.indices, material)
+    }
+}
+
+/// Файл двоичный, если его размер точно соответствует числу треугольников из заголовка
+/// или если он не похож на текст, начинающийся с `solid`.
+///
+/// Проверки префикса `solid` недостаточно: многие программы пишут его и в заголовок двоичных файлов.
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() >= BINARY_HEADER {
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+        if data.len() == BINARY_HEADER + count * BINARY_TRIANGLE {
+            return true;
+        }
+    }
+    !data.starts_with(b"solid") || data.contains(&0)
+}
+
+fn parse_binary(data: &[u8], material: Arc<dyn Material>) -> Result<MeshData, StlError> {
+    if data.len() < BINARY_HEADER {
+        return Err(StlError::Truncated);
+    }
+    let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+    if data.len() < BINARY_HEADER + count * BINARY_TRIANGLE {
+        return Err(StlError::Truncated);
+    }
+
+    let float = |offset: usize| f32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+    let point = |offset: usize| Vec3::new(float(offset), float(offset + 4), float(offset + 8));
+
+    let mut builder = MeshBuilder::default();
+    for i in 0..count {
+        // Первые 12 байт записи - нормаль грани, она пропускается.
+        let offset = BINARY_HEADER + i * BINARY_TRIANGLE + 12;
+        builder.triangle([point(offset), point(offset + 12), point(offset + 24)]);
+    }
+
+    Ok(builder.build(material))
+}
+
+fn parse_ascii(source: &str, material: Arc<dyn Material>) -> Result<MeshData, StlError> {
+    let mut builder = MeshBuilder::default();
+    let mut vertices = vec![];
+    let mut in_loop = false;
+
+    for (index, line) in source.lines().enumerate() {
+        let error = |message: String| StlError::Parse { line: index + 1, message };
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words.first().cloned() {
+            Some("outer") => {
+                if in_loop {
+                    return Err(error(String::from("nested 'outer loop'")));
+                }
+                in_loop = true;
+                vertices.clear();
+            }
+            Some("vertex") => {
+                if !in_loop {
+                    return Err(error(String::from("'vertex' outside of 'outer loop'")));
+                }
+                if words.len() != 4 {
+                    return Err(error(String::from("expected 'vertex <x> <y> <z>'")));
+                }
+                let mut p = [0.0; 3];
+                for (value, word) in p.iter_mut().zip(&words[1..]) {
+                    *value = word.parse().map_err(|_| error(format!("invalid number '{}'", word)))?;
+                }
+                vertices.push(Vec3::from(p));
+            }
+            Some("endloop") => {
+                if vertices.len() != 3 {
+                    return Err(error(format!("facet with {} vertices", vertices.len())));
+                }
+                builder.triangle([vertices[0], vertices[1], vertices[2]]);
+                in_loop = false;
+            }
+            Some("solid") | Some("facet") | Some("endfacet") | Some("endsolid") | None => {}
+            Some(other) => return Err(error(format!("unknown keyword '{}'", other))),
+        }
+    }
+
+    if in_loop {
+        return Err(StlError::Parse { line: source.lines().count(), message: String::from("unterminated 'outer loop'") });
+    }
+
+    Ok(builder.build(material))
+}
+
+/// Разбирает сетку STL из памяти, определяя текстовый или двоичный формат по содержимому.
+pub fn parse_stl(data: &[u8], material: Arc<dyn Material>) -> Result<MeshData, StlError> {
+    let mesh = if is_binary(data) {
+        parse_binary(data, material)?
+    } else {
+        parse_ascii(&String::from_utf8_lossy(data), material)?
+    };
+    if mesh.indices.is_empty() {
+        return Err(StlError::Empty);
+    }
+
+    Ok(mesh)
+}
+
+/// Загружает сетку из файла STL. Все грани получают материал `material`.
+pub fn load_stl<P: AsRef<Path>>(path: P, material: Arc<dyn Material>) -> Result<MeshData, StlError> {
+    parse_stl(&fs::read(path)?, material)
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_stl;
+    use crate::geom::Vec3;
+    use crate::materials::Lambert;
+    use std::sync::Arc;
+
+    fn material() -> Arc<Lambert> {
+        Arc::new(Lambert::with_color([1.0, 1.0, 1.0].into()))
+    }
+
+    #[test]
+    fn check_ascii_stl_shares_vertices() {
+        let source = "solid quad\n\
+                      facet normal 0 0 1\n outer loop\n  vertex 0 0 0\n  vertex 1 0 0\n  vertex 1 1 0\n endloop\nendfacet\n\
+                      facet normal 0 0 1\n outer loop\n  vertex 0 0 0\n  vertex 1 1 0\n  vertex 0 1 0\n endloop\nendfacet\n\
+                      endsolid quad\n";
+        let mesh = parse_stl(source.as_bytes(), material()).unwrap();
+
+        assert_eq!(4, mesh.positions.len());
+        assert_eq!(vec![[0, 1, 2], [0, 2, 3]], mesh.indices);
+        assert!(mesh.normals.is_empty());
+
+        let broken = "solid\nfacet normal 0 0 1\nouter loop\nvertex 0 0 x\n";
+        assert_eq!("line 4: invalid number 'x'", parse_stl(broken.as_bytes(), material()).err().unwrap().to_string());
+    }
+
+    #[test]
+    fn check_binary_stl_with_solid_header() {
+        let mut data = b"solid but actually binary".to_vec();
+        data.resize(80, 0);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        for v in [0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 2.0, 0.0].iter() {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        data.extend_from_slice(&[0, 0]);
+
+        let mesh = parse_stl(&data, material()).unwrap();
+        assert_eq!(vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0)], mesh.positions);
+        assert_eq!(vec![[0, 1, 2]], mesh.indices);
+
+        data.truncate(100);
+        assert_eq!("binary STL is truncated", parse_stl(&data, material()).err().unwrap().to_string());
+
+        let mut empty = vec![0; 80];
+        empty.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!("STL file has no triangles", parse_stl(&empty, material()).err().unwrap().to_string());
+        assert_eq!("STL file has no triangles", parse_stl(b"solid empty\nendsolid empty\n", material()).err().unwrap().to_string());
+    }
+}
@@ -0,0 +1,193 @@
+use crate::color;
+use crate::geom::Vec3;
+use crate::image::Image;
+use crate::perlin::Perlin;
+use crate::ppm::{read_ppm, PpmError};
+use crate::tonemap::srgb_eotf;
+use crate::Point;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Типаж описывает цвет поверхности, зависящий от положения точки на ней.
+///
+/// Координаты `u` и `v` задают точку на развертке поверхности, `p` - точку в пространстве.
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f32, v: f32, p: Vec3) -> Vec3;
+}
+
+/// Текстура одного цвета.
+pub struct SolidColor {
+    pub color: Vec3,
+}
+
+impl SolidColor {
+    pub fn new(color: Vec3) -> Self {
+        SolidColor { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _: f32, _: f32, _: Vec3) -> Vec3 {
+        self.color
+    }
+}
+
+/// Пространственная шахматная текстура из чередующихся текстур `odd` и `even`.
+pub struct CheckerTexture {
+    pub odd: Arc<dyn Texture>,
+    pub even: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    /// Создает шахматную текстуру из двух цветов.
+    pub fn with_colors(even: Vec3, odd: Vec3) -> Self {
+        CheckerTexture {
+            odd: Arc::new(SolidColor::new(odd)),
+            even: Arc::new(SolidColor::new(even)),
+        }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f32, v: f32, p: Vec3) -> Vec3 {
+        let sines = (10.0 * p.x()).sin() * (10.0 * p.y()).sin() * (10.0 * p.z()).sin();
+
+        if sines < 0.0 {
+            self.odd.value(u, v, p)
+        } else {
+            self.even.value(u, v, p)
+        }
+    }
+}
+
+/// Способ построения узора из шума Перлина.
+#[derive(Copy, Clone, Debug)]
+pub enum NoisePattern {
+    /// Сглаженный шум, приведенный к диапазону `[0; 1]`.
+    Smooth,
+    /// Турбулентность - сумма нескольких октав шума.
+    Turbulence,
+    /// Мраморные прожилки: синусоида вдоль оси `Z`, фаза которой возмущена турбулентностью.
+    Marble,
+}
+
+/// Процедурная текстура на основе шума Перлина.
+pub struct NoiseTexture {
+    noise: Perlin,
+    scale: f32,
+    octaves: usize,
+    pattern: NoisePattern,
+}
+
+impl NoiseTexture {
+    /// Создает текстуру с семью октавами турбулентности.
+    pub fn new(seed: u64, scale: f32, pattern: NoisePattern) -> Self {
+        Self::with_octaves(seed, scale, pattern, 7)
+    }
+
+    /// Создает текстуру с заданным числом октав турбулентности.
+    pub fn with_octaves(seed: u64, scale: f32, pattern: NoisePattern, octaves: usize) -> Self {
+        NoiseTexture {
+            noise: Perlin::new(seed),
+            scale,
+            octaves,
+            pattern,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _: f32, _: f32, p: Vec3) -> Vec3 {
+        let intensity = match self.pattern {
+            NoisePattern::Smooth => 0.5 * (1.0 + self.noise.noise(self.scale * p)),
+            NoisePattern::Turbulence => self.noise.turb(self.scale * p, self.octaves),
+            NoisePattern::Marble => {
+                0.5 * (1.0 + (self.scale * p.z() + 10.0 * self.noise.turb(p, self.octaves)).sin())
+            }
+        };
+
+        intensity * color::WHITE
+    }
+}
+
+/// Текстура из изображения, натянутая на поверхность по координатам `(u, v)`.
+pub struct ImageTexture {
+    image: Image,
+}
+
+impl ImageTexture {
+    /// Создает текстуру из изображения в кодировке sRGB, например прочитанного из PPM.
+    ///
+    /// Компоненты переводятся в линейные, чтобы служить альбедо: иначе текстура выглядит блеклой.
+    pub fn new(mut image: Image) -> Self {
+        for pixel in &mut image.pixels {
+            for c in 0..3 {
+                pixel[c] = srgb_eotf(pixel[c]);
+            }
+        }
+        ImageTexture { image }
+    }
+
+    /// Загружает текстуру из файла PPM.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, PpmError> {
+        Ok(Self::new(read_ppm(path)?))
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f32, v: f32, _: Vec3) -> Vec3 {
+        // Без изображения текстура окрашивается в бирюзовый цвет, чтобы ошибку было видно.
+        if self.image.pixels.is_empty() {
+            return Vec3::new(0.0, 1.0, 1.0);
+        }
+
+        let u = u.clamp(0.0, 1.0);
+        // Строки изображения идут сверху вниз, а координата v растет снизу вверх.
+        let v = 1.0 - v.clamp(0.0, 1.0);
+
+        let i = ((u * self.image.width as f32) as usize).min(self.image.width - 1);
+        let j = ((v * self.image.height as f32) as usize).min(self.image.height - 1);
+
+        self.image.get(i, j)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CheckerTexture, ImageTexture, Texture};
+    use crate::geom::Vec3;
+    use crate::image::Image;
+    use crate::ppm::{parse_ppm, write_ppm};
+    use crate::tonemap::ToneMapping;
+
+    #[test]
+    fn check_checker_parity() {
+        let even = Vec3::new(1.0, 1.0, 1.0);
+        let odd = Vec3::new(0.0, 0.0, 0.0);
+        let checker = CheckerTexture::with_colors(even, odd);
+        let value = |x: f32, y: f32, z: f32| checker.value(0.0, 0.0, Vec3::new(x, y, z));
+
+        assert_eq!(even, value(0.05, 0.05, 0.05));
+        // Клетка меняет цвет при переходе через любую из плоскостей `10 * x = k * PI`.
+        assert_eq!(odd, value(-0.05, 0.05, 0.05));
+        assert_eq!(odd, value(0.05, 0.4, 0.05));
+        assert_eq!(even, value(-0.05, -0.05, 0.05));
+        assert_eq!(odd, value(-0.05, -0.05, -0.05));
+    }
+
+    #[test]
+    fn check_written_ppm_reads_back_as_linear_texture() {
+        let mut image = Image::new(2, 1);
+        image.pixels[0] = Vec3::new(0.25, 0.5, 0.0);
+        image.pixels[1] = Vec3::new(1.0, 0.05, 0.75);
+        let mut data = vec![];
+        write_ppm(&mut data, &image, &ToneMapping::default()).unwrap();
+        let texture = ImageTexture::new(parse_ppm(&data).unwrap());
+
+        // Квантование до 8 бит дает погрешность меньше половины шага в кодировке sRGB.
+        for (i, &u) in [0.25, 0.75].iter().enumerate() {
+            let value = texture.value(u, 0.5, Vec3::default());
+            assert!((value - image.pixels[i]).length() < 0.005, "{} != {}", value, image.pixels[i]);
+        }
+    }
+}
@@ -0,0 +1,378 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Значение ключа в документе TOML.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    String(String),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    /// Название типа значения для сообщений об ошибках.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Boolean(_) => "boolean",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+        }
+    }
+
+    /// Число с плавающей точкой; целые значения тоже принимаются.
+    pub fn as_float(&self) -> Option<f64> {
+        match *self {
+            Value::Integer(value) => Some(value as f64),
+            Value::Float(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Таблица пар ключ-значение в порядке их появления в файле.
+#[derive(Debug, Default)]
+pub struct Table {
+    /// Номер строки заголовка таблицы (0 для корневой таблицы).
+    pub line: usize,
+    entries: Vec<(String, Value, usize)>,
+}
+
+impl Table {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.iter().find(|(k, _, _)| k == key).map(|(_, value, _)| value)
+    }
+
+    /// Номер строки, в которой задан ключ `key`, или строка заголовка таблицы, если ключа нет.
+    pub fn line_of(&self, key: &str) -> usize {
+        self.entries
+            .iter()
+            .find(|(k, _, _)| k == key)
+            .map_or(self.line, |&(_, _, line)| line)
+    }
+
+    /// Ключи таблицы вместе с номерами строк.
+    pub fn keys(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.entries.iter().map(|(key, _, line)| (key.as_str(), *line))
+    }
+}
+
+/// Именованная таблица документа: `[name]` или элемент массива таблиц `[[name]]`.
+#[derive(Debug)]
+pub struct Section {
+    pub name: String,
+    pub array: bool,
+    pub table: Table,
+}
+
+/// Разобранный документ: корневая таблица и секции в порядке следования.
+#[derive(Debug, Default)]
+pub struct Document {
+    pub root: Table,
+    pub sections: Vec<Section>,
+}
+
+/// Синтаксическая ошибка в строке `line`.
+#[derive(Debug, PartialEq)]
+pub struct TomlError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for TomlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for TomlError {}
+
+/// Разбирает подмножество TOML, достаточное для файлов сцен.
+///
+/// Поддерживаются комментарии, таблицы `[name]`, массивы таблиц `[[name]]`, простые и строковые ключи,
+/// строки в двойных и одинарных кавычках, целые и дробные числа, логические значения и массивы,
+/// в том числе многострочные. Составные ключи через точку, встроенные таблицы и даты не поддерживаются.
+pub fn parse(source: &str) -> Result<Document, TomlError> {
+    Parser { chars: source.chars().collect(), pos: 0, line: 1 }.document()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn error<T>(&self, message: String) -> Result<T, TomlError> {
+        Err(TomlError { line: self.line, message })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Пропускает пробелы и комментарий до конца строки.
+    fn skip_spaces(&mut self) {
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\r' => {
+                    self.bump();
+                }
+                '#' => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Пропускает пробелы, комментарии и переводы строк.
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_spaces();
+            if !self.eat('\n') {
+                break;
+            }
+        }
+    }
+
+    /// После значения или заголовка в строке не должно быть ничего, кроме комментария.
+    fn end_of_line(&mut self) -> Result<(), TomlError> {
+        self.skip_spaces();
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => self.error(format!("unexpected '{}' at end of line", c)),
+        }
+    }
+
+    fn document(mut self) -> Result<Document, TomlError> {
+        let mut document = Document::default();
+
+        loop {
+            self.skip_blank();
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Ok(document),
+            };
+
+            if c == '[' {
+                let line = self.line;
+                self.bump();
+                let array = self.eat('[');
+                self.skip_spaces();
+                let name = self.key()?;
+                self.skip_spaces();
+                if !self.eat(']') || (array && !self.eat(']')) {
+                    return self.error(format!("expected '{}' after table name", if array { "]]" } else { "]" }));
+                }
+                let duplicate = document.sections.iter().any(|s| s.name == name && !(s.array && array));
+                if duplicate || name.is_empty() {
+                    return self.error(format!("duplicate or invalid table name '{}'", name));
+                }
+                self.end_of_line()?;
+
+                let table = Table { line, entries: vec![] };
+                document.sections.push(Section { name, array, table });
+            } else {
+                let line = self.line;
+                let key = self.key()?;
+                self.skip_spaces();
+                if !self.eat('=') {
+                    return self.error(format!("expected '=' after key '{}'", key));
+                }
+                self.skip_spaces();
+                let value = self.value()?;
+                self.end_of_line()?;
+
+                let table = match document.sections.last_mut() {
+                    Some(section) => &mut section.table,
+                    None => &mut document.root,
+                };
+                if table.get(&key).is_some() {
+                    return Err(TomlError { line, message: format!("duplicate key '{}'", key) });
+                }
+                table.entries.push((key, value, line));
+            }
+        }
+    }
+
+    fn key(&mut self) -> Result<String, TomlError> {
+        let key = match self.peek() {
+            Some('"') | Some('\'') => self.string()?,
+            _ => {
+                let mut key = String::new();
+                while let Some(c) = self.peek().filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-') {
+                    key.push(c);
+                    self.bump();
+                }
+                if key.is_empty() {
+                    return self.error(String::from("expected a key"));
+                }
+                key
+            }
+        };
+
+        if self.peek() == Some('.') {
+            return self.error(String::from("dotted keys are not supported"));
+        }
+        Ok(key)
+    }
+
+    fn value(&mut self) -> Result<Value, TomlError> {
+        match self.peek() {
+            Some('"') | Some('\'') => Ok(Value::String(self.string()?)),
+            Some('[') => self.array(),
+            Some(c) if c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.' => {
+                let mut word = String::new();
+                while let Some(c) = self.peek().filter(|c| c.is_ascii_alphanumeric() || "+-._".contains(*c)) {
+                    word.push(c);
+                    self.bump();
+                }
+                self.scalar(&word)
+            }
+            Some('\n') | None => self.error(String::from("missing value")),
+            Some(c) => self.error(format!("unexpected '{}'", c)),
+        }
+    }
+
+    fn scalar(&self, word: &str) -> Result<Value, TomlError> {
+        match word {
+            "true" => return Ok(Value::Boolean(true)),
+            "false" => return Ok(Value::Boolean(false)),
+            _ => {}
+        }
+
+        let digits = word.replace('_', "");
+        let is_float = digits.contains(['.', 'e', 'E']) || digits.ends_with("inf") || digits.ends_with("nan");
+        let parsed = if is_float {
+            digits.parse().ok().map(Value::Float)
+        } else {
+            digits.parse().ok().map(Value::Integer)
+        };
+
+        match parsed {
+            Some(value) if !word.starts_with('_') && !word.ends_with('_') => Ok(value),
+            _ => self.error(format!("invalid value '{}'", word)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, TomlError> {
+        let quote = self.bump().unwrap_or('"');
+        let mut result = String::new();
+
+        loop {
+            let c = match self.peek() {
+                Some(c) if c != '\n' => c,
+                _ => return self.error(String::from("unterminated string")),
+            };
+            self.bump();
+
+            match c {
+                c if c == quote => return Ok(result),
+                '\\' if quote == '"' => {
+                    let escaped = match self.bump() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        other => return self.error(format!("invalid escape '\\{}'", other.unwrap_or(' '))),
+                    };
+                    result.push(escaped);
+                }
+                c => result.push(c),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, TomlError> {
+        self.bump();
+        let mut items = vec![];
+
+        loop {
+            self.skip_blank();
+            if self.eat(']') {
+                return Ok(Value::Array(items));
+            }
+            if self.peek().is_none() {
+                return self.error(String::from("unterminated array"));
+            }
+
+            items.push(self.value()?);
+            self.skip_blank();
+            if !self.eat(',') && self.peek() != Some(']') {
+                return self.error(String::from("expected ',' or ']' in array"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, TomlError, Value};
+
+    #[test]
+    fn check_sections_and_values() {
+        let source = "title = \"demo\" # comment\n\n\
+                      [camera]\nvfov = 40\nlookfrom = [\n  1.5, -2, 3e1, # x y z\n]\n\
+                      [[object]]\nname = 'a\\b'\nvisible = true\n[[object]]\nname = \"q\\\"\"\n";
+        let document = parse(source).unwrap();
+
+        assert_eq!(Some(&Value::String(String::from("demo"))), document.root.get("title"));
+        assert_eq!(3, document.sections.len());
+
+        let camera = &document.sections[0];
+        assert_eq!(("camera", false, 3), (camera.name.as_str(), camera.array, camera.table.line));
+        assert_eq!(Some(&Value::Integer(40)), camera.table.get("vfov"));
+        assert_eq!(
+            Some(&Value::Array(vec![Value::Float(1.5), Value::Integer(-2), Value::Float(30.0)])),
+            camera.table.get("lookfrom")
+        );
+        assert_eq!(5, camera.table.line_of("lookfrom"));
+
+        assert_eq!(Some(&Value::String(String::from("a\\b"))), document.sections[1].table.get("name"));
+        assert_eq!(Some(&Value::Boolean(true)), document.sections[1].table.get("visible"));
+        assert_eq!(Some(&Value::String(String::from("q\""))), document.sections[2].table.get("name"));
+    }
+
+    #[test]
+    fn check_syntax_errors() {
+        let error = |source: &str| parse(source).err().unwrap();
+
+        assert_eq!(TomlError { line: 2, message: String::from("duplicate key 'a'") }, error("a = 1\na = 2"));
+        assert_eq!(TomlError { line: 1, message: String::from("invalid value '1.2.3'") }, error("a = 1.2.3"));
+        assert_eq!(TomlError { line: 3, message: String::from("unterminated array") }, error("a = [1,\n2,\n"));
+        assert_eq!(TomlError { line: 2, message: String::from("duplicate or invalid table name 't'") }, error("[t]\n[t]"));
+        assert_eq!(TomlError { line: 1, message: String::from("dotted keys are not supported") }, error("a.b = 1"));
+        assert_eq!(TomlError { line: 1, message: String::from("unterminated string") }, error("a = \"x\nb"));
+        assert_eq!(TomlError { line: 1, message: String::from("unexpected '2' at end of line") }, error("a = 1 2"));
+    }
+}
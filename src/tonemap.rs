@@ -0,0 +1,210 @@
+use crate::geom::Vec3;
+use crate::image::Image;
+use crate::utils::clamp;
+
+/// Оператор тональной компрессии: переводит яркость из `[0; ∞)` в диапазон экрана `[0; 1]`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ToneMapOperator {
+    /// Без компрессии: значения больше единицы просто ограничиваются.
+    Clamp,
+    /// Оператор Рейнхарда `L / (1 + L)` для яркости пикселя.
+    Reinhard,
+    /// Расширенный оператор Рейнхарда: яркость `white` и выше становится белым цветом.
+    ExtendedReinhard { white: f32 },
+    /// Кривая Джона Хейбла из Uncharted 2.
+    Hable,
+    /// Аппроксимация кривой ACES Кшиштофа Нарковича.
+    Aces,
+}
+
+impl ToneMapOperator {
+    /// Применяет оператор к линейному цвету.
+    pub fn apply(self, color: Vec3) -> Vec3 {
+        match self {
+            ToneMapOperator::Clamp => color,
+            ToneMapOperator::Reinhard => scale_luminance(color, |l| l / (1.0 + l)),
+            ToneMapOperator::ExtendedReinhard { white } => {
+                scale_luminance(color, |l| l * (1.0 + l / (white * white)) / (1.0 + l))
+            }
+            ToneMapOperator::Hable => {
+                // Белая точка и множитель экспозиции из исходной статьи.
+                const WHITE: f32 = 11.2;
+                const EXPOSURE_BIAS: f32 = 2.0;
+                let scale = 1.0 / hable(WHITE);
+                Vec3(color.0.map(|x| hable(EXPOSURE_BIAS * x) * scale))
+            }
+            ToneMapOperator::Aces => Vec3(color.0.map(|x| {
+                let x = x.max(0.0);
+                (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+            })),
+        }
+    }
+}
+
+fn hable(x: f32) -> f32 {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+    let x = x.max(0.0);
+    (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F
+}
+
+/// Относительная яркость по коэффициентам Rec. 709.
+pub fn luminance(color: Vec3) -> f32 {
+    0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2]
+}
+
+/// Сжимает яркость цвета функцией `curve`, сохраняя соотношение компонент.
+fn scale_luminance(color: Vec3, curve: impl Fn(f32) -> f32) -> Vec3 {
+    let l = luminance(color);
+    if l > 0.0 {
+        curve(l) / l * color
+    } else {
+        Vec3::default()
+    }
+}
+
+/// Передаточная функция sRGB (OETF): линейный участок около нуля и степень 1/2.4.
+pub fn srgb_oetf(x: f32) -> f32 {
+    if x <= 0.003_130_8 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Обратная передаточная функция sRGB (EOTF): переводит закодированное значение в линейное.
+pub fn srgb_eotf(x: f32) -> f32 {
+    if x <= 0.040_45 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Шум с треугольным распределением на `(-1; 1)`, который зависит только от номера значения.
+///
+/// Один и тот же кадр всегда квантуется одинаково.
+fn triangular_noise(index: u32) -> f32 {
+    let uniform = |seed: u32| {
+        // Хэш-функция lowbias32 Криса Веллонса.
+        let mut x = seed;
+        x ^= x >> 16;
+        x = x.wrapping_mul(0x7FEB_352D);
+        x ^= x >> 15;
+        x = x.wrapping_mul(0x846C_A68B);
+        x ^= x >> 16;
+        (x >> 8) as f32 / (1 << 24) as f32
+    };
+    uniform(index.wrapping_mul(2)) + uniform(index.wrapping_mul(2) + 1) - 1.0
+}
+
+/// Последовательность преобразований для вывода в 8-битные форматы:
+/// экспозиция, тональная компрессия, sRGB и квантование.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ToneMapping {
+    /// Экспозиция в ступенях: яркость умножается на `2^exposure`.
+    pub exposure: f32,
+    pub operator: ToneMapOperator,
+    /// Добавлять перед квантованием шум в пределах младшего разряда, чтобы на градиентах не было полос.
+    pub dither: bool,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping {
+            exposure: 0.0,
+            operator: ToneMapOperator::Clamp,
+            dither: false,
+        }
+    }
+}
+
+impl ToneMapping {
+    /// Цвет на экране в диапазоне `[0; 1]` до кодирования sRGB.
+    pub fn map(&self, color: Vec3) -> Vec3 {
+        let color = self.operator.apply(2f32.powf(self.exposure) * color);
+        Vec3(color.0.map(|x| x.clamp(0.0, 1.0)))
+    }
+
+    /// Компоненты цвета в 8-битном sRGB по три байта на пиксель.
+    pub fn quantize(&self, image: &Image) -> Vec<u8> {
+        let mut rgb = Vec::with_capacity(image.pixels.len() * 3);
+        for pixel in &image.pixels {
+            for &x in self.map(*pixel).0.iter() {
+                let noise = if self.dither {
+                    triangular_noise(rgb.len() as u32)
+                } else {
+                    0.0
+                };
+                rgb.push(clamp(u8::MAX as f32 * srgb_oetf(x) + 0.5 + noise));
+            }
+        }
+        rgb
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{srgb_eotf, srgb_oetf, triangular_noise, ToneMapOperator, ToneMapping};
+    use crate::geom::Vec3;
+    use crate::image::Image;
+
+    #[test]
+    fn check_operators() {
+        let grey = Vec3::new(1.0, 1.0, 1.0);
+        let map = |operator: ToneMapOperator, x: f32| operator.apply(x * grey)[0];
+
+        assert_eq!(4.0, map(ToneMapOperator::Clamp, 4.0));
+        assert!((map(ToneMapOperator::Reinhard, 1.0) - 0.5).abs() < 1e-6);
+        assert!((map(ToneMapOperator::ExtendedReinhard { white: 4.0 }, 4.0) - 1.0).abs() < 1e-6);
+        assert!((map(ToneMapOperator::Hable, 5.6) - 1.0).abs() < 1e-6);
+        assert!((map(ToneMapOperator::Aces, 1000.0) - 1.0).abs() < 0.04);
+
+        for operator in [ToneMapOperator::Reinhard, ToneMapOperator::Hable, ToneMapOperator::Aces] {
+            assert!(map(operator, 0.0).abs() < 1e-6);
+            assert!(map(operator, 0.5) < map(operator, 0.6), "{:?} is not monotonic", operator);
+        }
+
+        // Операторы по яркости сохраняют оттенок.
+        let red = ToneMapOperator::Reinhard.apply(Vec3::new(2.0, 1.0, 0.0));
+        assert!((red[0] - 2.0 * red[1]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn check_srgb_and_quantization() {
+        assert_eq!(0.0, srgb_oetf(0.0));
+        assert!((srgb_oetf(0.003_130_8) - 0.040_45).abs() < 1e-5);
+        assert!((srgb_oetf(0.214_041_14) - 0.5).abs() < 1e-5);
+        assert!((srgb_oetf(1.0) - 1.0).abs() < 1e-6);
+        for &x in &[0.0, 0.002, 0.003_130_8, 0.25, 0.5, 1.0] {
+            assert!((srgb_eotf(srgb_oetf(x)) - x).abs() < 1e-6, "{}", x);
+        }
+
+        let mut image = Image::new(2, 1);
+        image.pixels[0] = Vec3::new(0.25, 1.0, 8.0);
+        image.pixels[1] = Vec3::new(0.125, 0.0, -1.0);
+        assert_eq!(vec![137, 255, 255, 99, 0, 0], ToneMapping::default().quantize(&image));
+
+        let brighter = ToneMapping { exposure: 1.0, ..ToneMapping::default() };
+        assert_eq!(vec![188, 255, 255, 137, 0, 0], brighter.quantize(&image));
+    }
+
+    #[test]
+    fn check_dithering_keeps_mean() {
+        let noise: Vec<f32> = (0..10000).map(triangular_noise).collect();
+        let mean = noise.iter().sum::<f32>() / noise.len() as f32;
+        assert!(mean.abs() < 0.02);
+        assert!(noise.iter().all(|x| x.abs() < 1.0));
+
+        // Значение посередине между уровнями 0 и 1 квантуется в оба примерно поровну.
+        let mut image = Image::new(1000, 1);
+        image.pixels = vec![Vec3::new(0.5 / 255.0 / 12.92, 0.0, 0.0); 1000];
+        let dithered = ToneMapping { dither: true, ..ToneMapping::default() }.quantize(&image);
+        let ones = dithered.iter().step_by(3).filter(|&&x| x == 1).count();
+        assert!((400..600).contains(&ones), "{} ones", ones);
+    }
+}
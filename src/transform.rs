@@ -0,0 +1,276 @@
+use crate::aabb::{surrounding_box, Aabb};
+use crate::bodies::{HitRecord, Hittable};
+use crate::geom::{unit_vector, Mat4, Vec3};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::utils::deg_to_rad;
+
+/// Возвращает параллелепипед, охватывающий все восемь углов `bbox` после преобразования `f`.
+fn transform_box<F>(bbox: Aabb, f: F) -> Aabb
+where
+    F: Fn(Vec3) -> Vec3,
+{
+    let mut result = Aabb::empty();
+    for i in 0..8 {
+        let corner = Vec3::new(
+            if i & 1 == 0 { bbox.min[0] } else { bbox.max[0] },
+            if i & 2 == 0 { bbox.min[1] } else { bbox.max[1] },
+            if i & 4 == 0 { bbox.min[2] } else { bbox.max[2] },
+        );
+        let p = f(corner);
+        result = surrounding_box(result, Aabb::new(p, p));
+    }
+    result
+}
+
+/// Объект, перенесенный на вектор `offset`.
+pub struct Translate {
+    pub object: Box<dyn Hittable>,
+    pub offset: Vec3,
+}
+
+impl Translate {
+    pub fn new(object: Box<dyn Hittable>, offset: Vec3) -> Self {
+        Translate { object, offset }
+    }
+}
+
+impl Hittable for Translate {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        let moved = Ray::new(ray.origin() - self.offset, ray.direction(), ray.time());
+        let mut rec = self.object.hit(&moved, t_min, t_max, sampler)?;
+        rec.point += self.offset;
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        self.object
+            .bounding_box(time0, time1)
+            .map(|bbox| Aabb::new(bbox.min + self.offset, bbox.max + self.offset))
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        self.object.pdf_value(origin - self.offset, direction)
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.object.random(origin - self.offset, sampler)
+    }
+}
+
+/// Объект, повернутый вокруг оси `Y` на угол в градусах.
+pub struct RotateY {
+    object: Box<dyn Hittable>,
+    sin_theta: f32,
+    cos_theta: f32,
+    bbox: Option<Aabb>,
+}
+
+impl RotateY {
+    pub fn new(object: Box<dyn Hittable>, degrees: f32) -> Self {
+        let (sin_theta, cos_theta) = deg_to_rad(degrees).sin_cos();
+        let bbox = object.bounding_box(0.0, 1.0).map(|bbox| {
+            transform_box(bbox, |p| {
+                Vec3::new(cos_theta * p[0] + sin_theta * p[2], p[1], -sin_theta * p[0] + cos_theta * p[2])
+            })
+        });
+
+        RotateY { object, sin_theta, cos_theta, bbox }
+    }
+
+    /// Поворот из мировой системы координат в систему координат объекта.
+    fn to_object(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v[0] - self.sin_theta * v[2],
+            v[1],
+            self.sin_theta * v[0] + self.cos_theta * v[2],
+        )
+    }
+
+    /// Поворот из системы координат объекта в мировую систему координат.
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v[0] + self.sin_theta * v[2],
+            v[1],
+            -self.sin_theta * v[0] + self.cos_theta * v[2],
+        )
+    }
+}
+
+impl Hittable for RotateY {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        let rotated = Ray::new(self.to_object(ray.origin()), self.to_object(ray.direction()), ray.time());
+        let mut rec = self.object.hit(&rotated, t_min, t_max, sampler)?;
+        rec.point = self.to_world(rec.point);
+        rec.normal = self.to_world(rec.normal);
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _: f32, _: f32) -> Option<Aabb> {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        self.object.pdf_value(self.to_object(origin), self.to_object(direction))
+    }
+
+    fn random(&self, origin: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.to_world(self.object.random(self.to_object(origin), sampler))
+    }
+}
+
+/// Объект, к которому применено произвольное аффинное преобразование.
+///
+/// Луч переводится в систему координат объекта обратной матрицей без нормировки направления,
+/// поэтому параметр `t` точки попадания одинаков в обеих системах.
+/// Нормали преобразуются транспонированной обратной матрицей, что верно и при неравномерном масштабе.
+pub struct Transform {
+    object: Box<dyn Hittable>,
+    matrix: Mat4,
+    inverse: Mat4,
+    normal_matrix: Mat4,
+}
+
+impl Transform {
+    /// Создает преобразованный объект.
+    ///
+    /// Возвращает `None`, если матрица `matrix` вырождена.
+    pub fn new(object: Box<dyn Hittable>, matrix: Mat4) -> Option<Self> {
+        let inverse = matrix.inverse()?;
+
+        Some(Transform {
+            object,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+        })
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        let local = Ray::new(
+            self.inverse.transform_point(ray.origin()),
+            self.inverse.transform_vector(ray.direction()),
+            ray.time(),
+        );
+        let mut rec = self.object.hit(&local, t_min, t_max, sampler)?;
+        rec.point = self.matrix.transform_point(rec.point);
+        rec.normal = unit_vector(self.normal_matrix.transform_vector(rec.normal));
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        self.object
+            .bounding_box(time0, time1)
+            .map(|bbox| transform_box(bbox, |p| self.matrix.transform_point(p)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RotateY, Transform, Translate};
+    use crate::bodies::{BoxShape, Hittable, Sphere};
+    use crate::geom::{dot, unit_vector, Mat4, Vec3};
+    use crate::materials::Lambert;
+    use crate::ray::Ray;
+    use crate::sampler::IndependentSampler;
+    use std::sync::Arc;
+
+    fn unit_box() -> Box<dyn Hittable> {
+        Box::new(BoxShape::new(
+            [0.0, 0.0, 0.0].into(),
+            [1.0, 2.0, 3.0].into(),
+            Arc::new(Lambert::with_color([0.5, 0.5, 0.5].into())),
+        ))
+    }
+
+    fn rays() -> Vec<Ray> {
+        let mut rays = vec![];
+        for i in 0..20 {
+            for j in 0..20 {
+                let target = Vec3::new(-2.0 + 0.25 * i as f32, -1.0 + 0.2 * j as f32, 1.0);
+                let orig = Vec3::new(7.0, 3.0, 9.0);
+                rays.push(Ray::new(orig, target - orig, 0.0));
+            }
+        }
+        rays
+    }
+
+    fn assert_same_hits(a: &dyn Hittable, b: &dyn Hittable) {
+        let mut hits = 0;
+        for ray in rays() {
+            let ra = a.hit(&ray, 0.001, f32::MAX, &mut IndependentSampler::new(0));
+            let rb = b.hit(&ray, 0.001, f32::MAX, &mut IndependentSampler::new(0));
+            assert_eq!(ra.is_some(), rb.is_some());
+            if let (Some(ra), Some(rb)) = (ra, rb) {
+                hits += 1;
+                assert!((ra.t - rb.t).abs() < 1e-4);
+                assert!((ra.point - rb.point).length() < 1e-3);
+                assert!((ra.normal - rb.normal).length() < 1e-4);
+            }
+        }
+        assert!(hits > 0);
+    }
+
+    #[test]
+    fn check_transform_matches_translate() {
+        let offset = Vec3::new(0.5, -0.25, 1.0);
+
+        assert_same_hits(
+            &Translate::new(unit_box(), offset),
+            &Transform::new(unit_box(), Mat4::translation(offset)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn check_transform_matches_rotate_y() {
+        assert_same_hits(
+            &RotateY::new(unit_box(), 30.0),
+            &Transform::new(unit_box(), Mat4::rotation([0.0, 1.0, 0.0].into(), 30.0)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn check_scaled_sphere_normal() {
+        // Сфера, растянутая в эллипсоид с полуосями 2, 1, 1.
+        let sphere = Box::new(Sphere {
+            center: [0.0, 0.0, 0.0].into(),
+            radius: 1.0,
+            material: Arc::new(Lambert::with_color([0.5, 0.5, 0.5].into())),
+        });
+        let ellipsoid = Transform::new(sphere, Mat4::scaling([2.0, 1.0, 1.0].into())).unwrap();
+        let ray = Ray::new([1.0, 5.0, 0.0].into(), [0.0, -1.0, 0.0].into(), 0.0);
+        let rec = ellipsoid.hit(&ray, 0.001, f32::MAX, &mut IndependentSampler::new(0)).unwrap();
+
+        // Нормаль эллипсоида x²/4 + y² + z² = 1 направлена вдоль градиента (x/4, y, z).
+        let p = rec.point;
+        let expected = unit_vector(Vec3::new(p[0] / 4.0, p[1], p[2]));
+        assert!((rec.point[1] - 0.75f32.sqrt()).abs() < 1e-4);
+        assert!((dot(rec.normal, expected) - 1.0).abs() < 1e-4);
+
+        let bbox = ellipsoid.bounding_box(0.0, 1.0).unwrap();
+        assert_eq!(Vec3::new(-2.0, -1.0, -1.0), bbox.min);
+        assert_eq!(Vec3::new(2.0, 1.0, 1.0), bbox.max);
+    }
+
+    #[test]
+    fn check_matrix_inverse() {
+        let m = Mat4::translation([1.0, 2.0, 3.0].into())
+            * Mat4::rotation([1.0, 1.0, 0.0].into(), 40.0)
+            * Mat4::scaling([2.0, 0.5, 3.0].into());
+        let product = m * m.inverse().unwrap();
+        let identity = Mat4::identity();
+
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!((product.0[i][j] - identity.0[i][j]).abs() < 1e-5);
+            }
+        }
+        assert!(Mat4::scaling([1.0, 0.0, 1.0].into()).inverse().is_none());
+        assert!(Transform::new(unit_box(), Mat4::scaling([1.0, 0.0, 1.0].into())).is_none());
+    }
+}
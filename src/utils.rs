@@ -1,7 +1,5 @@
-use crate::geom::{dot, unit_vector, Vec3};
-
-use rand::distributions::Uniform;
-use rand::Rng;
+use crate::geom::Vec3;
+use crate::sampler::Sampler;
 
 pub fn clamp(value: f32) -> u8 {
     value.max(u8::MIN as f32).min(u8::MAX as f32) as u8
@@ -11,47 +9,49 @@ pub fn lerp(from: Vec3, to: Vec3, t: f32) -> Vec3 {
     (1.0 - t) * from + t * to
 }
 
-pub fn random_range(low: f32, high: f32) -> f32 {
-    rand::thread_rng().sample::<f32, _>(Uniform::new(low, high))
+/// Создает случайный вектор внутри единичной сферы: направление и радиус `∛ξ` дают равномерное распределение.
+///
+/// В отличие от метода исключения, всегда занимает ровно три измерения сэмпла.
+pub fn random_in_unit_sphere(sampler: &mut dyn Sampler) -> Vec3 {
+    let radius = sampler.next_1d().cbrt();
+    radius * random_unit_vector(sampler)
 }
 
-/// Создает случайный вектор внутри единичной сферы методом исключения.
-pub fn random_in_unit_sphere() -> Vec3 {
-    loop {
-        let x = random_range(-1.0, 1.0);
-        let y = random_range(-1.0, 1.0);
-        let z = random_range(-1.0, 1.0);
-        let p = Vec3::new(x, y, z);
-        if p.length_squared() <= 1.0 {
-            return p;
-        }
-    }
+/// Создает случайный единичный вектор, равномерно распределенный по сфере.
+pub fn random_unit_vector(sampler: &mut dyn Sampler) -> Vec3 {
+    let (u, v) = sampler.next_2d();
+    let z = 1.0 - 2.0 * u;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * std::f32::consts::PI * v;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
 }
 
-pub fn random_unit_vector() -> Vec3 {
-    unit_vector(random_in_unit_sphere())
-}
+/// Создает случайную точку единичного круга в плоскости `XY`.
+///
+/// Концентрическое отображение Ширли-Чиу переводит квадрат в круг без разрывов,
+/// поэтому равномерно расположенные сэмплы остаются равномерными и на линзе.
+pub fn random_in_unit_disk(sampler: &mut dyn Sampler) -> Vec3 {
+    let (u, v) = sampler.next_2d();
+    let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return Vec3::default();
+    }
 
-/// Создает случайный вектор внутри единичной полусферы в направлении нормали.
-pub fn random_in_hemisphere(normal: Vec3) -> Vec3 {
-    let in_unit_sphere = random_in_unit_sphere();
-    if dot(in_unit_sphere, normal) > 0.0 {
-        // In the same hemisphere as the normal
-        in_unit_sphere
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, std::f32::consts::FRAC_PI_4 * (b / a))
     } else {
-        -in_unit_sphere
-    }
+        (b, std::f32::consts::FRAC_PI_2 - std::f32::consts::FRAC_PI_4 * (a / b))
+    };
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
 }
 
-pub fn random_in_unit_disk() -> Vec3 {
-    loop {
-        let x = random_range(-1.0, 1.0);
-        let y = random_range(-1.0, 1.0);
-        let p: Vec3 = [x, y, 0.0].into();
-        if p.length_squared() < 1.0 {
-            return p;
-        }
-    }
+/// Создает случайное направление в полусфере `z > 0` с плотностью, пропорциональной косинусу угла с осью `Z`.
+///
+/// Точка единичного круга поднимается на полусферу (метод Малли).
+pub fn random_cosine_direction(sampler: &mut dyn Sampler) -> Vec3 {
+    let p = random_in_unit_disk(sampler);
+    let z = (1.0 - p[0] * p[0] - p[1] * p[1]).max(0.0).sqrt();
+    Vec3::new(p[0], p[1], z)
 }
 
 pub fn deg_to_rad(deg: f32) -> f32 {
@@ -0,0 +1,405 @@
+use crate::aabb::Aabb;
+use crate::bodies::{HitRecord, Hittable};
+use crate::geom::Vec3;
+use crate::materials::{Isotropic, Material};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::perlin::Perlin;
+use crate::texture::{SolidColor, Texture};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+use std::{fmt, fs, io};
+
+/// Отрезок луча `[t1; t2]` внутри выпуклой границы среды, обрезанный по `[t_min; t_max]`.
+fn boundary_interval(boundary: &dyn Hittable, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut dyn Sampler) -> Option<(f32, f32)> {
+    // Точки входа луча в среду и выхода из нее, в том числе позади начала луча.
+    let enter = boundary.hit(ray, f32::NEG_INFINITY, f32::INFINITY, sampler)?;
+    let exit = boundary.hit(ray, enter.t + 0.0001, f32::INFINITY, sampler)?;
+
+    let t1 = enter.t.max(t_min).max(0.0);
+    let t2 = exit.t.min(t_max);
+    if t1 >= t2 {
+        return None;
+    }
+
+    Some((t1, t2))
+}
+
+/// Параметры точки рассеяния внутри среды.
+fn medium_record(ray: &Ray, t: f32, phase_function: &Arc<dyn Material>) -> HitRecord {
+    // Нормаль и сторона поверхности внутри среды не имеют смысла и выбираются произвольно.
+    HitRecord {
+        t,
+        point: ray.at(t),
+        normal: Vec3::new(1.0, 0.0, 0.0),
+        u: 0.0,
+        v: 0.0,
+        material: phase_function.clone(),
+        front_face: true,
+        vertex_color: None,
+    }
+}
+
+/// Однородная рассеивающая среда (дым, туман) внутри выпуклой границы.
+///
+/// Вероятность рассеяния луча на отрезке длины `dL` равна `density * dL`,
+/// поэтому расстояние свободного пробега распределено экспоненциально.
+pub struct ConstantMedium {
+    boundary: Box<dyn Hittable>,
+    phase_function: Arc<dyn Material>,
+    neg_inv_density: f32,
+}
+
+impl ConstantMedium {
+    /// Создает среду с плотностью `density` и цветом, заданным текстурой.
+    pub fn new(boundary: Box<dyn Hittable>, density: f32, albedo: Arc<dyn Texture>) -> Self {
+        ConstantMedium {
+            boundary,
+            phase_function: Arc::new(Isotropic::new(albedo)),
+            neg_inv_density: -1.0 / density,
+        }
+    }
+
+    /// Создает среду с плотностью `density` одного цвета.
+    pub fn with_color(boundary: Box<dyn Hittable>, density: f32, color: Vec3) -> Self {
+        Self::new(boundary, density, Arc::new(SolidColor::new(color)))
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        let (t1, t2) = boundary_interval(self.boundary.as_ref(), ray, t_min, t_max, sampler)?;
+
+        let ray_length = ray.direction().length();
+        let distance_inside_boundary = (t2 - t1) * ray_length;
+        let hit_distance = self.neg_inv_density * sampler.next_1d().ln();
+        if hit_distance > distance_inside_boundary {
+            return None;
+        }
+
+        let t = t1 + hit_distance / ray_length;
+
+        Some(medium_record(ray, t, &self.phase_function))
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        self.boundary.bounding_box(time0, time1)
+    }
+}
+
+/// Типаж описывает плотность неоднородной среды в каждой точке пространства.
+pub trait DensityField: Send + Sync {
+    /// Плотность в точке `p`, не меньше нуля.
+    fn density(&self, p: Vec3) -> f32;
+
+    /// Верхняя граница плотности во всем поле (мажоранта).
+    fn max_density(&self) -> f32;
+}
+
+/// Поле плотности на основе турбулентности шума Перлина.
+pub struct NoiseDensity {
+    noise: Perlin,
+    scale: f32,
+    density: f32,
+    octaves: usize,
+}
+
+impl NoiseDensity {
+    /// Создает поле с плотностью `density * turb(scale * p)`, где турбулентность ограничена сверху единицей.
+    pub fn new(seed: u64, scale: f32, density: f32, octaves: usize) -> Self {
+        NoiseDensity { noise: Perlin::new(seed), scale, density, octaves }
+    }
+}
+
+impl DensityField for NoiseDensity {
+    fn density(&self, p: Vec3) -> f32 {
+        self.density * self.noise.turb(self.scale * p, self.octaves).min(1.0)
+    }
+
+    fn max_density(&self) -> f32 {
+        self.density
+    }
+}
+
+/// Ошибка загрузки воксельной сетки.
+#[derive(Debug)]
+pub enum VolumeError {
+    /// Ошибка ввода-вывода при чтении файла.
+    Io(io::Error),
+    /// Размер файла не совпадает с размером сетки.
+    SizeMismatch { expected: usize, actual: usize },
+    /// Нулевой размер сетки по одной из осей или размер, при котором число вокселей не помещается в `usize`.
+    InvalidSize([usize; 3]),
+    /// Число значений не совпадает с числом вокселей.
+    DataMismatch { expected: usize, actual: usize },
+}
+
+impl Display for VolumeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            VolumeError::Io(err) => write!(f, "I/O error: {}", err),
+            VolumeError::SizeMismatch { expected, actual } => {
+                write!(f, "expected {} bytes of voxel data, found {}", expected, actual)
+            }
+            VolumeError::InvalidSize(size) => write!(f, "invalid voxel grid size {}x{}x{}", size[0], size[1], size[2]),
+            VolumeError::DataMismatch { expected, actual } => {
+                write!(f, "expected {} voxel values, found {}", expected, actual)
+            }
+        }
+    }
+}
+
+impl Error for VolumeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            VolumeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for VolumeError {
+    fn from(err: io::Error) -> Self {
+        VolumeError::Io(err)
+    }
+}
+
+/// Плотная воксельная сетка плотностей, растянутая на параллелепипед `bounds`.
+///
+/// Значения хранятся по осям `x`, затем `y`, затем `z` и задают плотность в центрах вокселей.
+/// Между центрами плотность интерполируется трилинейно, вне параллелепипеда она равна нулю.
+pub struct VoxelGrid {
+    size: [usize; 3],
+    data: Vec<f32>,
+    bounds: Aabb,
+    max: f32,
+}
+
+/// Число вокселей в сетке размера `size`.
+fn voxel_count(size: [usize; 3]) -> Result<usize, VolumeError> {
+    if size.contains(&0) {
+        return Err(VolumeError::InvalidSize(size));
+    }
+
+    size[0]
+        .checked_mul(size[1])
+        .and_then(|n| n.checked_mul(size[2]))
+        .ok_or(VolumeError::InvalidSize(size))
+}
+
+impl VoxelGrid {
+    /// Создает сетку размера `nx * ny * nz` из массива значений.
+    ///
+    /// Отрицательные значения заменяются нулем.
+    pub fn new(size: [usize; 3], data: Vec<f32>, bounds: Aabb) -> Result<Self, VolumeError> {
+        let expected = voxel_count(size)?;
+        if data.len() != expected {
+            return Err(VolumeError::DataMismatch { expected, actual: data.len() });
+        }
+        let data: Vec<f32> = data.into_iter().map(|d| d.max(0.0)).collect();
+        let max = data.iter().cloned().fold(0.0, f32::max);
+
+        Ok(VoxelGrid { size, data, bounds, max })
+    }
+
+    /// Загружает сетку из двоичного файла без заголовка: `nx * ny * nz` чисел `f32` little-endian.
+    pub fn load<P: AsRef<Path>>(path: P, size: [usize; 3], bounds: Aabb) -> Result<Self, VolumeError> {
+        let expected = voxel_count(size)?.checked_mul(4).ok_or(VolumeError::InvalidSize(size))?;
+        let bytes = fs::read(path)?;
+        if bytes.len() != expected {
+            return Err(VolumeError::SizeMismatch { expected, actual: bytes.len() });
+        }
+
+        let data = bytes
+            .chunks(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+
+        Self::new(size, data, bounds)
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f32 {
+        self.data[(z * self.size[1] + y) * self.size[0] + x]
+    }
+}
+
+impl DensityField for VoxelGrid {
+    fn density(&self, p: Vec3) -> f32 {
+        let mut index = [0usize; 3];
+        let mut frac = [0.0f32; 3];
+
+        for a in 0..3 {
+            if p[a] < self.bounds.min[a] || p[a] > self.bounds.max[a] {
+                return 0.0;
+            }
+            // Координата в единицах вокселей относительно центра первого вокселя.
+            let extent = self.bounds.max[a] - self.bounds.min[a];
+            let g = ((p[a] - self.bounds.min[a]) / extent * self.size[a] as f32 - 0.5)
+                .max(0.0)
+                .min((self.size[a] - 1) as f32);
+            index[a] = (g as usize).min(self.size[a].saturating_sub(2));
+            frac[a] = g - index[a] as f32;
+        }
+
+        let mut accum = 0.0;
+        for corner in 0..8 {
+            let mut weight = 1.0;
+            let mut c = [0usize; 3];
+            for a in 0..3 {
+                let bit = (corner >> a) & 1;
+                c[a] = (index[a] + bit).min(self.size[a] - 1);
+                weight *= if bit == 1 { frac[a] } else { 1.0 - frac[a] };
+            }
+            accum += weight * self.voxel(c[0], c[1], c[2]);
+        }
+
+        accum
+    }
+
+    fn max_density(&self) -> f32 {
+        self.max
+    }
+}
+
+/// Неоднородная рассеивающая среда внутри выпуклой границы.
+///
+/// Точка рассеяния выбирается дельта-трекингом (Woodcock tracking): луч движется шагами,
+/// распределенными как в однородной среде с плотностью-мажорантой, и в каждой точке
+/// рассеивается с вероятностью `density / max_density`. Оценка получается несмещенной.
+pub struct HeterogeneousMedium {
+    boundary: Box<dyn Hittable>,
+    field: Arc<dyn DensityField>,
+    phase_function: Arc<dyn Material>,
+    majorant: f32,
+}
+
+impl HeterogeneousMedium {
+    pub fn new(boundary: Box<dyn Hittable>, field: Arc<dyn DensityField>, albedo: Arc<dyn Texture>) -> Self {
+        let majorant = field.max_density();
+
+        HeterogeneousMedium {
+            boundary,
+            field,
+            phase_function: Arc::new(Isotropic::new(albedo)),
+            majorant,
+        }
+    }
+}
+
+impl Hittable for HeterogeneousMedium {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        let (t1, t2) = boundary_interval(self.boundary.as_ref(), ray, t_min, t_max, sampler)?;
+        if self.majorant <= 0.0 {
+            return None;
+        }
+
+        let step = 1.0 / (self.majorant * ray.direction().length());
+        let mut t = t1;
+        loop {
+            t -= (1.0 - sampler.next_1d()).ln() * step;
+            if t >= t2 {
+                return None;
+            }
+            if sampler.next_1d() * self.majorant < self.field.density(ray.at(t)) {
+                return Some(medium_record(ray, t, &self.phase_function));
+            }
+        }
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        self.boundary.bounding_box(time0, time1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConstantMedium, DensityField, HeterogeneousMedium, VolumeError, VoxelGrid};
+    use crate::aabb::Aabb;
+    use crate::bodies::{BoxShape, Hittable};
+    use crate::geom::Vec3;
+    use crate::materials::Lambert;
+    use crate::ray::Ray;
+    use crate::sampler::IndependentSampler;
+    use crate::texture::SolidColor;
+    use std::sync::Arc;
+
+    fn unit_cube() -> Box<dyn Hittable> {
+        Box::new(BoxShape::new(
+            [0.0, 0.0, 0.0].into(),
+            [1.0, 1.0, 1.0].into(),
+            Arc::new(Lambert::with_color([0.5, 0.5, 0.5].into())),
+        ))
+    }
+
+    /// Сетка, в которой плотность равна 2 в левой половине и 0.5 в правой.
+    fn two_layer_grid() -> VoxelGrid {
+        let mut data = vec![];
+        for _z in 0..2 {
+            for _y in 0..2 {
+                data.extend_from_slice(&[2.0, 2.0, 0.5, 0.5]);
+            }
+        }
+        VoxelGrid::new([4, 2, 2], data, Aabb::new([0.0, 0.0, 0.0].into(), [1.0, 1.0, 1.0].into())).unwrap()
+    }
+
+    #[test]
+    fn check_voxel_grid_interpolation() {
+        let grid = two_layer_grid();
+
+        assert_eq!(2.0, grid.max_density());
+        assert_eq!(2.0, grid.density(Vec3::new(0.1, 0.5, 0.5)));
+        assert_eq!(0.5, grid.density(Vec3::new(0.9, 0.5, 0.5)));
+        assert!((grid.density(Vec3::new(0.5, 0.5, 0.5)) - 1.25).abs() < 1e-5);
+        assert_eq!(0.0, grid.density(Vec3::new(1.5, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn check_voxel_grid_size_errors() {
+        let bounds = Aabb::new([0.0, 0.0, 0.0].into(), [1.0, 1.0, 1.0].into());
+
+        let err = VoxelGrid::new([2, 2, 2], vec![1.0; 7], bounds).err().unwrap();
+        assert!(matches!(err, VolumeError::DataMismatch { expected: 8, actual: 7 }));
+        assert!(matches!(VoxelGrid::new([2, 0, 2], vec![], bounds), Err(VolumeError::InvalidSize([2, 0, 2]))));
+        // Размер проверяется до чтения файла, поэтому несуществующий путь не мешает.
+        let huge = [usize::MAX / 2, 4, 1];
+        assert!(matches!(VoxelGrid::load("missing.raw", huge, bounds), Err(VolumeError::InvalidSize(_))));
+    }
+
+    #[test]
+    fn check_constant_medium_hit_probability() {
+        let n = 40000;
+        let sampler = &mut IndependentSampler::new(3);
+
+        // Длина направления не должна влиять на вероятность, а `t_max` укорачивает отрезок внутри среды.
+        for &(density, speed, t_max, distance) in &[(0.5, 1.0, f32::MAX, 1.0), (2.0, 2.0, f32::MAX, 1.0), (2.0, 1.0, 1.5, 0.5)] {
+            let medium = ConstantMedium::with_color(unit_cube(), density, Vec3::new(1.0, 1.0, 1.0));
+            let ray = Ray::new([-1.0, 0.5, 0.5].into(), [speed, 0.0, 0.0].into(), 0.0);
+            let hits: Vec<f32> = (0..n).filter_map(|_| medium.hit(&ray, 0.001, t_max, sampler)).map(|rec| rec.point[0]).collect();
+
+            let expected = 1.0 - (-density * distance).exp();
+            let actual = hits.len() as f32 / n as f32;
+            assert!((actual - expected).abs() < 0.01, "density {}: {} != {}", density, actual, expected);
+            assert!(hits.iter().all(|&x| (0.0..=distance).contains(&x)));
+        }
+    }
+
+    #[test]
+    fn check_tracking_is_unbiased() {
+        // Плотность вдоль луча линейно падает с 2 до 0.5 на отрезке [0.375; 0.625].
+        let grid = Arc::new(two_layer_grid());
+        let optical_depth = 2.0 * 0.375 + 1.25 * 0.25 + 0.5 * 0.375;
+        let expected = (-optical_depth as f32).exp();
+
+        let medium = HeterogeneousMedium::new(unit_cube(), grid, Arc::new(SolidColor::new(Vec3::new(1.0, 1.0, 1.0))));
+        let ray = Ray::new([-1.0, 0.5, 0.5].into(), [1.0, 0.0, 0.0].into(), 0.0);
+        let n = 40000;
+        let sampler = &mut IndependentSampler::new(5);
+
+        let passed = (0..n).filter(|_| medium.hit(&ray, 0.001, f32::MAX, sampler).is_none()).count();
+        let delta = passed as f32 / n as f32;
+
+        assert!((delta - expected).abs() < 0.01, "{} != {}", delta, expected);
+    }
+}